@@ -0,0 +1,63 @@
+use std::str::FromStr;
+
+use crc::{Crc, CRC_32_ISO_HDLC};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use pngme::chunk::Chunk;
+use pngme::chunk_type::ChunkType;
+use pngme::png::{CrcMode, ParseOptions, Png};
+
+const SIZES: [usize; 3] = [1 << 10, 1 << 20, 64 << 20];
+
+/// The original checksum: a fresh byte-wise engine and a concatenated copy of type and data.
+fn baseline_checksum(chunk_type: &ChunkType, data: &[u8]) -> u32 {
+    let bytes: Vec<u8> = chunk_type
+        .bytes()
+        .iter()
+        .chain(data.iter())
+        .copied()
+        .collect();
+    Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(bytes.as_slice())
+}
+
+fn idat(size: usize) -> Chunk {
+    let data = (0..size).map(|i| (i * 31 % 251) as u8).collect();
+    Chunk::new(ChunkType::from_str("IDAT").unwrap(), data)
+}
+
+fn bench_checksum(c: &mut Criterion) {
+    let mut group = c.benchmark_group("crc");
+    group.sample_size(10);
+    for size in SIZES {
+        let chunk = idat(size);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::new("baseline", size), &chunk, |b, chunk| {
+            b.iter(|| baseline_checksum(chunk.chunk_type(), black_box(chunk.data())))
+        });
+        group.bench_with_input(BenchmarkId::new("slice16", size), &chunk, |b, chunk| {
+            b.iter(|| black_box(chunk).computed_crc())
+        });
+    }
+    group.finish();
+}
+
+fn bench_parse_crc_modes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_crc_mode");
+    group.sample_size(10);
+    for size in SIZES {
+        let bytes = Png::from_chunks(vec![idat(size)]).as_bytes();
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        for (name, crc) in [("strict", CrcMode::Strict), ("defer", CrcMode::Defer)] {
+            let options = ParseOptions {
+                crc,
+                ..Default::default()
+            };
+            group.bench_with_input(BenchmarkId::new(name, size), &bytes, |b, bytes| {
+                b.iter(|| Png::parse_with(black_box(bytes), options).unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_checksum, bench_parse_crc_modes);
+criterion_main!(benches);
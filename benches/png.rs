@@ -0,0 +1,98 @@
+use std::str::FromStr;
+
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+};
+use pngme::chunk::Chunk;
+use pngme::chunk_type::ChunkType;
+use pngme::png::Png;
+
+const DICE: &[u8] = include_bytes!("../tests/fixtures/dice.png");
+
+fn chunk(chunk_type: &str, data: Vec<u8>) -> Chunk {
+    Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+}
+
+/// Many small text chunks, with the one being searched for at the very end.
+fn many_chunks() -> Vec<u8> {
+    let mut chunks: Vec<Chunk> = (0..10_000)
+        .map(|i| chunk("tEXt", format!("Comment\0chunk number {}", i).into_bytes()))
+        .collect();
+    chunks.push(chunk("RuSt", b"needle".to_vec()));
+    chunks.push(chunk("IEND", vec![]));
+    Png::from_chunks(chunks).as_bytes()
+}
+
+/// A 256 MiB image payload split into 8 MiB IDAT chunks.
+fn large() -> Vec<u8> {
+    let mut chunks: Vec<Chunk> = (0..32)
+        .map(|i| chunk("IDAT", vec![i as u8; 8 << 20]))
+        .collect();
+    chunks.push(chunk("RuSt", b"needle".to_vec()));
+    chunks.push(chunk("IEND", vec![]));
+    Png::from_chunks(chunks).as_bytes()
+}
+
+fn inputs() -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        ("small", DICE.to_vec()),
+        ("many_chunks", many_chunks()),
+        ("large", large()),
+    ]
+}
+
+fn bench_png(c: &mut Criterion) {
+    let mut group = c.benchmark_group("png");
+    group.sample_size(10);
+    for (name, bytes) in inputs() {
+        let png = Png::try_from(bytes.as_ref()).unwrap();
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("try_from", name), &bytes, |b, bytes| {
+            b.iter(|| Png::try_from(black_box(bytes.as_slice())).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("as_bytes", name), &png, |b, png| {
+            b.iter(|| black_box(png).as_bytes())
+        });
+        group.bench_with_input(BenchmarkId::new("chunk_by_type", name), &png, |b, png| {
+            b.iter(|| black_box(png).chunk_by_type("RuSt").unwrap())
+        });
+        group.bench_with_input(
+            BenchmarkId::new("remove_chunk", name),
+            &bytes,
+            |b, bytes| {
+                b.iter_batched(
+                    || Png::try_from(bytes.as_slice()).unwrap(),
+                    |mut png| {
+                        png.remove_chunk("RuSt").unwrap();
+                        png
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_chunk_new(c: &mut Criterion) {
+    let mut group = c.benchmark_group("chunk_new");
+    for size in [0, 1 << 10, 1 << 20, 64 << 20] {
+        let data = vec![0x5a; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        if size >= 1 << 20 {
+            group.sample_size(10);
+        }
+        group.bench_with_input(BenchmarkId::from_parameter(size), &data, |b, data| {
+            b.iter_batched(
+                || data.clone(),
+                |data| chunk("IDAT", data),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_png, bench_chunk_new);
+criterion_main!(benches);
@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pngme::chunk::Chunk;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(chunk) = Chunk::try_from(data) {
+        let bytes = chunk.as_bytes();
+        assert_eq!(bytes, data[..bytes.len()]);
+        assert_eq!(Chunk::try_from(bytes.as_slice()).unwrap(), chunk);
+    }
+});
@@ -0,0 +1,16 @@
+#![no_main]
+
+use std::str::FromStr;
+
+use libfuzzer_sys::fuzz_target;
+use pngme::chunk_type::ChunkType;
+
+fuzz_target!(|data: [u8; 4]| {
+    if let Ok(chunk_type) = ChunkType::try_from(data) {
+        assert_eq!(chunk_type.bytes(), data);
+        assert_eq!(
+            ChunkType::from_str(&chunk_type.to_string()).unwrap(),
+            chunk_type
+        );
+    }
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pngme::exif::ExifData;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(exif) = ExifData::parse(data) {
+        // Whatever we accept must serialise to something we accept again.
+        ExifData::parse(&exif.to_bytes()).unwrap();
+    }
+});
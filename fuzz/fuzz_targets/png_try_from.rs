@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pngme::png::{ParseOptions, Png};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(png) = Png::try_from(data) {
+        assert_eq!(png.as_bytes(), data);
+    }
+    if let Ok((png, _)) = Png::parse_with(data, ParseOptions::repair()) {
+        let bytes = png.as_bytes();
+        let reparsed = Png::try_from(bytes.as_slice()).expect("repaired output must parse");
+        assert_eq!(reparsed, png);
+    }
+});
@@ -0,0 +1,1114 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::chunk_type::ChunkType;
+use crate::codec::{ChunkData, CodecError};
+use crate::exif;
+use crate::limits::Limits;
+use crate::png::PngError;
+use crate::zlib;
+
+fn expect_length(data: &[u8], length: usize) -> Result<(), CodecError> {
+    if data.len() == length {
+        Ok(())
+    } else {
+        Err(CodecError::InvalidLength)
+    }
+}
+
+fn u16_at(data: &[u8], i: usize) -> u16 {
+    u16::from_be_bytes([data[i], data[i + 1]])
+}
+
+fn u32_at(data: &[u8], i: usize) -> u32 {
+    u32::from_be_bytes(data[i..i + 4].try_into().unwrap())
+}
+
+pub(crate) fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| *b as char).collect()
+}
+
+/// Encodes `s` as Latin-1, failing on characters beyond U+00FF.
+pub(crate) fn latin1_bytes(s: &str) -> Result<Vec<u8>, CodecError> {
+    s.chars()
+        .map(|c| u8::try_from(c).map_err(|_| CodecError::InvalidValue))
+        .collect()
+}
+
+/// Encodes a keyword, which must be 1-79 Latin-1 characters without nulls.
+pub(crate) fn keyword_bytes(s: &str) -> Result<Vec<u8>, CodecError> {
+    match latin1_bytes(s) {
+        Ok(bytes) if (1..=79).contains(&bytes.len()) && !bytes.contains(&0) => Ok(bytes),
+        _ => Err(CodecError::InvalidKeyword),
+    }
+}
+
+/// Splits a null-terminated Latin-1 keyword (1-79 bytes) off the front of `data`.
+pub(crate) fn split_keyword(data: &[u8]) -> Result<(String, &[u8]), CodecError> {
+    match data.iter().position(|b| *b == 0) {
+        Some(n) if (1..=79).contains(&n) => Ok((latin1(&data[..n]), &data[n + 1..])),
+        _ => Err(CodecError::InvalidKeyword),
+    }
+}
+
+/// Image gamma, scaled by 100000.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Gama {
+    pub gamma: u32,
+}
+
+impl Gama {
+    /// Scales `gamma`, which must be positive and fit in the chunk once scaled.
+    pub fn from_value(gamma: f64) -> Result<Gama, CodecError> {
+        let scaled = (gamma * 100_000.0).round();
+        if gamma > 0.0 && scaled <= u32::MAX as f64 {
+            Ok(Gama {
+                gamma: scaled as u32,
+            })
+        } else {
+            Err(CodecError::InvalidValue)
+        }
+    }
+    pub fn value(&self) -> f64 {
+        self.gamma as f64 / 100_000.0
+    }
+}
+
+impl ChunkData for Gama {
+    const CHUNK_TYPE: ChunkType = ChunkType::gAMA;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        expect_length(data, 4)?;
+        Ok(Gama {
+            gamma: u32_at(data, 0),
+        })
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        Ok(self.gamma.to_be_bytes().to_vec())
+    }
+}
+
+impl Display for Gama {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "gamma {:.5}", self.value())
+    }
+}
+
+/// Primary chromaticities and white point as (x, y) pairs, scaled by 100000.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Chrm {
+    pub white: (u32, u32),
+    pub red: (u32, u32),
+    pub green: (u32, u32),
+    pub blue: (u32, u32),
+}
+
+impl Chrm {
+    /// Builds the chunk from white, red, green and blue (x, y) pairs in that order, each of
+    /// which must lie in 0.0..=1.0.
+    pub fn from_values(values: [f64; 8]) -> Result<Chrm, CodecError> {
+        if !values.iter().all(|x| (0.0..=1.0).contains(x)) {
+            return Err(CodecError::InvalidValue);
+        }
+        let scaled = |i: usize| (values[i] * 100_000.0).round() as u32;
+        Ok(Chrm {
+            white: (scaled(0), scaled(1)),
+            red: (scaled(2), scaled(3)),
+            green: (scaled(4), scaled(5)),
+            blue: (scaled(6), scaled(7)),
+        })
+    }
+}
+
+impl ChunkData for Chrm {
+    const CHUNK_TYPE: ChunkType = ChunkType::cHRM;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        expect_length(data, 32)?;
+        let pair = |i: usize| (u32_at(data, i), u32_at(data, i + 4));
+        Ok(Chrm {
+            white: pair(0),
+            red: pair(8),
+            green: pair(16),
+            blue: pair(24),
+        })
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        Ok([self.white, self.red, self.green, self.blue]
+            .iter()
+            .flat_map(|(x, y)| x.to_be_bytes().into_iter().chain(y.to_be_bytes()))
+            .collect())
+    }
+}
+
+impl Display for Chrm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let xy = |(x, y): (u32, u32)| format!("({:.5}, {:.5})", x as f64 / 1e5, y as f64 / 1e5);
+        write!(
+            f,
+            "white {}, red {}, green {}, blue {}",
+            xy(self.white),
+            xy(self.red),
+            xy(self.green),
+            xy(self.blue)
+        )
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RenderingIntent {
+    Perceptual,
+    RelativeColorimetric,
+    Saturation,
+    AbsoluteColorimetric,
+}
+
+impl FromStr for RenderingIntent {
+    type Err = CodecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "perceptual" => Ok(RenderingIntent::Perceptual),
+            "relative" => Ok(RenderingIntent::RelativeColorimetric),
+            "saturation" => Ok(RenderingIntent::Saturation),
+            "absolute" => Ok(RenderingIntent::AbsoluteColorimetric),
+            _ => Err(CodecError::InvalidValue),
+        }
+    }
+}
+
+/// Standard RGB colour space with a rendering intent.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Srgb {
+    pub intent: RenderingIntent,
+}
+
+impl ChunkData for Srgb {
+    const CHUNK_TYPE: ChunkType = ChunkType::sRGB;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        expect_length(data, 1)?;
+        let intent = match data[0] {
+            0 => RenderingIntent::Perceptual,
+            1 => RenderingIntent::RelativeColorimetric,
+            2 => RenderingIntent::Saturation,
+            3 => RenderingIntent::AbsoluteColorimetric,
+            _ => return Err(CodecError::InvalidValue),
+        };
+        Ok(Srgb { intent })
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        Ok(vec![self.intent as u8])
+    }
+}
+
+impl Display for Srgb {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "rendering intent {:?}", self.intent)
+    }
+}
+
+/// An embedded ICC profile; the profile stays zlib-compressed.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Iccp {
+    pub name: String,
+    pub compressed_profile: Vec<u8>,
+}
+
+impl Iccp {
+    /// Compresses `profile` for embedding under `name`.
+    pub fn from_profile(name: &str, profile: &[u8]) -> Iccp {
+        Iccp {
+            name: name.to_string(),
+            compressed_profile: zlib::deflate(profile),
+        }
+    }
+    /// Inflates the embedded ICC profile.
+    pub fn profile(&self, limits: &Limits) -> Result<Vec<u8>, PngError> {
+        zlib::inflate(&self.compressed_profile, limits)
+    }
+}
+
+impl ChunkData for Iccp {
+    const CHUNK_TYPE: ChunkType = ChunkType::iCCP;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        let (name, rest) = split_keyword(data)?;
+        match rest.split_first() {
+            Some((0, profile)) => Ok(Iccp {
+                name,
+                compressed_profile: profile.to_vec(),
+            }),
+            Some(_) => Err(CodecError::InvalidValue),
+            None => Err(CodecError::InvalidLength),
+        }
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = keyword_bytes(&self.name)?;
+        bytes.extend([0, 0]);
+        bytes.extend_from_slice(&self.compressed_profile);
+        Ok(bytes)
+    }
+}
+
+impl Display for Iccp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "profile \"{}\", {} bytes compressed",
+            self.name,
+            self.compressed_profile.len()
+        )
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PhysUnit {
+    Unknown,
+    Metre,
+}
+
+/// Physical pixel dimensions.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Phys {
+    pub pixels_per_unit_x: u32,
+    pub pixels_per_unit_y: u32,
+    pub unit: PhysUnit,
+}
+
+impl Phys {
+    const METRES_PER_INCH: f64 = 0.0254;
+
+    pub fn from_dpi(x: f64, y: f64) -> Phys {
+        let ppm = |dpi: f64| (dpi / Phys::METRES_PER_INCH).round() as u32;
+        Phys {
+            pixels_per_unit_x: ppm(x),
+            pixels_per_unit_y: ppm(y),
+            unit: PhysUnit::Metre,
+        }
+    }
+    /// Horizontal and vertical dots per inch, if the unit is known.
+    pub fn dpi(&self) -> Option<(f64, f64)> {
+        match self.unit {
+            PhysUnit::Metre => Some((
+                self.pixels_per_unit_x as f64 * Phys::METRES_PER_INCH,
+                self.pixels_per_unit_y as f64 * Phys::METRES_PER_INCH,
+            )),
+            PhysUnit::Unknown => None,
+        }
+    }
+}
+
+impl ChunkData for Phys {
+    const CHUNK_TYPE: ChunkType = ChunkType::pHYs;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        expect_length(data, 9)?;
+        let unit = match data[8] {
+            0 => PhysUnit::Unknown,
+            1 => PhysUnit::Metre,
+            _ => return Err(CodecError::InvalidValue),
+        };
+        Ok(Phys {
+            pixels_per_unit_x: u32_at(data, 0),
+            pixels_per_unit_y: u32_at(data, 4),
+            unit,
+        })
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = self.pixels_per_unit_x.to_be_bytes().to_vec();
+        bytes.extend(self.pixels_per_unit_y.to_be_bytes());
+        bytes.push(self.unit as u8);
+        Ok(bytes)
+    }
+}
+
+impl Display for Phys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.unit {
+            PhysUnit::Metre => write!(
+                f,
+                "{}x{} pixels per metre",
+                self.pixels_per_unit_x, self.pixels_per_unit_y
+            ),
+            PhysUnit::Unknown => write!(
+                f,
+                "pixel aspect ratio {}:{}",
+                self.pixels_per_unit_x, self.pixels_per_unit_y
+            ),
+        }
+    }
+}
+
+/// Significant bits per channel; how many channels depends on the colour type.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Sbit(pub Vec<u8>);
+
+impl ChunkData for Sbit {
+    const CHUNK_TYPE: ChunkType = ChunkType::sBIT;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        if !(1..=4).contains(&data.len()) {
+            return Err(CodecError::InvalidLength);
+        }
+        if data.iter().any(|x| !(1..=16).contains(x)) {
+            return Err(CodecError::InvalidValue);
+        }
+        Ok(Sbit(data.to_vec()))
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        Ok(self.0.clone())
+    }
+}
+
+impl Display for Sbit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bits: Vec<String> = self.0.iter().map(|x| x.to_string()).collect();
+        write!(f, "significant bits {}", bits.join(", "))
+    }
+}
+
+/// Background colour; the layout is determined by the data length.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Bkgd {
+    Index(u8),
+    Grey(u16),
+    Rgb(u16, u16, u16),
+}
+
+impl ChunkData for Bkgd {
+    const CHUNK_TYPE: ChunkType = ChunkType::bKGD;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        match data.len() {
+            1 => Ok(Bkgd::Index(data[0])),
+            2 => Ok(Bkgd::Grey(u16_at(data, 0))),
+            6 => Ok(Bkgd::Rgb(u16_at(data, 0), u16_at(data, 2), u16_at(data, 4))),
+            _ => Err(CodecError::InvalidLength),
+        }
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        Ok(match *self {
+            Bkgd::Index(i) => vec![i],
+            Bkgd::Grey(g) => g.to_be_bytes().to_vec(),
+            Bkgd::Rgb(r, g, b) => [r, g, b].iter().flat_map(|x| x.to_be_bytes()).collect(),
+        })
+    }
+}
+
+impl Display for Bkgd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Bkgd::Index(i) => write!(f, "background palette index {}", i),
+            Bkgd::Grey(g) => write!(f, "background grey {}", g),
+            Bkgd::Rgb(r, g, b) => write!(f, "background rgb({}, {}, {})", r, g, b),
+        }
+    }
+}
+
+/// Raw transparency data; interpreting it needs the colour type from `IHDR`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Trns(pub Vec<u8>);
+
+impl ChunkData for Trns {
+    const CHUNK_TYPE: ChunkType = ChunkType::tRNS;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        Ok(Trns(data.to_vec()))
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        Ok(self.0.clone())
+    }
+}
+
+impl Display for Trns {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} bytes of transparency data", self.0.len())
+    }
+}
+
+/// Palette histogram: approximate usage frequency of each palette entry.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Hist(pub Vec<u16>);
+
+impl ChunkData for Hist {
+    const CHUNK_TYPE: ChunkType = ChunkType::hIST;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        if !data.len().is_multiple_of(2) {
+            return Err(CodecError::InvalidLength);
+        }
+        Ok(Hist(data.chunks(2).map(|x| u16_at(x, 0)).collect()))
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        Ok(self.0.iter().flat_map(|x| x.to_be_bytes()).collect())
+    }
+}
+
+impl Display for Hist {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} histogram entries", self.0.len())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SpltEntry {
+    pub red: u16,
+    pub green: u16,
+    pub blue: u16,
+    pub alpha: u16,
+    pub frequency: u16,
+}
+
+/// A named suggested palette.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Splt {
+    pub name: String,
+    pub sample_depth: u8,
+    pub entries: Vec<SpltEntry>,
+}
+
+impl ChunkData for Splt {
+    const CHUNK_TYPE: ChunkType = ChunkType::sPLT;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        let (name, rest) = split_keyword(data)?;
+        let (sample_depth, rest) = rest.split_first().ok_or(CodecError::InvalidLength)?;
+        let entry_size = match sample_depth {
+            8 => 6,
+            16 => 10,
+            _ => return Err(CodecError::InvalidValue),
+        };
+        if !rest.len().is_multiple_of(entry_size) {
+            return Err(CodecError::InvalidLength);
+        }
+        let entries = rest
+            .chunks(entry_size)
+            .map(|x| {
+                let sample = |i: usize| {
+                    if entry_size == 6 {
+                        x[i] as u16
+                    } else {
+                        u16_at(x, i * 2)
+                    }
+                };
+                SpltEntry {
+                    red: sample(0),
+                    green: sample(1),
+                    blue: sample(2),
+                    alpha: sample(3),
+                    frequency: u16_at(x, entry_size - 2),
+                }
+            })
+            .collect();
+        Ok(Splt {
+            name,
+            sample_depth: *sample_depth,
+            entries,
+        })
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = keyword_bytes(&self.name)?;
+        bytes.extend([0, self.sample_depth]);
+        for entry in self.entries.iter() {
+            for sample in [entry.red, entry.green, entry.blue, entry.alpha] {
+                if self.sample_depth == 8 {
+                    bytes.push(sample as u8);
+                } else {
+                    bytes.extend(sample.to_be_bytes());
+                }
+            }
+            bytes.extend(entry.frequency.to_be_bytes());
+        }
+        Ok(bytes)
+    }
+}
+
+impl Display for Splt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "palette \"{}\", {} entries, {}-bit samples",
+            self.name,
+            self.entries.len(),
+            self.sample_depth
+        )
+    }
+}
+
+/// Last-modification time in UTC.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Time {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl Time {
+    /// The current system time.
+    pub fn now() -> Time {
+        let since_epoch = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        Time::from_unix(since_epoch.as_secs())
+    }
+    /// Converts seconds since the Unix epoch to a UTC date and time.
+    pub fn from_unix(secs: u64) -> Time {
+        // Civil-from-days conversion, counting eras of 400 years from 0000-03-01.
+        let days = secs / 86_400 + 719_468;
+        let era = days / 146_097;
+        let day_of_era = days % 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + (month <= 2) as u64;
+
+        let secs_of_day = secs % 86_400;
+        Time {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (secs_of_day / 3600) as u8,
+            minute: (secs_of_day / 60 % 60) as u8,
+            second: (secs_of_day % 60) as u8,
+        }
+    }
+    /// Checks every field, including the day against the length of the month.
+    pub fn is_valid(&self) -> bool {
+        let leap = self.year.is_multiple_of(4)
+            && (!self.year.is_multiple_of(100) || self.year.is_multiple_of(400));
+        let days_in_month = match self.month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if leap => 29,
+            2 => 28,
+            _ => return false,
+        };
+        (1..=days_in_month).contains(&self.day)
+            && self.hour <= 23
+            && self.minute <= 59
+            // 60 allows for leap seconds.
+            && self.second <= 60
+    }
+}
+
+impl ChunkData for Time {
+    const CHUNK_TYPE: ChunkType = ChunkType::tIME;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        expect_length(data, 7)?;
+        let time = Time {
+            year: u16_at(data, 0),
+            month: data[2],
+            day: data[3],
+            hour: data[4],
+            minute: data[5],
+            second: data[6],
+        };
+        if time.is_valid() {
+            Ok(time)
+        } else {
+            Err(CodecError::InvalidValue)
+        }
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = self.year.to_be_bytes().to_vec();
+        bytes.extend([self.month, self.day, self.hour, self.minute, self.second]);
+        Ok(bytes)
+    }
+}
+
+impl Display for Time {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// Raw EXIF data in TIFF layout.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Exif(pub Vec<u8>);
+
+impl ChunkData for Exif {
+    const CHUNK_TYPE: ChunkType = ChunkType::eXIf;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        match exif::byte_order(data) {
+            Ok(_) => Ok(Exif(data.to_vec())),
+            Err(_) => Err(CodecError::InvalidValue),
+        }
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        Ok(self.0.clone())
+    }
+}
+
+impl Display for Exif {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let byte_order = match self.0.get(0..4) {
+            Some(b"MM\0*") => "big-endian",
+            Some(b"II*\0") => "little-endian",
+            _ => "unknown byte order",
+        };
+        write!(f, "{} bytes of EXIF data, {}", self.0.len(), byte_order)
+    }
+}
+
+/// Coding-independent code points (ITU-T H.273).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Cicp {
+    pub colour_primaries: u8,
+    pub transfer_function: u8,
+    pub matrix_coefficients: u8,
+    pub video_full_range: bool,
+}
+
+impl ChunkData for Cicp {
+    const CHUNK_TYPE: ChunkType = ChunkType::cICP;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        expect_length(data, 4)?;
+        let video_full_range = match data[3] {
+            0 => false,
+            1 => true,
+            _ => return Err(CodecError::InvalidValue),
+        };
+        Ok(Cicp {
+            colour_primaries: data[0],
+            transfer_function: data[1],
+            matrix_coefficients: data[2],
+            video_full_range,
+        })
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        Ok(vec![
+            self.colour_primaries,
+            self.transfer_function,
+            self.matrix_coefficients,
+            self.video_full_range as u8,
+        ])
+    }
+}
+
+impl Display for Cicp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "primaries {}, transfer {}, matrix {}, {} range",
+            self.colour_primaries,
+            self.transfer_function,
+            self.matrix_coefficients,
+            if self.video_full_range {
+                "full"
+            } else {
+                "narrow"
+            }
+        )
+    }
+}
+
+/// Mastering display colour volume. Chromaticities are in units of 0.00002, luminance in 0.0001 cd/m².
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Mdcv {
+    pub primaries: [(u16, u16); 3],
+    pub white_point: (u16, u16),
+    pub max_luminance: u32,
+    pub min_luminance: u32,
+}
+
+impl ChunkData for Mdcv {
+    const CHUNK_TYPE: ChunkType = ChunkType::mDCv;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        expect_length(data, 24)?;
+        let pair = |i: usize| (u16_at(data, i), u16_at(data, i + 2));
+        Ok(Mdcv {
+            primaries: [pair(0), pair(4), pair(8)],
+            white_point: pair(12),
+            max_luminance: u32_at(data, 16),
+            min_luminance: u32_at(data, 20),
+        })
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        let mut bytes: Vec<u8> = self
+            .primaries
+            .iter()
+            .chain([self.white_point].iter())
+            .flat_map(|(x, y)| x.to_be_bytes().into_iter().chain(y.to_be_bytes()))
+            .collect();
+        bytes.extend(self.max_luminance.to_be_bytes());
+        bytes.extend(self.min_luminance.to_be_bytes());
+        Ok(bytes)
+    }
+}
+
+impl Display for Mdcv {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let xy = |(x, y): (u16, u16)| format!("({:.4}, {:.4})", x as f64 * 2e-5, y as f64 * 2e-5);
+        let primaries: Vec<String> = self.primaries.iter().map(|x| xy(*x)).collect();
+        write!(
+            f,
+            "primaries {}, white {}, luminance {:.4}-{:.4} cd/m2",
+            primaries.join(" "),
+            xy(self.white_point),
+            self.min_luminance as f64 * 1e-4,
+            self.max_luminance as f64 * 1e-4
+        )
+    }
+}
+
+/// Content light level information in units of 0.0001 cd/m².
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Clli {
+    pub max_content_light_level: u32,
+    pub max_frame_average_light_level: u32,
+}
+
+impl ChunkData for Clli {
+    const CHUNK_TYPE: ChunkType = ChunkType::cLLi;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        expect_length(data, 8)?;
+        Ok(Clli {
+            max_content_light_level: u32_at(data, 0),
+            max_frame_average_light_level: u32_at(data, 4),
+        })
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = self.max_content_light_level.to_be_bytes().to_vec();
+        bytes.extend(self.max_frame_average_light_level.to_be_bytes());
+        Ok(bytes)
+    }
+}
+
+impl Display for Clli {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "max content light level {:.4} cd/m2, max frame average {:.4} cd/m2",
+            self.max_content_light_level as f64 * 1e-4,
+            self.max_frame_average_light_level as f64 * 1e-4
+        )
+    }
+}
+
+/// Uncompressed Latin-1 text with a keyword.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Text {
+    pub keyword: String,
+    pub text: String,
+}
+
+impl ChunkData for Text {
+    const CHUNK_TYPE: ChunkType = ChunkType::tEXt;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        let (keyword, text) = split_keyword(data)?;
+        Ok(Text {
+            keyword,
+            text: latin1(text),
+        })
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = keyword_bytes(&self.keyword)?;
+        bytes.push(0);
+        bytes.extend(latin1_bytes(&self.text)?);
+        Ok(bytes)
+    }
+}
+
+impl Display for Text {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.keyword, self.text)
+    }
+}
+
+/// International text; `text` holds the zlib stream when `compressed` is set.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Itxt {
+    pub keyword: String,
+    pub compressed: bool,
+    pub language_tag: String,
+    pub translated_keyword: String,
+    pub text: Vec<u8>,
+}
+
+impl Itxt {
+    /// Uncompressed text without language information.
+    pub fn new(keyword: &str, text: &str) -> Itxt {
+        Itxt {
+            keyword: keyword.to_string(),
+            compressed: false,
+            language_tag: String::new(),
+            translated_keyword: String::new(),
+            text: text.as_bytes().to_vec(),
+        }
+    }
+    /// The text, inflated if needed.
+    pub fn text(&self, limits: &Limits) -> Result<String, PngError> {
+        let text = if self.compressed {
+            zlib::inflate(&self.text, limits)?
+        } else {
+            self.text.clone()
+        };
+        String::from_utf8(text).map_err(|_| PngError::InvalidChunk)
+    }
+}
+
+impl ChunkData for Itxt {
+    const CHUNK_TYPE: ChunkType = ChunkType::iTXt;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        let (keyword, rest) = split_keyword(data)?;
+        let (compressed, rest) = match rest {
+            [0, 0, rest @ ..] => (false, rest),
+            [1, 0, rest @ ..] => (true, rest),
+            [_, _, ..] => return Err(CodecError::InvalidValue),
+            _ => return Err(CodecError::InvalidLength),
+        };
+        let mut fields = rest.splitn(3, |b| *b == 0);
+        let mut field = || match fields.next() {
+            Some(x) => Ok(x),
+            None => Err(CodecError::InvalidLength),
+        };
+        let language_tag = field()?;
+        let translated_keyword = field()?;
+        let text = field()?;
+        let utf8 = |x: &[u8]| String::from_utf8(x.to_vec()).map_err(|_| CodecError::InvalidValue);
+        Ok(Itxt {
+            keyword,
+            compressed,
+            language_tag: utf8(language_tag)?,
+            translated_keyword: utf8(translated_keyword)?,
+            text: text.to_vec(),
+        })
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = keyword_bytes(&self.keyword)?;
+        bytes.extend([0, self.compressed as u8, 0]);
+        bytes.extend(self.language_tag.as_bytes());
+        bytes.push(0);
+        bytes.extend(self.translated_keyword.as_bytes());
+        bytes.push(0);
+        bytes.extend(&self.text);
+        Ok(bytes)
+    }
+}
+
+impl Display for Itxt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match std::str::from_utf8(&self.text) {
+            Ok(text) if !self.compressed => write!(f, "{}: {}", self.keyword, text),
+            _ => write!(f, "{}: {} bytes compressed", self.keyword, self.text.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<T: ChunkData + PartialEq>(data: &[u8]) -> T {
+        let value = T::from_bytes(data).unwrap();
+        assert_eq!(value.to_bytes().unwrap(), data);
+        value
+    }
+
+    #[test]
+    fn test_dice_chunks() {
+        // The sRGB, gAMA and pHYs chunks of the dice image.
+        let srgb: Srgb = round_trip(&[0]);
+        assert_eq!(srgb.intent, RenderingIntent::Perceptual);
+
+        let gama: Gama = round_trip(&[0, 0, 177, 143]);
+        assert_eq!(gama.gamma, 45455);
+
+        let phys: Phys = round_trip(&[0, 0, 14, 194, 0, 0, 14, 194, 1]);
+        assert_eq!(phys.pixels_per_unit_x, 3778);
+        assert_eq!(phys.unit, PhysUnit::Metre);
+        assert_eq!(phys.to_string(), "3778x3778 pixels per metre");
+    }
+
+    #[test]
+    fn test_colour_helpers() {
+        assert_eq!(Gama::from_value(1.0 / 2.2), Ok(Gama { gamma: 45455 }));
+        for gamma in [0.0, -0.45, f64::NAN, f64::INFINITY, 50_000.0] {
+            assert_eq!(Gama::from_value(gamma), Err(CodecError::InvalidValue));
+        }
+        let values = [0.3127, 0.329, 0.64, 0.33, 0.3, 0.6, 0.15, 0.06];
+        let chrm = Chrm::from_values(values).unwrap();
+        assert_eq!(chrm.white, (31270, 32900));
+        assert_eq!(chrm.blue, (15000, 6000));
+        for x in [-0.1, 1.5, f64::NAN, f64::INFINITY] {
+            let mut values = values;
+            values[3] = x;
+            assert_eq!(Chrm::from_values(values), Err(CodecError::InvalidValue));
+        }
+        assert_eq!(
+            RenderingIntent::from_str("relative"),
+            Ok(RenderingIntent::RelativeColorimetric)
+        );
+        assert!(RenderingIntent::from_str("other").is_err());
+
+        let iccp = Iccp::from_profile("test", b"profile bytes");
+        let iccp: Iccp = round_trip(&iccp.to_bytes().unwrap());
+        assert_eq!(
+            iccp.profile(&Limits::default()).unwrap(),
+            b"profile bytes".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_phys_dpi() {
+        let phys = Phys::from_dpi(72.0, 300.0);
+        assert_eq!(phys.pixels_per_unit_x, 2835);
+        assert_eq!(phys.pixels_per_unit_y, 11811);
+        let (x, y) = phys.dpi().unwrap();
+        assert_eq!((x.round(), y.round()), (72.0, 300.0));
+
+        let phys = Phys {
+            unit: PhysUnit::Unknown,
+            ..phys
+        };
+        assert_eq!(phys.dpi(), None);
+    }
+
+    #[test]
+    fn test_time_from_unix() {
+        assert_eq!(Time::from_unix(0).to_string(), "1970-01-01 00:00:00 UTC");
+        assert_eq!(
+            Time::from_unix(951_782_400).to_string(),
+            "2000-02-29 00:00:00 UTC"
+        );
+        assert_eq!(
+            Time::from_unix(1_709_251_199).to_string(),
+            "2024-02-29 23:59:59 UTC"
+        );
+        assert!(Time::now().is_valid());
+    }
+
+    #[test]
+    fn test_fixed_layout_chunks() {
+        let chrm: Chrm = round_trip(&(0..32).collect::<Vec<u8>>());
+        assert_eq!(chrm.white, (0x00010203, 0x04050607));
+
+        let cicp: Cicp = round_trip(&[1, 13, 0, 1]);
+        assert!(cicp.video_full_range);
+        assert_eq!(
+            Cicp::from_bytes(&[1, 13, 0, 2]),
+            Err(CodecError::InvalidValue)
+        );
+
+        let mdcv: Mdcv = round_trip(&(0..24).collect::<Vec<u8>>());
+        assert_eq!(mdcv.white_point, (0x0c0d, 0x0e0f));
+
+        let clli: Clli = round_trip(&[0, 0, 0, 10, 0, 0, 0, 5]);
+        assert_eq!(clli.max_frame_average_light_level, 5);
+
+        let time: Time = round_trip(&[7, 232, 2, 29, 23, 59, 60]);
+        assert_eq!(time.to_string(), "2024-02-29 23:59:60 UTC");
+        assert_eq!(
+            Time::from_bytes(&[7, 232, 13, 1, 0, 0, 0]),
+            Err(CodecError::InvalidValue)
+        );
+        assert_eq!(
+            Time::from_bytes(&[7, 231, 2, 29, 0, 0, 0]),
+            Err(CodecError::InvalidValue)
+        );
+        assert_eq!(
+            Time::from_bytes(&[7, 232, 4, 31, 0, 0, 0]),
+            Err(CodecError::InvalidValue)
+        );
+        assert_eq!(Gama::from_bytes(&[0, 0, 1]), Err(CodecError::InvalidLength));
+    }
+
+    #[test]
+    fn test_variable_layout_chunks() {
+        assert_eq!(round_trip::<Bkgd>(&[3]), Bkgd::Index(3));
+        assert_eq!(round_trip::<Bkgd>(&[0, 9]), Bkgd::Grey(9));
+        assert_eq!(round_trip::<Bkgd>(&[0, 1, 0, 2, 0, 3]), Bkgd::Rgb(1, 2, 3));
+        assert_eq!(Bkgd::from_bytes(&[0, 1, 2]), Err(CodecError::InvalidLength));
+
+        assert_eq!(round_trip::<Sbit>(&[5, 6, 5]), Sbit(vec![5, 6, 5]));
+        assert_eq!(round_trip::<Hist>(&[0, 1, 1, 0]), Hist(vec![1, 256]));
+        assert_eq!(
+            round_trip::<Trns>(&[255, 0]).to_string(),
+            "2 bytes of transparency data"
+        );
+        assert_eq!(
+            round_trip::<Exif>(b"MM\0*\0\0\0\x08").to_string(),
+            "8 bytes of EXIF data, big-endian"
+        );
+    }
+
+    #[test]
+    fn test_keyword_chunks() {
+        let text: Text = round_trip(b"Comment\0caf\xe9");
+        assert_eq!(text.keyword, "Comment");
+        assert_eq!(text.text, "café");
+        assert_eq!(
+            Text::from_bytes(b"\0empty keyword"),
+            Err(CodecError::InvalidKeyword)
+        );
+        let text = |keyword: &str, text: &str| Text {
+            keyword: keyword.to_string(),
+            text: text.to_string(),
+        };
+        assert_eq!(
+            text("Comment", "€").to_bytes(),
+            Err(CodecError::InvalidValue)
+        );
+        assert_eq!(
+            text("Ωmega", "").to_bytes(),
+            Err(CodecError::InvalidKeyword)
+        );
+        assert_eq!(text("", "").to_bytes(), Err(CodecError::InvalidKeyword));
+        assert_eq!(
+            Iccp::from_profile("日本", &[]).to_chunk(),
+            Err(CodecError::InvalidKeyword)
+        );
+
+        let itxt: Itxt = round_trip("Title\0\0\0ko\0제목\0안녕".as_bytes());
+        assert_eq!(itxt.language_tag, "ko");
+        assert_eq!(itxt.translated_keyword, "제목");
+        assert_eq!(itxt.text(&Limits::default()).unwrap(), "안녕");
+        let itxt = Itxt {
+            compressed: true,
+            text: zlib::deflate("compressed".as_bytes()),
+            ..Itxt::new("Comment", "")
+        };
+        let itxt: Itxt = round_trip(&itxt.to_bytes().unwrap());
+        assert_eq!(itxt.text(&Limits::default()).unwrap(), "compressed");
+        assert_eq!(
+            Itxt::from_bytes(b"Comment\0\0\0en"),
+            Err(CodecError::InvalidLength)
+        );
+
+        let iccp: Iccp = round_trip(b"sRGB\0\0\x78\x9c");
+        assert_eq!(iccp.name, "sRGB");
+        assert_eq!(iccp.compressed_profile, vec![0x78, 0x9c]);
+
+        let splt: Splt = round_trip(b"pal\0\x08\x01\x02\x03\x04\x00\x05");
+        assert_eq!(
+            splt.entries,
+            vec![SpltEntry {
+                red: 1,
+                green: 2,
+                blue: 3,
+                alpha: 4,
+                frequency: 5
+            }]
+        );
+        let splt: Splt = round_trip(b"pal\0\x10\x00\x01\x00\x02\x00\x03\x00\x04\x00\x05");
+        assert_eq!(splt.entries[0].alpha, 4);
+    }
+}
@@ -0,0 +1,569 @@
+use std::fmt::Display;
+
+use derive_more::{Display, Error};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::codec::{ChunkData, CodecError};
+use crate::ihdr::Ihdr;
+use crate::png::{Png, PngError};
+
+#[derive(PartialEq, Debug, Display, Error)]
+pub enum ApngError {
+    NotAnimated,
+    InvalidSequence,
+    FrameCountMismatch,
+    FrameOutOfBounds,
+    MissingFrameData,
+    IncompatibleFrames,
+    InvalidChunk(CodecError),
+    InvalidPng(PngError),
+}
+
+impl From<CodecError> for ApngError {
+    fn from(e: CodecError) -> Self {
+        ApngError::InvalidChunk(e)
+    }
+}
+
+impl From<PngError> for ApngError {
+    fn from(e: PngError) -> Self {
+        ApngError::InvalidPng(e)
+    }
+}
+
+/// Chunks carrying APNG sequence numbers, which cannot be added or removed one at a time.
+pub const SEQUENCED_CHUNKS: [ChunkType; 3] = [ChunkType::acTL, ChunkType::fcTL, ChunkType::fdAT];
+
+fn u32_at(data: &[u8], i: usize) -> u32 {
+    u32::from_be_bytes(data[i..i + 4].try_into().unwrap())
+}
+
+/// Animation control: the number of frames and how often to play them (0 loops forever).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Actl {
+    pub num_frames: u32,
+    pub num_plays: u32,
+}
+
+impl ChunkData for Actl {
+    const CHUNK_TYPE: ChunkType = ChunkType::acTL;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        if data.len() != 8 {
+            return Err(CodecError::InvalidLength);
+        }
+        Ok(Actl {
+            num_frames: u32_at(data, 0),
+            num_plays: u32_at(data, 4),
+        })
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = self.num_frames.to_be_bytes().to_vec();
+        bytes.extend(self.num_plays.to_be_bytes());
+        Ok(bytes)
+    }
+}
+
+impl Display for Actl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.num_plays {
+            0 => write!(f, "{} frames, looping forever", self.num_frames),
+            n => write!(f, "{} frames, {} plays", self.num_frames, n),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DisposeOp {
+    None,
+    Background,
+    Previous,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlendOp {
+    Source,
+    Over,
+}
+
+/// Frame control: the region a frame covers and how it is composited.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Fctl {
+    pub sequence_number: u32,
+    pub width: u32,
+    pub height: u32,
+    pub x_offset: u32,
+    pub y_offset: u32,
+    pub delay_num: u16,
+    pub delay_den: u16,
+    pub dispose_op: DisposeOp,
+    pub blend_op: BlendOp,
+}
+
+impl Fctl {
+    /// Delay in seconds; a denominator of 0 means hundredths of a second.
+    pub fn delay(&self) -> f64 {
+        let den = if self.delay_den == 0 {
+            100
+        } else {
+            self.delay_den
+        };
+        self.delay_num as f64 / den as f64
+    }
+}
+
+impl ChunkData for Fctl {
+    const CHUNK_TYPE: ChunkType = ChunkType::fcTL;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        if data.len() != 26 {
+            return Err(CodecError::InvalidLength);
+        }
+        let dispose_op = match data[24] {
+            0 => DisposeOp::None,
+            1 => DisposeOp::Background,
+            2 => DisposeOp::Previous,
+            _ => return Err(CodecError::InvalidValue),
+        };
+        let blend_op = match data[25] {
+            0 => BlendOp::Source,
+            1 => BlendOp::Over,
+            _ => return Err(CodecError::InvalidValue),
+        };
+        let fctl = Fctl {
+            sequence_number: u32_at(data, 0),
+            width: u32_at(data, 4),
+            height: u32_at(data, 8),
+            x_offset: u32_at(data, 12),
+            y_offset: u32_at(data, 16),
+            delay_num: u16::from_be_bytes([data[20], data[21]]),
+            delay_den: u16::from_be_bytes([data[22], data[23]]),
+            dispose_op,
+            blend_op,
+        };
+        if fctl.width == 0 || fctl.height == 0 {
+            return Err(CodecError::InvalidValue);
+        }
+        Ok(fctl)
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = vec![];
+        for x in [
+            self.sequence_number,
+            self.width,
+            self.height,
+            self.x_offset,
+            self.y_offset,
+        ] {
+            bytes.extend(x.to_be_bytes());
+        }
+        bytes.extend(self.delay_num.to_be_bytes());
+        bytes.extend(self.delay_den.to_be_bytes());
+        bytes.extend([self.dispose_op as u8, self.blend_op as u8]);
+        Ok(bytes)
+    }
+}
+
+impl Display for Fctl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "sequence {}, {}x{} at ({}, {}), delay {}/{}, dispose {:?}, blend {:?}",
+            self.sequence_number,
+            self.width,
+            self.height,
+            self.x_offset,
+            self.y_offset,
+            self.delay_num,
+            self.delay_den,
+            self.dispose_op,
+            self.blend_op
+        )
+    }
+}
+
+/// Frame data: a sequence number followed by what would be `IDAT` data.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Fdat {
+    pub sequence_number: u32,
+    pub data: Vec<u8>,
+}
+
+impl ChunkData for Fdat {
+    const CHUNK_TYPE: ChunkType = ChunkType::fdAT;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        if data.len() < 4 {
+            return Err(CodecError::InvalidLength);
+        }
+        Ok(Fdat {
+            sequence_number: u32_at(data, 0),
+            data: data[4..].to_vec(),
+        })
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = self.sequence_number.to_be_bytes().to_vec();
+        bytes.extend(&self.data);
+        Ok(bytes)
+    }
+}
+
+impl Display for Fdat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "sequence {}, {} bytes of frame data",
+            self.sequence_number,
+            self.data.len()
+        )
+    }
+}
+
+/// One animation frame with its compressed image data split as it was stored.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Frame {
+    pub control: Fctl,
+    pub data: Vec<Vec<u8>>,
+    /// Whether the frame is stored in `IDAT` and doubles as the static image.
+    pub is_default_image: bool,
+}
+
+/// The frames of an APNG file.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Animation {
+    pub control: Actl,
+    pub frames: Vec<Frame>,
+}
+
+impl Animation {
+    /// Reads and validates the animation chunks, including sequence numbers and frame bounds.
+    pub fn from_png(png: &Png) -> Result<Animation, ApngError> {
+        let ihdr = png.ihdr()?;
+        let control = match png.chunk_by_type("acTL") {
+            Some(chunk) => Actl::from_chunk(chunk)?,
+            None => return Err(ApngError::NotAnimated),
+        };
+
+        let mut frames: Vec<Frame> = vec![];
+        let mut sequence = 0;
+        let mut check_sequence = |n: u32| {
+            if n == sequence {
+                sequence += 1;
+                Ok(())
+            } else {
+                Err(ApngError::InvalidSequence)
+            }
+        };
+        let mut seen_idat = false;
+        for chunk in png.chunks() {
+            match *chunk.chunk_type() {
+                ChunkType::fcTL => {
+                    let fctl = Fctl::from_chunk(chunk)?;
+                    check_sequence(fctl.sequence_number)?;
+                    frames.push(Frame {
+                        control: fctl,
+                        data: vec![],
+                        is_default_image: !seen_idat,
+                    });
+                }
+                ChunkType::IDAT => {
+                    seen_idat = true;
+                    match frames.last_mut() {
+                        Some(frame) if frame.is_default_image => {
+                            frame.data.push(chunk.data().to_vec())
+                        }
+                        _ => (),
+                    }
+                }
+                ChunkType::fdAT => {
+                    let fdat = Fdat::from_chunk(chunk)?;
+                    check_sequence(fdat.sequence_number)?;
+                    match frames.last_mut() {
+                        Some(frame) if !frame.is_default_image => frame.data.push(fdat.data),
+                        _ => return Err(ApngError::InvalidSequence),
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        if frames.len() != control.num_frames as usize {
+            return Err(ApngError::FrameCountMismatch);
+        }
+        for frame in frames.iter() {
+            let c = &frame.control;
+            let fits = |offset: u32, size: u32, max: u32| {
+                offset.checked_add(size).is_some_and(|end| end <= max)
+            };
+            if !fits(c.x_offset, c.width, ihdr.width) || !fits(c.y_offset, c.height, ihdr.height) {
+                return Err(ApngError::FrameOutOfBounds);
+            }
+            if frame.is_default_image
+                && (c.x_offset, c.y_offset, c.width, c.height) != (0, 0, ihdr.width, ihdr.height)
+            {
+                return Err(ApngError::FrameOutOfBounds);
+            }
+            if frame.data.is_empty() {
+                return Err(ApngError::MissingFrameData);
+            }
+        }
+        Ok(Animation { control, frames })
+    }
+}
+
+/// Builds a standalone PNG of `frame`, sized to the frame region.
+///
+/// Frames are not composited onto earlier ones, so frames that rely on blending or
+/// disposal of their predecessors come out as just their own region.
+pub fn extract_frame(png: &Png, frame: &Frame) -> Result<Png, ApngError> {
+    let ihdr = Ihdr {
+        width: frame.control.width,
+        height: frame.control.height,
+        ..png.ihdr()?
+    };
+    let mut chunks = vec![Chunk::new(ChunkType::IHDR, ihdr.as_bytes())];
+    // Keep chunks that affect how pixels decode, such as PLTE, tRNS and colour information.
+    for chunk in png.chunks().iter().skip(1) {
+        match *chunk.chunk_type() {
+            ChunkType::IDAT => break,
+            ChunkType::acTL | ChunkType::fcTL => (),
+            _ => chunks.push(Chunk::new(*chunk.chunk_type(), chunk.data().to_vec())),
+        }
+    }
+    for data in frame.data.iter() {
+        chunks.push(Chunk::new(ChunkType::IDAT, data.clone()));
+    }
+    chunks.push(Chunk::new(ChunkType::IEND, vec![]));
+    Ok(Png::from_chunks(chunks))
+}
+
+/// Builds an animation from standalone PNGs; the first frame sets the canvas and is the default image.
+///
+/// Frames share the first frame's `PLTE` and `tRNS`, so their format and palette must match it.
+pub fn assemble(frames: &[Png], delay: (u16, u16), num_plays: u32) -> Result<Png, ApngError> {
+    let first = frames.first().ok_or(ApngError::MissingFrameData)?;
+    let canvas = first.ihdr()?;
+    let shared = |png: &Png| {
+        ["PLTE", "tRNS"].map(|x| png.chunk_by_type(x).map(|chunk| chunk.data().to_vec()))
+    };
+
+    let mut chunks = vec![];
+    for chunk in first.chunks() {
+        match *chunk.chunk_type() {
+            ChunkType::IDAT | ChunkType::IEND => break,
+            ChunkType::acTL | ChunkType::fcTL | ChunkType::fdAT => (),
+            _ => chunks.push(Chunk::new(*chunk.chunk_type(), chunk.data().to_vec())),
+        }
+    }
+    let control = Actl {
+        num_frames: frames.len() as u32,
+        num_plays,
+    };
+    chunks.push(control.to_chunk()?);
+
+    let mut sequence = 0;
+    for (i, frame) in frames.iter().enumerate() {
+        let ihdr = frame.ihdr()?;
+        let compatible = ihdr.bit_depth == canvas.bit_depth
+            && ihdr.colour_type == canvas.colour_type
+            && ihdr.interlaced == canvas.interlaced
+            && ihdr.width <= canvas.width
+            && ihdr.height <= canvas.height
+            && shared(frame) == shared(first);
+        if !compatible {
+            return Err(ApngError::IncompatibleFrames);
+        }
+        let fctl = Fctl {
+            sequence_number: sequence,
+            width: ihdr.width,
+            height: ihdr.height,
+            x_offset: 0,
+            y_offset: 0,
+            delay_num: delay.0,
+            delay_den: delay.1,
+            dispose_op: DisposeOp::None,
+            blend_op: BlendOp::Source,
+        };
+        chunks.push(fctl.to_chunk()?);
+        sequence += 1;
+
+        let idats = frame
+            .chunks()
+            .iter()
+            .filter(|x| *x.chunk_type() == ChunkType::IDAT);
+        for idat in idats {
+            if i == 0 {
+                chunks.push(Chunk::new(ChunkType::IDAT, idat.data().to_vec()));
+            } else {
+                let fdat = Fdat {
+                    sequence_number: sequence,
+                    data: idat.data().to_vec(),
+                };
+                chunks.push(fdat.to_chunk()?);
+                sequence += 1;
+            }
+        }
+    }
+    chunks.push(Chunk::new(ChunkType::IEND, vec![]));
+    Ok(Png::from_chunks(chunks))
+}
+
+/// Rewrites `fcTL` and `fdAT` sequence numbers to count up from 0 in file order.
+pub fn renumber(png: &mut Png) -> Result<(), ApngError> {
+    let chunks = png
+        .chunks_mut()
+        .iter_mut()
+        .filter(|x| matches!(*x.chunk_type(), ChunkType::fcTL | ChunkType::fdAT));
+    for (sequence, chunk) in (0u32..).zip(chunks) {
+        if chunk.data().len() < 4 {
+            return Err(ApngError::InvalidChunk(CodecError::InvalidLength));
+        }
+        if u32_at(chunk.data(), 0) != sequence {
+            chunk.map_data(|data| data[..4].copy_from_slice(&sequence.to_be_bytes()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ihdr::ColourType;
+
+    fn frame_png(width: u32, height: u32, data: &[&[u8]]) -> Png {
+        let ihdr = Ihdr {
+            width,
+            height,
+            bit_depth: 8,
+            colour_type: ColourType::Greyscale,
+            interlaced: false,
+        };
+        let mut chunks = vec![
+            Chunk::new(ChunkType::IHDR, ihdr.as_bytes()),
+            Chunk::new(ChunkType::gAMA, vec![0, 0, 177, 143]),
+        ];
+        for x in data {
+            chunks.push(Chunk::new(ChunkType::IDAT, x.to_vec()));
+        }
+        chunks.push(Chunk::new(ChunkType::IEND, vec![]));
+        Png::from_chunks(chunks)
+    }
+
+    fn testing_apng() -> Png {
+        let frames = [
+            frame_png(4, 4, &[b"first", b"second"]),
+            frame_png(2, 3, &[b"third"]),
+            frame_png(4, 4, &[b"fourth", b"fifth"]),
+        ];
+        assemble(&frames, (1, 10), 0).unwrap()
+    }
+
+    #[test]
+    fn test_control_chunks() {
+        let actl = Actl::from_bytes(&[0, 0, 0, 3, 0, 0, 0, 0]).unwrap();
+        assert_eq!(actl.to_string(), "3 frames, looping forever");
+
+        let fctl = Fctl {
+            sequence_number: 5,
+            width: 10,
+            height: 20,
+            x_offset: 1,
+            y_offset: 2,
+            delay_num: 7,
+            delay_den: 0,
+            dispose_op: DisposeOp::Previous,
+            blend_op: BlendOp::Over,
+        };
+        assert_eq!(Fctl::from_bytes(&fctl.to_bytes().unwrap()), Ok(fctl));
+        assert_eq!(fctl.delay(), 0.07);
+        let mut bytes = fctl.to_bytes().unwrap();
+        bytes[24] = 3;
+        assert_eq!(Fctl::from_bytes(&bytes), Err(CodecError::InvalidValue));
+
+        let fdat = Fdat::from_bytes(&[0, 0, 0, 9, 1, 2]).unwrap();
+        assert_eq!(fdat.sequence_number, 9);
+        assert_eq!(fdat.data, vec![1, 2]);
+        assert_eq!(Fdat::from_bytes(&[0, 0]), Err(CodecError::InvalidLength));
+    }
+
+    #[test]
+    fn test_assemble_and_parse() {
+        let png = testing_apng();
+        let animation = Animation::from_png(&png).unwrap();
+        assert_eq!(animation.control.num_frames, 3);
+        assert_eq!(animation.frames.len(), 3);
+        assert!(animation.frames[0].is_default_image);
+        assert!(!animation.frames[1].is_default_image);
+        assert_eq!(
+            animation.frames[0].data,
+            vec![b"first".to_vec(), b"second".to_vec()]
+        );
+        assert_eq!(animation.frames[1].control.sequence_number, 1);
+        assert_eq!(animation.frames[2].control.sequence_number, 3);
+        assert_eq!(animation.frames[2].data.len(), 2);
+        assert_eq!(png.position("gAMA"), Some(1));
+        assert_eq!(png.position("acTL"), Some(2));
+    }
+
+    #[test]
+    fn test_extract_frame() {
+        let png = testing_apng();
+        let animation = Animation::from_png(&png).unwrap();
+        let frame = extract_frame(&png, &animation.frames[1]).unwrap();
+        assert_eq!(frame, frame_png(2, 3, &[b"third"]));
+    }
+
+    #[test]
+    fn test_invalid_animation() {
+        assert_eq!(
+            Animation::from_png(&frame_png(1, 1, &[b""])),
+            Err(ApngError::NotAnimated)
+        );
+
+        // Swap the two fdAT chunks of the last frame.
+        let png = testing_apng();
+        let mut chunks: Vec<Chunk> = png
+            .chunks()
+            .iter()
+            .map(|x| Chunk::new(*x.chunk_type(), x.data().to_vec()))
+            .collect();
+        let n = chunks.len();
+        chunks.swap(n - 2, n - 3);
+        let mut png = Png::from_chunks(chunks);
+        assert_eq!(Animation::from_png(&png), Err(ApngError::InvalidSequence));
+        renumber(&mut png).unwrap();
+        let animation = Animation::from_png(&png).unwrap();
+        assert_eq!(
+            animation.frames[2].data,
+            vec![b"fifth".to_vec(), b"fourth".to_vec()]
+        );
+        assert!(png.verify_crcs().is_ok());
+
+        let frames = [frame_png(2, 2, &[b"a"]), frame_png(4, 4, &[b"b"])];
+        assert_eq!(
+            assemble(&frames, (1, 10), 0),
+            Err(ApngError::IncompatibleFrames)
+        );
+
+        let mut transparent = frame_png(4, 4, &[b"b"]);
+        transparent.insert_chunk(2, Chunk::new(ChunkType::tRNS, vec![0, 0]));
+        let frames = [frame_png(4, 4, &[b"a"]), transparent];
+        assert_eq!(
+            assemble(&frames, (1, 10), 0),
+            Err(ApngError::IncompatibleFrames)
+        );
+
+        let mut interlaced = frame_png(4, 4, &[b"b"]);
+        let ihdr = Ihdr {
+            interlaced: true,
+            ..interlaced.ihdr().unwrap()
+        };
+        interlaced.chunks_mut()[0] = Chunk::new(ChunkType::IHDR, ihdr.as_bytes());
+        let frames = [frame_png(4, 4, &[b"a"]), interlaced];
+        assert_eq!(
+            assemble(&frames, (1, 10), 0),
+            Err(ApngError::IncompatibleFrames)
+        );
+    }
+}
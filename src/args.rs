@@ -1,5 +1,13 @@
-use clap::Parser;
-use std::path::PathBuf;
+use clap::error::ErrorKind;
+use clap::{ArgMatches, Command, FromArgMatches, Parser};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::ancillary::RenderingIntent;
+use crate::chunk_type::ChunkType;
+use crate::exif::{self, IfdKind};
+use crate::limits::Limits;
+use crate::optimize::Strategy;
 
 #[derive(Parser, Debug)] // requires `derive` feature
 #[command(
@@ -8,44 +16,892 @@ use std::path::PathBuf;
     about = "A command line program that lets you hide secret messages in PNG files."
 )]
 pub enum PngMeArgs {
-    Encode(EncodeArgs),
-    Decode(DecodeArgs),
-    Remove(RemoveArgs),
+    Encode(Trailing<EncodeArgs>),
+    Decode(Trailing<DecodeArgs>),
+    Remove(Trailing<RemoveArgs>),
     Print(PrintArgs),
+    Repair(RepairArgs),
+    #[command(name = "chunktype")]
+    ChunkType(ChunkTypeArgs),
+    Palette(PaletteArgs),
+    #[command(name = "color")]
+    Colour(ColourArgs),
+    Dpi(DpiArgs),
+    Exif(ExifArgs),
+    Xmp(XmpArgs),
+    Apng(ApngArgs),
+    NormalizeCgbi(NormalizeCgbiArgs),
+    Diff(DiffArgs),
+    CopyChunks(CopyChunksArgs),
+    Reorder(ReorderArgs),
+    Optimize(OptimizeArgs),
 }
 
-#[derive(clap::Args, Debug)]
+#[derive(clap::Args, Debug, Clone)]
 #[command(author, version, about = "Encode a message into a PNG file", long_about = None)]
 pub struct EncodeArgs {
-    pub filepath: PathBuf,
-    pub chunk_type: String,
+    /// Files, directories or glob patterns to process, followed by the chunk type (unless
+    /// --auto-type is given) and the message
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    #[arg(skip)]
+    pub chunk_type: Option<String>,
+    #[arg(skip)]
     pub data: String,
+    /// Pick an unused ancillary, private, safe-to-copy chunk type automatically instead of
+    /// taking one from the command line
+    #[arg(long)]
+    pub auto_type: bool,
+    /// Seed for --auto-type, so the same key always yields the same chunk type
+    #[arg(long, requires = "auto_type")]
+    pub key: Option<String>,
+    /// Allow critical chunk types, which make decoders reject the image
+    #[arg(long)]
+    pub force: bool,
+    /// Record the modification time in a tIME chunk
+    #[arg(long)]
+    pub touch: bool,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
 }
 
-#[derive(clap::Args, Debug)]
+#[derive(clap::Args, Debug, Clone)]
 #[command(author, version, about="Decode a message stored in a PNG file", long_about = None)]
 pub struct DecodeArgs {
-    pub filepath: PathBuf,
+    /// Files, directories or glob patterns to process, followed by the chunk type
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    #[arg(skip)]
     pub chunk_type: String,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
 }
 
-#[derive(clap::Args, Debug)]
+#[derive(clap::Args, Debug, Clone)]
 #[command(author, version, about="Remove a message from a PNG file", long_about = None)]
 pub struct RemoveArgs {
-    pub filepath: PathBuf,
+    /// Files, directories or glob patterns to process, followed by the chunk type
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    #[arg(skip)]
     pub chunk_type: String,
+    /// Record the modification time in a tIME chunk
+    #[arg(long)]
+    pub touch: bool,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
 }
 
-#[derive(clap::Args, Debug)]
-#[command(author, version, about="Print a list of PNG chunks that can be searched for messages", long_about = None)]
+#[derive(clap::Args, Debug, Clone)]
+#[command(author, version, about="List the chunks of a PNG file with their lengths", long_about = None)]
 pub struct PrintArgs {
+    /// Files, directories or glob patterns to process
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    /// Also decode the contents of every chunk pngme understands
+    #[arg(short, long)]
+    pub verbose: bool,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[command(author, version, about="Repair CRCs and broken chunk structure in a PNG file", long_about = None)]
+pub struct RepairArgs {
+    /// Files, directories or glob patterns to process
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    /// Write the repaired file here instead of overwriting the input
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// Only report the problems, do not write anything
+    #[arg(long)]
+    pub dry_run: bool,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[command(author, version, about="Convert an Apple CgBI file into a standard PNG", long_about = None)]
+pub struct NormalizeCgbiArgs {
+    /// Files, directories or glob patterns to process
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    /// Write the converted file here instead of overwriting the input
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(author, version, about="List the chunks added, removed, moved or modified between two files", long_about = None)]
+pub struct DiffArgs {
+    pub old: PathBuf,
+    pub new: PathBuf,
+    /// Compare decoded pixels instead of IDAT bytes, ignoring re-compression
+    #[arg(long)]
+    pub pixels: bool,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[command(author, version, about="Copy chunks such as metadata from one file into another", long_about = None)]
+pub struct CopyChunksArgs {
+    #[arg(long)]
+    pub from: PathBuf,
+    /// Files, directories or glob patterns to copy into
+    #[arg(long, value_name = "PATH", required = true, num_args = 1..)]
+    pub to: Vec<PathBuf>,
+    /// Comma separated chunk types to copy
+    #[arg(long, required = true, value_delimiter = ',', value_parser = ChunkType::from_str)]
+    pub types: Vec<ChunkType>,
+    /// Copy critical chunks, and unsafe-to-copy chunks even when the image data differs
+    #[arg(long)]
+    pub force: bool,
+    /// Write the result here instead of overwriting the --to file
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[command(author, version, about="Sort chunks into canonical order, rechunk IDAT and drop duplicates", long_about = None)]
+pub struct ReorderArgs {
+    /// Files, directories or glob patterns to process
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    /// Largest IDAT chunk to write, in bytes
+    #[arg(long, value_name = "BYTES", default_value_t = 65536)]
+    pub idat_size: usize,
+    /// Leave the IDAT chunks as they are
+    #[arg(long, conflicts_with = "idat_size")]
+    pub keep_idat: bool,
+    /// Keep ancillary chunks that duplicate an earlier chunk
+    #[arg(long)]
+    pub keep_duplicates: bool,
+    /// Write the result here instead of overwriting the input
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[command(author, version, about="Recompress the image data losslessly, keeping every other chunk", long_about = None)]
+pub struct OptimizeArgs {
+    /// Files, directories or glob patterns to process
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    /// Comma separated row filter strategies to try: none, sub, up, average, paeth, minsum, entropy, brute
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_parser = Strategy::from_str,
+        default_value = "none,sub,up,average,paeth,minsum,entropy"
+    )]
+    pub strategies: Vec<Strategy>,
+    /// Comma separated deflate levels to try, from 0 to 10
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_parser = clap::value_parser!(u8).range(0..=10),
+        default_value = "6,9"
+    )]
+    pub levels: Vec<u8>,
+    /// Also try a smaller bit depth or colour type when no pixel changes
+    #[arg(long)]
+    pub reduce: bool,
+    /// Write the result here instead of overwriting the input
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(author, version, about="Explain the property bits of a chunk type", long_about = None)]
+pub struct ChunkTypeArgs {
+    pub chunk_type: String,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(author, version, about="Inspect and edit the palette of an indexed PNG file", long_about = None)]
+pub struct PaletteArgs {
+    #[command(subcommand)]
+    pub command: PaletteCommand,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum PaletteCommand {
+    /// List the palette entries with their alpha values
+    Show(PaletteShowArgs),
+    /// Replace a single palette entry
+    Set(Trailing<PaletteSetArgs>),
+    /// Reorder the palette together with tRNS and hIST
+    Reorder(Trailing<PaletteReorderArgs>),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct PaletteShowArgs {
+    /// Files, directories or glob patterns to process
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct PaletteSetArgs {
+    /// Files, directories or glob patterns to process, followed by the new colour as #rrggbb
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    /// Palette entry to replace
+    #[arg(long)]
+    pub index: usize,
+    #[arg(skip)]
+    pub colour: String,
+    /// Also set the alpha of the entry, adding a tRNS chunk if needed
+    #[arg(long)]
+    pub alpha: Option<u8>,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct PaletteReorderArgs {
+    /// Files, directories or glob patterns to process, followed by the new order as a comma separated
+    /// list of the old indices
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    #[arg(skip)]
+    pub order: Vec<usize>,
+    /// Leave the pixel indices unchanged, which changes how the image looks
+    #[arg(long)]
+    pub keep_pixels: bool,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(author, version, about="Inspect and edit colour-management chunks (gAMA, cHRM, sRGB, iCCP, cICP)", long_about = None)]
+pub struct ColourArgs {
+    #[command(subcommand)]
+    pub command: ColourCommand,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum ColourCommand {
+    /// Print the decoded colour-management chunks
+    Show(ColourShowArgs),
+    /// Add or replace colour-management chunks
+    Set(ColourSetArgs),
+    /// Remove every colour-management chunk
+    Strip(ColourStripArgs),
+    /// Write the embedded ICC profile to a file
+    ExportIcc(ExportIccArgs),
+    /// Embed an ICC profile from a file, replacing any sRGB chunk
+    ImportIcc(Trailing<ImportIccArgs>),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ColourShowArgs {
+    /// Files, directories or glob patterns to process
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ColourSetArgs {
+    /// Files, directories or glob patterns to process
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    /// Image gamma, e.g. 0.45455
+    #[arg(long)]
+    pub gamma: Option<f64>,
+    /// White, red, green and blue x,y pairs as eight comma separated values
+    #[arg(long, value_parser = parse_list::<f64, 8>, value_name = "X,Y,...")]
+    pub chromaticities: Option<[f64; 8]>,
+    /// sRGB rendering intent: perceptual, relative, saturation or absolute
+    #[arg(long, value_name = "INTENT")]
+    pub srgb: Option<RenderingIntent>,
+    /// Colour primaries, transfer function, matrix coefficients and full-range flag
+    #[arg(long, value_parser = parse_list::<u8, 4>, value_name = "P,T,M,F")]
+    pub cicp: Option<[u8; 4]>,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ColourStripArgs {
+    /// Files, directories or glob patterns to process
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ExportIccArgs {
+    pub filepath: PathBuf,
+    pub output: PathBuf,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ImportIccArgs {
+    /// Files, directories or glob patterns to process, followed by the ICC profile
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    #[arg(skip)]
+    pub profile: PathBuf,
+    /// Profile name stored in the iCCP chunk
+    #[arg(long, default_value = "ICC profile")]
+    pub name: String,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(author, version, about="Read or set the physical pixel density (pHYs)", long_about = None)]
+pub struct DpiArgs {
+    #[command(subcommand)]
+    pub command: DpiCommand,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum DpiCommand {
+    /// Print the pixel density in dots per inch
+    Get(DpiGetArgs),
+    /// Set the pixel density in dots per inch
+    Set(Trailing<DpiSetArgs>),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct DpiGetArgs {
+    /// Files, directories or glob patterns to process
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct DpiSetArgs {
+    /// Files, directories or glob patterns to process, followed by the horizontal density
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    #[arg(skip)]
+    pub x: f64,
+    /// Vertical density; defaults to the horizontal one
+    #[arg(long = "vertical", value_name = "DPI", value_parser = parse_dpi)]
+    pub y: Option<f64>,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(author, version, about="Inspect and edit EXIF metadata stored in eXIf", long_about = None)]
+pub struct ExifArgs {
+    #[command(subcommand)]
+    pub command: ExifCommand,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum ExifCommand {
+    /// List every EXIF tag
+    Show(ExifShowArgs),
+    /// Print the value of a single tag
+    Get(Trailing<ExifGetArgs>),
+    /// Add or replace a tag
+    Set(Trailing<ExifSetArgs>),
+    /// Remove the GPS location tags
+    RemoveGps(ExifShowArgs),
+    /// Remove the eXIf chunk
+    Strip(ExifShowArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ExifShowArgs {
+    /// Files, directories or glob patterns to process
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ExifGetArgs {
+    /// Files, directories or glob patterns to process, followed by the tag name such as
+    /// Orientation, or a number such as 0x0112
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    #[arg(skip)]
+    pub tag: String,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ExifSetArgs {
+    /// Files, directories or glob patterns to process, followed by the new value; lists are
+    /// comma separated and rationals written n/d
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    /// Tag name such as Orientation, or a number such as 0x0112
+    #[arg(long)]
+    pub tag: String,
+    #[arg(skip)]
+    pub value: String,
+    /// Field type such as short or rational; required for numeric tags missing from the tag table
+    #[arg(long = "type", value_name = "TYPE", value_parser = exif::parse_type)]
+    pub field_type: Option<u16>,
+    /// IFD that receives a numeric tag missing from the tag table
+    #[arg(long, default_value = "IFD0", value_parser = IfdKind::from_str)]
+    pub ifd: IfdKind,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(author, version, about="Read or write the XMP packet stored in iTXt", long_about = None)]
+pub struct XmpArgs {
+    #[command(subcommand)]
+    pub command: XmpCommand,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum XmpCommand {
+    /// Print the XMP packet
+    Get(XmpGetArgs),
+    /// Replace the XMP packet with the contents of a file
+    Set(Trailing<XmpSetArgs>),
+    /// Add the descriptions from a file to the existing XMP packet
+    Merge(Trailing<XmpSetArgs>),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct XmpGetArgs {
+    /// Files, directories or glob patterns to process
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct XmpSetArgs {
+    /// Files, directories or glob patterns to process, followed by the file containing the XML
+    /// packet
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    #[arg(skip)]
+    pub packet: PathBuf,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(author, version, about="Inspect, split and build animated PNGs", long_about = None)]
+pub struct ApngArgs {
+    #[command(subcommand)]
+    pub command: ApngCommand,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum ApngCommand {
+    /// List the frames of an animation
+    Info(ApngInfoArgs),
+    /// Write each frame as a standalone PNG
+    Extract(ApngExtractArgs),
+    /// Build an animation from PNG frames
+    Assemble(ApngAssembleArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ApngInfoArgs {
+    /// Files, directories or glob patterns to process
+    #[arg(value_name = "PATH", required = true, num_args = 1..)]
+    pub filepaths: Vec<PathBuf>,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ApngExtractArgs {
     pub filepath: PathBuf,
+    /// Directory to write frame_000.png, frame_001.png, ... into
+    pub output: PathBuf,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ApngAssembleArgs {
+    pub output: PathBuf,
+    /// Frames in display order; the first one sets the canvas size
+    #[arg(required = true)]
+    pub frames: Vec<PathBuf>,
+    /// Delay of every frame in seconds, written n/d
+    #[arg(long, default_value = "1/10", value_parser = parse_delay)]
+    pub delay: (u16, u16),
+    /// Number of times to play the animation; 0 loops forever
+    #[arg(long, default_value_t = 0)]
+    pub plays: u32,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+/// Parses a frame delay written `n/d` or `n` (whole seconds).
+fn parse_delay(s: &str) -> Result<(u16, u16), String> {
+    let (num, den) = s.split_once('/').unwrap_or((s, "1"));
+    match (num.trim().parse(), den.trim().parse()) {
+        (Ok(num), Ok(den)) if den != 0 => Ok((num, den)),
+        _ => Err(format!("invalid delay: {}", s)),
+    }
+}
+
+/// Parses a pixel density, which must be a positive finite number.
+fn parse_dpi(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(dpi) if dpi.is_finite() && dpi > 0.0 => Ok(dpi),
+        _ => Err(format!("invalid density: {}", s)),
+    }
+}
+
+/// Parses exactly `N` comma separated values.
+fn parse_list<T: FromStr, const N: usize>(s: &str) -> Result<[T; N], String> {
+    let values: Vec<T> = s
+        .split(',')
+        .map(|x| {
+            x.trim()
+                .parse()
+                .map_err(|_| format!("invalid value: {}", x))
+        })
+        .collect::<Result<_, _>>()?;
+    values
+        .try_into()
+        .map_err(|_| format!("expected {} comma separated values", N))
+}
+
+/// Parses a comma separated list of palette indices.
+fn parse_order(s: &str) -> Result<Vec<usize>, String> {
+    s.split(',')
+        .map(|x| {
+            x.trim()
+                .parse()
+                .map_err(|_| format!("invalid index: {}", x))
+        })
+        .collect()
+}
+
+/// Arguments whose paths are followed by values of their own, as in `decode a.png b.png ruSt`.
+///
+/// Clap only lets options sit between positionals when a single variadic positional takes them
+/// all, so `A` collects every operand in its paths and [`SplitOperands`] moves the values off the
+/// end once parsing is done.
+#[derive(Debug, Clone)]
+pub struct Trailing<A>(pub A);
+
+/// Arguments that read values from the end of their operands.
+pub trait SplitOperands {
+    /// Moves the values after the paths into their own fields.
+    fn split_operands(&mut self) -> Result<(), clap::Error>;
+}
+
+impl<A: clap::Args + SplitOperands> clap::Args for Trailing<A> {
+    fn augment_args(cmd: Command) -> Command {
+        A::augment_args(cmd)
+    }
+    fn augment_args_for_update(cmd: Command) -> Command {
+        A::augment_args_for_update(cmd)
+    }
+}
+
+impl<A: FromArgMatches + SplitOperands> FromArgMatches for Trailing<A> {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
+        let mut args = A::from_arg_matches(matches)?;
+        args.split_operands()?;
+        Ok(Trailing(args))
+    }
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), clap::Error> {
+        *self = Self::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+/// Removes one value per name from the end of `paths`, keeping at least one path.
+fn take_operands(paths: &mut Vec<PathBuf>, names: &[&str]) -> Result<Vec<String>, clap::Error> {
+    if paths.len() <= names.len() {
+        let usage: Vec<_> = names.iter().map(|x| format!("<{}>", x)).collect();
+        return Err(clap::Error::raw(
+            ErrorKind::MissingRequiredArgument,
+            format!(
+                "the following required arguments were not provided:\n  <PATH>... {}",
+                usage.join(" ")
+            ),
+        ));
+    }
+    paths
+        .split_off(paths.len() - names.len())
+        .into_iter()
+        .map(|x| {
+            x.into_os_string()
+                .into_string()
+                .map_err(|_| clap::Error::new(ErrorKind::InvalidUtf8))
+        })
+        .collect()
+}
+
+/// Parses the operand `value` named `name` with `parse`.
+fn parse_operand<T, E: std::fmt::Display>(
+    name: &str,
+    value: String,
+    parse: impl Fn(&str) -> Result<T, E>,
+) -> Result<T, clap::Error> {
+    parse(&value).map_err(|e| {
+        clap::Error::raw(
+            ErrorKind::ValueValidation,
+            format!("invalid value '{}' for '<{}>': {}", value, name, e),
+        )
+    })
+}
+
+/// Implements [`SplitOperands`] for arguments whose `filepaths` end with the given fields, each
+/// shown under its value name and read with `FromStr` or the parser after `=`.
+macro_rules! split_operands {
+    (@parse) => {
+        str::parse
+    };
+    (@parse $parse:expr) => {
+        $parse
+    };
+    ($($args:ident { $($field:ident: $name:literal $(= $parse:expr)?),+ }),* $(,)?) => {
+        $(impl SplitOperands for $args {
+            fn split_operands(&mut self) -> Result<(), clap::Error> {
+                let names = [$($name),+];
+                let mut values = take_operands(&mut self.filepaths, &names)?.into_iter();
+                $(self.$field = parse_operand(
+                    $name,
+                    values.next().unwrap_or_default(),
+                    split_operands!(@parse $($parse)?),
+                )?;)+
+                Ok(())
+            }
+        })*
+    };
+}
+
+split_operands!(
+    DecodeArgs {
+        chunk_type: "CHUNK_TYPE"
+    },
+    RemoveArgs {
+        chunk_type: "CHUNK_TYPE"
+    },
+    PaletteSetArgs { colour: "COLOUR" },
+    PaletteReorderArgs {
+        order: "ORDER" = parse_order
+    },
+    ImportIccArgs { profile: "PROFILE" },
+    DpiSetArgs {
+        x: "DPI" = parse_dpi
+    },
+    ExifGetArgs { tag: "TAG" },
+    ExifSetArgs { value: "VALUE" },
+    XmpSetArgs { packet: "PACKET" },
+);
+
+impl SplitOperands for EncodeArgs {
+    fn split_operands(&mut self) -> Result<(), clap::Error> {
+        // --auto-type stands in for the chunk type, leaving only the message.
+        if self.auto_type {
+            let [data] = take_operands(&mut self.filepaths, &["MESSAGE"])?
+                .try_into()
+                .unwrap_or_default();
+            self.data = data;
+        } else {
+            let [chunk_type, data] =
+                take_operands(&mut self.filepaths, &["CHUNK_TYPE", "MESSAGE"])?
+                    .try_into()
+                    .unwrap_or_default();
+            self.chunk_type = Some(chunk_type);
+            self.data = data;
+        }
+        Ok(())
+    }
+}
+
+/// Options for running a command on many files at once.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct BatchArgs {
+    /// Include images in subdirectories of directory arguments
+    #[arg(short, long)]
+    pub recursive: bool,
+    /// Number of files to process in parallel; defaults to one per CPU
+    #[arg(short, long, value_name = "COUNT")]
+    pub jobs: Option<usize>,
+}
+
+/// Arguments of commands that run once per input file.
+pub trait BatchTarget: Clone + Sync {
+    /// The paths given on the command line and the options selecting further files.
+    fn batch(&self) -> (&[PathBuf], &BatchArgs);
+    /// A fixed output file, which cannot be shared by several inputs.
+    fn output(&self) -> Option<&Path> {
+        None
+    }
+}
+
+/// Implements [`BatchTarget`] for arguments whose paths are in `filepaths`, or in the field named
+/// in braces; `output` marks arguments with an `--output` file.
+macro_rules! batch_target {
+    (@impl $args:ident, $paths:ident $(, $output:ident)?) => {
+        impl BatchTarget for $args {
+            fn batch(&self) -> (&[PathBuf], &BatchArgs) {
+                (&self.$paths, &self.batch)
+            }
+            $(fn output(&self) -> Option<&Path> {
+                self.$output.as_deref()
+            })?
+        }
+    };
+    ($args:ident { $paths:ident } $(, $output:ident)?) => {
+        batch_target!(@impl $args, $paths $(, $output)?);
+    };
+    (output: $($args:ident),* $(,)?) => {
+        $(batch_target!(@impl $args, filepaths, output);)*
+    };
+    ($($args:ident),* $(,)?) => {
+        $(batch_target!(@impl $args, filepaths);)*
+    };
+}
+
+batch_target!(
+    EncodeArgs,
+    DecodeArgs,
+    RemoveArgs,
+    PrintArgs,
+    PaletteShowArgs,
+    PaletteSetArgs,
+    PaletteReorderArgs,
+    ColourShowArgs,
+    ColourSetArgs,
+    ColourStripArgs,
+    ImportIccArgs,
+    DpiGetArgs,
+    DpiSetArgs,
+    ExifShowArgs,
+    ExifGetArgs,
+    ExifSetArgs,
+    XmpGetArgs,
+    XmpSetArgs,
+    ApngInfoArgs,
+);
+batch_target!(output: RepairArgs, NormalizeCgbiArgs, ReorderArgs, OptimizeArgs);
+// Runs once per destination, so one source can be copied into many files.
+batch_target!(CopyChunksArgs { to }, output);
+
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct LimitArgs {
+    /// Apply conservative limits suitable for untrusted input
+    #[arg(long)]
+    pub untrusted: bool,
+    /// Maximum input file size in bytes
+    #[arg(long, value_name = "BYTES")]
+    pub max_file_size: Option<usize>,
+    /// Maximum length of a single chunk in bytes
+    #[arg(long, value_name = "BYTES")]
+    pub max_chunk_length: Option<usize>,
+    /// Maximum number of chunks
+    #[arg(long, value_name = "COUNT")]
+    pub max_chunk_count: Option<usize>,
+    /// Maximum size of decompressed zTXt/iTXt/iCCP/IDAT data in bytes
+    #[arg(long, value_name = "BYTES")]
+    pub max_decompressed_size: Option<usize>,
+    /// Maximum image width in pixels
+    #[arg(long, value_name = "PIXELS")]
+    pub max_width: Option<u32>,
+    /// Maximum image height in pixels
+    #[arg(long, value_name = "PIXELS")]
+    pub max_height: Option<u32>,
+}
+
+impl From<&LimitArgs> for Limits {
+    fn from(args: &LimitArgs) -> Self {
+        let base = if args.untrusted {
+            Limits::untrusted()
+        } else {
+            Limits::default()
+        };
+        Limits {
+            max_file_size: args.max_file_size.unwrap_or(base.max_file_size),
+            max_chunk_length: args.max_chunk_length.unwrap_or(base.max_chunk_length),
+            max_chunk_count: args.max_chunk_count.unwrap_or(base.max_chunk_count),
+            max_decompressed_size: args
+                .max_decompressed_size
+                .unwrap_or(base.max_decompressed_size),
+            max_width: args.max_width.unwrap_or(base.max_width),
+            max_height: args.max_height.unwrap_or(base.max_height),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::str::FromStr;
-
     use super::*;
 
     #[test]
@@ -58,40 +914,233 @@ mod tests {
             "This is a secret message!",
         ]);
 
-        if let PngMeArgs::Encode(x) = result {
-            let actual = x.filepath;
-            let expect = PathBuf::from_str("./dice.png").unwrap();
+        if let PngMeArgs::Encode(Trailing(x)) = result {
+            let actual = x.filepaths;
+            let expect = vec![PathBuf::from_str("./dice.png").unwrap()];
             assert_eq!(actual, expect);
-            assert_eq!(x.chunk_type, "ruSt");
+            assert_eq!(x.chunk_type.as_deref(), Some("ruSt"));
+            assert_eq!(x.data, "This is a secret message!");
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    pub fn test_encode_auto_type_args() {
+        let with_type = ["pngme", "encode", "./dice.png", "ruSt", "message"];
+        if let Ok(PngMeArgs::Encode(Trailing(x))) = PngMeArgs::try_parse_from(with_type) {
+            assert_eq!(x.filepaths, [PathBuf::from("./dice.png")]);
+            assert_eq!(x.chunk_type.as_deref(), Some("ruSt"));
+            assert_eq!(x.data, "message");
+            assert!(!x.auto_type);
+        } else {
+            panic!();
+        }
+
+        let auto_type = ["pngme", "encode", "--auto-type", "./dice.png", "message"];
+        if let Ok(PngMeArgs::Encode(Trailing(x))) = PngMeArgs::try_parse_from(auto_type) {
+            assert_eq!(x.filepaths, [PathBuf::from("./dice.png")]);
+            assert_eq!(x.chunk_type, None);
+            assert_eq!(x.data, "message");
+            assert!(x.auto_type);
+        } else {
+            panic!();
+        }
+
+        let result = PngMeArgs::parse_from([
+            "pngme",
+            "encode",
+            "./dice.png",
+            "This is a secret message!",
+            "--auto-type",
+            "--key",
+            "app",
+        ]);
+        if let PngMeArgs::Encode(Trailing(x)) = result {
+            assert_eq!(x.chunk_type, None);
             assert_eq!(x.data, "This is a secret message!");
+            assert_eq!(x.key.as_deref(), Some("app"));
         } else {
             panic!();
         }
+
+        let missing = ["pngme", "encode", "./dice.png", "message"];
+        assert!(PngMeArgs::try_parse_from(missing).is_err());
+        let key_alone = [
+            "pngme",
+            "encode",
+            "./dice.png",
+            "ruSt",
+            "message",
+            "--key",
+            "x",
+        ];
+        assert!(PngMeArgs::try_parse_from(key_alone).is_err());
     }
 
     #[test]
     pub fn test_decode_args() {
         let result = PngMeArgs::parse_from(["pngme", "decode", "./dice.png", "ruSt"]);
 
-        if let PngMeArgs::Decode(x) = result {
-            let actual = x.filepath;
-            let expect = PathBuf::from_str("./dice.png").unwrap();
+        if let PngMeArgs::Decode(Trailing(x)) = result {
+            let actual = x.filepaths;
+            let expect = vec![PathBuf::from_str("./dice.png").unwrap()];
             assert_eq!(actual, expect);
             assert_eq!(x.chunk_type, "ruSt");
         } else {
             panic!();
         }
+
+        let result = PngMeArgs::parse_from(["pngme", "decode", "./a.png", "./b.png", "ruSt"]);
+        if let PngMeArgs::Decode(Trailing(x)) = result {
+            assert_eq!(x.filepaths.len(), 2);
+            assert_eq!(x.chunk_type, "ruSt");
+        } else {
+            panic!();
+        }
+        let result = PngMeArgs::try_parse_from(["pngme", "decode", "ruSt"]);
+        assert!(result.is_err());
     }
 
     #[test]
     pub fn test_remove_args() {
         let result = PngMeArgs::parse_from(["pngme", "remove", "./dice.png", "ruSt"]);
 
-        if let PngMeArgs::Remove(x) = result {
-            let actual = x.filepath;
-            let expect = PathBuf::from_str("./dice.png").unwrap();
+        if let PngMeArgs::Remove(Trailing(x)) = result {
+            let actual = x.filepaths;
+            let expect = vec![PathBuf::from_str("./dice.png").unwrap()];
             assert_eq!(actual, expect);
             assert_eq!(x.chunk_type, "ruSt");
+            assert!(!x.touch);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    pub fn test_interleaved_args() {
+        let result =
+            PngMeArgs::try_parse_from(["pngme", "encode", "a.png", "--touch", "ruSt", "hello"]);
+        if let Ok(PngMeArgs::Encode(Trailing(x))) = result {
+            assert_eq!(x.filepaths, [PathBuf::from("a.png")]);
+            assert_eq!(x.chunk_type.as_deref(), Some("ruSt"));
+            assert_eq!(x.data, "hello");
+            assert!(x.touch);
+        } else {
+            panic!("{:?}", result);
+        }
+
+        let result = PngMeArgs::try_parse_from(["pngme", "decode", "a.png", "-j", "1", "RuSt"]);
+        if let Ok(PngMeArgs::Decode(Trailing(x))) = result {
+            assert_eq!(x.filepaths, [PathBuf::from("a.png")]);
+            assert_eq!(x.chunk_type, "RuSt");
+            assert_eq!(x.batch.jobs, Some(1));
+        } else {
+            panic!("{:?}", result);
+        }
+
+        let result = PngMeArgs::try_parse_from([
+            "pngme", "palette", "set", "a.png", "--index", "0", "#ff0000",
+        ]);
+        if let Ok(PngMeArgs::Palette(PaletteArgs {
+            command: PaletteCommand::Set(Trailing(x)),
+        })) = result
+        {
+            assert_eq!(x.filepaths, [PathBuf::from("a.png")]);
+            assert_eq!(x.index, 0);
+            assert_eq!(x.colour, "#ff0000");
+        } else {
+            panic!("{:?}", result);
+        }
+
+        let result = PngMeArgs::try_parse_from([
+            "pngme",
+            "exif",
+            "set",
+            "a.png",
+            "--tag",
+            "Orientation",
+            "6",
+        ]);
+        if let Ok(PngMeArgs::Exif(ExifArgs {
+            command: ExifCommand::Set(Trailing(x)),
+        })) = result
+        {
+            assert_eq!(x.filepaths, [PathBuf::from("a.png")]);
+            assert_eq!(x.tag, "Orientation");
+            assert_eq!(x.value, "6");
+        } else {
+            panic!("{:?}", result);
+        }
+
+        let result = PngMeArgs::try_parse_from([
+            "pngme",
+            "dpi",
+            "set",
+            "a.png",
+            "-r",
+            "b.png",
+            "--vertical",
+            "150",
+            "300",
+        ]);
+        if let Ok(PngMeArgs::Dpi(DpiArgs {
+            command: DpiCommand::Set(Trailing(x)),
+        })) = result
+        {
+            assert_eq!(
+                x.filepaths,
+                [PathBuf::from("a.png"), PathBuf::from("b.png")]
+            );
+            assert_eq!((x.x, x.y), (300.0, Some(150.0)));
+            assert!(x.batch.recursive);
+        } else {
+            panic!("{:?}", result);
+        }
+
+        let result = PngMeArgs::try_parse_from(["pngme", "remove", "--touch", "ruSt"]);
+        assert!(result.is_err());
+        let result = PngMeArgs::try_parse_from(["pngme", "encode", "a.png", "-j", "1", "ruSt"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    pub fn test_dpi_args() {
+        let result = PngMeArgs::parse_from(["pngme", "dpi", "set", "./dice.png", "300"]);
+        if let PngMeArgs::Dpi(DpiArgs {
+            command: DpiCommand::Set(Trailing(x)),
+        }) = result
+        {
+            assert_eq!(x.x, 300.0);
+            assert_eq!(x.y, None);
+        } else {
+            panic!();
+        }
+        let result = PngMeArgs::parse_from([
+            "pngme",
+            "dpi",
+            "set",
+            "./dice.png",
+            "300",
+            "--vertical",
+            "150",
+        ]);
+        if let PngMeArgs::Dpi(DpiArgs {
+            command: DpiCommand::Set(Trailing(x)),
+        }) = result
+        {
+            assert_eq!(x.y, Some(150.0));
+        } else {
+            panic!();
+        }
+        for dpi in ["0", "-72", "NaN", "inf"] {
+            let result = PngMeArgs::try_parse_from(["pngme", "dpi", "set", "./dice.png", dpi]);
+            assert!(result.is_err());
+        }
+
+        let result = PngMeArgs::parse_from(["pngme", "remove", "./dice.png", "ruSt", "--touch"]);
+        if let PngMeArgs::Remove(Trailing(x)) = result {
+            assert!(x.touch);
         } else {
             panic!();
         }
@@ -102,9 +1151,415 @@ mod tests {
         let result = PngMeArgs::parse_from(["pngme", "print", "./dice.png"]);
 
         if let PngMeArgs::Print(x) = result {
-            let actual = x.filepath;
-            let expect = PathBuf::from_str("./dice.png").unwrap();
+            let actual = x.filepaths;
+            let expect = vec![PathBuf::from_str("./dice.png").unwrap()];
             assert_eq!(actual, expect);
+            assert!(!x.verbose);
+        } else {
+            panic!();
+        }
+
+        let result = PngMeArgs::parse_from(["pngme", "print", "./dice.png", "--verbose"]);
+        if let PngMeArgs::Print(x) = result {
+            assert!(x.verbose);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    pub fn test_repair_args() {
+        let result = PngMeArgs::parse_from(["pngme", "repair", "./dice.png", "-o", "./fixed.png"]);
+
+        if let PngMeArgs::Repair(x) = result {
+            assert_eq!(x.filepaths, vec![PathBuf::from_str("./dice.png").unwrap()]);
+            assert_eq!(x.output, Some(PathBuf::from_str("./fixed.png").unwrap()));
+            assert!(!x.dry_run);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    pub fn test_chunk_type_args() {
+        let result = PngMeArgs::parse_from(["pngme", "chunktype", "RuSt"]);
+
+        if let PngMeArgs::ChunkType(x) = result {
+            assert_eq!(x.chunk_type, "RuSt");
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    pub fn test_palette_args() {
+        let result = PngMeArgs::parse_from([
+            "pngme",
+            "palette",
+            "set",
+            "--index",
+            "3",
+            "./dice.png",
+            "#ff0000",
+            "--alpha",
+            "128",
+        ]);
+        if let PngMeArgs::Palette(PaletteArgs {
+            command: PaletteCommand::Set(Trailing(x)),
+        }) = result
+        {
+            assert_eq!(x.index, 3);
+            assert_eq!(x.colour, "#ff0000");
+            assert_eq!(x.alpha, Some(128));
+        } else {
+            panic!();
+        }
+
+        let result =
+            PngMeArgs::parse_from(["pngme", "palette", "reorder", "./a.png", "./b.png", "2,0,1"]);
+        if let PngMeArgs::Palette(PaletteArgs {
+            command: PaletteCommand::Reorder(Trailing(x)),
+        }) = result
+        {
+            assert_eq!(x.filepaths.len(), 2);
+            assert_eq!(x.order, vec![2, 0, 1]);
+            assert!(!x.keep_pixels);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    pub fn test_colour_args() {
+        let result = PngMeArgs::parse_from([
+            "pngme",
+            "color",
+            "set",
+            "./dice.png",
+            "--gamma",
+            "0.45455",
+            "--srgb",
+            "perceptual",
+            "--cicp",
+            "1,13,0,1",
+        ]);
+        if let PngMeArgs::Colour(ColourArgs {
+            command: ColourCommand::Set(x),
+        }) = result
+        {
+            assert_eq!(x.gamma, Some(0.45455));
+            assert_eq!(x.srgb, Some(RenderingIntent::Perceptual));
+            assert_eq!(x.cicp, Some([1, 13, 0, 1]));
+            assert_eq!(x.chromaticities, None);
+        } else {
+            panic!();
+        }
+
+        let result = PngMeArgs::try_parse_from([
+            "pngme",
+            "color",
+            "set",
+            "./dice.png",
+            "--chromaticities",
+            "0.3127,0.329",
+        ]);
+        assert!(result.is_err());
+
+        let result = PngMeArgs::parse_from(["pngme", "color", "export-icc", "./a.png", "./a.icc"]);
+        if let PngMeArgs::Colour(ColourArgs {
+            command: ColourCommand::ExportIcc(x),
+        }) = result
+        {
+            assert_eq!(x.output, PathBuf::from_str("./a.icc").unwrap());
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    pub fn test_exif_args() {
+        let result = PngMeArgs::parse_from([
+            "pngme",
+            "exif",
+            "set",
+            "--tag",
+            "Orientation",
+            "./dice.png",
+            "6",
+        ]);
+        if let PngMeArgs::Exif(ExifArgs {
+            command: ExifCommand::Set(Trailing(x)),
+        }) = result
+        {
+            assert_eq!(x.tag, "Orientation");
+            assert_eq!(x.value, "6");
+            assert_eq!(x.field_type, None);
+            assert_eq!(x.ifd, IfdKind::Primary);
+        } else {
+            panic!();
+        }
+
+        let result = PngMeArgs::parse_from([
+            "pngme",
+            "exif",
+            "set",
+            "--tag",
+            "0xbeef",
+            "--type",
+            "srational",
+            "--ifd",
+            "gps",
+            "./dice.png",
+            "1/2",
+        ]);
+        if let PngMeArgs::Exif(ExifArgs {
+            command: ExifCommand::Set(Trailing(x)),
+        }) = result
+        {
+            assert_eq!(x.field_type, Some(10));
+            assert_eq!(x.ifd, IfdKind::Gps);
+            assert_eq!(x.value, "1/2");
+        } else {
+            panic!();
+        }
+
+        let result = PngMeArgs::parse_from(["pngme", "exif", "remove-gps", "./dice.png"]);
+        assert!(matches!(
+            result,
+            PngMeArgs::Exif(ExifArgs {
+                command: ExifCommand::RemoveGps(_)
+            })
+        ));
+    }
+
+    #[test]
+    pub fn test_xmp_args() {
+        let result = PngMeArgs::parse_from(["pngme", "xmp", "merge", "./dice.png", "./extra.xmp"]);
+        if let PngMeArgs::Xmp(XmpArgs {
+            command: XmpCommand::Merge(Trailing(x)),
+        }) = result
+        {
+            assert_eq!(x.packet, PathBuf::from_str("./extra.xmp").unwrap());
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    pub fn test_apng_args() {
+        let result = PngMeArgs::parse_from([
+            "pngme",
+            "apng",
+            "assemble",
+            "./out.png",
+            "./a.png",
+            "./b.png",
+            "--delay",
+            "3/100",
+        ]);
+        if let PngMeArgs::Apng(ApngArgs {
+            command: ApngCommand::Assemble(x),
+        }) = result
+        {
+            assert_eq!(x.frames.len(), 2);
+            assert_eq!(x.delay, (3, 100));
+            assert_eq!(x.plays, 0);
+        } else {
+            panic!();
+        }
+
+        let result = PngMeArgs::try_parse_from([
+            "pngme",
+            "apng",
+            "assemble",
+            "./out.png",
+            "./a.png",
+            "--delay",
+            "1/0",
+        ]);
+        assert!(result.is_err());
+        let result = PngMeArgs::try_parse_from(["pngme", "apng", "assemble", "./out.png"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    pub fn test_normalize_cgbi_args() {
+        let result =
+            PngMeArgs::parse_from(["pngme", "normalize-cgbi", "./icon.png", "-o", "./out.png"]);
+        if let PngMeArgs::NormalizeCgbi(x) = result {
+            assert_eq!(x.output, Some(PathBuf::from_str("./out.png").unwrap()));
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    pub fn test_diff_args() {
+        let result = PngMeArgs::parse_from(["pngme", "diff", "./a.png", "./b.png", "--pixels"]);
+        if let PngMeArgs::Diff(x) = result {
+            assert_eq!(x.new, PathBuf::from_str("./b.png").unwrap());
+            assert!(x.pixels);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    pub fn test_copy_chunks_args() {
+        let result = PngMeArgs::parse_from([
+            "pngme",
+            "copy-chunks",
+            "--from",
+            "./a.png",
+            "--to",
+            "./b.png",
+            "--types",
+            "iCCP,tEXt,ruSt",
+        ]);
+        if let PngMeArgs::CopyChunks(x) = result {
+            assert_eq!(x.types.len(), 3);
+            assert_eq!(x.types[0], ChunkType::iCCP);
+            assert!(!x.force);
+        } else {
+            panic!();
+        }
+        let result = PngMeArgs::try_parse_from([
+            "pngme",
+            "copy-chunks",
+            "--from",
+            "./a.png",
+            "--to",
+            "./b.png",
+            "--types",
+            "iCCP,tE",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    pub fn test_reorder_args() {
+        let result = PngMeArgs::parse_from(["pngme", "reorder", "./a.png", "--idat-size", "8192"]);
+        if let PngMeArgs::Reorder(x) = result {
+            assert_eq!(x.idat_size, 8192);
+            assert!(!x.keep_idat);
+            assert!(!x.keep_duplicates);
+        } else {
+            panic!();
+        }
+        let result = PngMeArgs::try_parse_from([
+            "pngme",
+            "reorder",
+            "./a.png",
+            "--keep-idat",
+            "--idat-size",
+            "8192",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    pub fn test_optimize_args() {
+        let result = PngMeArgs::parse_from(["pngme", "optimize", "./a.png"]);
+        if let PngMeArgs::Optimize(x) = result {
+            assert_eq!(x.strategies.len(), 7);
+            assert_eq!(x.levels, vec![6, 9]);
+            assert!(!x.reduce);
+        } else {
+            panic!();
+        }
+        let result = PngMeArgs::parse_from([
+            "pngme",
+            "optimize",
+            "./a.png",
+            "--strategies",
+            "brute,minsum",
+            "--levels",
+            "10",
+            "--reduce",
+        ]);
+        if let PngMeArgs::Optimize(x) = result {
+            assert_eq!(x.strategies, [Strategy::BruteForce, Strategy::MinimumSum]);
+            assert_eq!(x.levels, vec![10]);
+            assert!(x.reduce);
+        } else {
+            panic!();
+        }
+        for arg in [["--levels", "11"], ["--strategies", "fast"]] {
+            let result =
+                PngMeArgs::try_parse_from(["pngme", "optimize", "./a.png", arg[0], arg[1]]);
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    pub fn test_batch_args() {
+        let result = PngMeArgs::parse_from([
+            "pngme", "exif", "strip", "./a.png", "./b.png", "./assets", "-r", "-j", "4",
+        ]);
+        if let PngMeArgs::Exif(ExifArgs {
+            command: ExifCommand::Strip(x),
+        }) = result
+        {
+            let (paths, batch) = x.batch();
+            assert_eq!(paths.len(), 3);
+            assert_eq!(paths[0], Path::new("./a.png"));
+            assert!(batch.recursive);
+            assert_eq!(batch.jobs, Some(4));
+            assert_eq!(x.output(), None);
+        } else {
+            panic!();
+        }
+
+        let result = PngMeArgs::parse_from([
+            "pngme",
+            "copy-chunks",
+            "--from",
+            "a.png",
+            "--to",
+            "b.png",
+            "c.png",
+            "--types",
+            "tEXt",
+        ]);
+        if let PngMeArgs::CopyChunks(x) = result {
+            assert_eq!(
+                x.batch().0,
+                [PathBuf::from("b.png"), PathBuf::from("c.png")]
+            );
+            assert_eq!(x.output(), None);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    pub fn test_command_definition() {
+        use clap::CommandFactory;
+        PngMeArgs::command().debug_assert();
+    }
+
+    #[test]
+    pub fn test_limit_args() {
+        let result = PngMeArgs::parse_from([
+            "pngme",
+            "print",
+            "./dice.png",
+            "--untrusted",
+            "--max-chunk-count",
+            "5",
+        ]);
+
+        if let PngMeArgs::Print(x) = result {
+            let limits = Limits::from(&x.limits);
+            assert_eq!(limits.max_chunk_count, 5);
+            assert_eq!(limits.max_width, Limits::untrusted().max_width);
+        } else {
+            panic!();
+        }
+
+        let result = PngMeArgs::parse_from(["pngme", "print", "./dice.png"]);
+        if let PngMeArgs::Print(x) = result {
+            assert_eq!(Limits::from(&x.limits), Limits::default());
         } else {
             panic!();
         }
@@ -0,0 +1,147 @@
+use std::path::{Path, PathBuf};
+
+use derive_more::{Display, Error};
+use rayon::prelude::*;
+
+#[derive(PartialEq, Debug, Display, Error)]
+pub enum BatchError {
+    InvalidPattern,
+    NoMatchingFiles,
+    UnreadableDirectory,
+}
+
+/// File extensions picked up when walking directories.
+const EXTENSIONS: [&str; 4] = ["png", "apng", "mng", "jng"];
+
+fn is_image_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|x| x.to_str())
+            .is_some_and(|x| EXTENSIONS.iter().any(|e| x.eq_ignore_ascii_case(e)))
+}
+
+fn is_pattern(path: &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?', '['])
+}
+
+/// Image files in `dir`, including subdirectories when `recursive` is set.
+fn walk(dir: &Path, recursive: bool) -> Result<Vec<PathBuf>, BatchError> {
+    let depth = if recursive { usize::MAX } else { 1 };
+    let mut files = vec![];
+    for entry in walkdir::WalkDir::new(dir).max_depth(depth) {
+        let entry = entry.map_err(|_| BatchError::UnreadableDirectory)?;
+        if is_image_file(entry.path()) {
+            files.push(entry.into_path());
+        }
+    }
+    Ok(files)
+}
+
+/// Expands paths, directories and glob patterns into a sorted list of files without duplicates.
+///
+/// Plain paths are kept even if they do not exist, so commands such as `encode` can create them.
+pub fn expand(specs: &[PathBuf], recursive: bool) -> Result<Vec<PathBuf>, BatchError> {
+    let mut files = vec![];
+    for spec in specs {
+        if is_pattern(spec) {
+            let pattern = spec.to_str().ok_or(BatchError::InvalidPattern)?;
+            let paths = glob::glob(pattern).map_err(|_| BatchError::InvalidPattern)?;
+            for path in paths.flatten() {
+                if path.is_dir() {
+                    files.extend(walk(&path, recursive)?);
+                } else {
+                    files.push(path);
+                }
+            }
+        } else if spec.is_dir() {
+            files.extend(walk(spec, recursive)?);
+        } else {
+            files.push(spec.clone());
+        }
+    }
+    files.sort();
+    files.dedup();
+    if files.is_empty() {
+        Err(BatchError::NoMatchingFiles)
+    } else {
+        Ok(files)
+    }
+}
+
+/// Runs `f` on every file with `jobs` worker threads (0 picks one per CPU), returning results in input order.
+pub fn run<T, F>(files: &[PathBuf], jobs: usize, f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(&Path) -> T + Sync,
+{
+    let run_all = || files.par_iter().map(|x| f(x)).collect();
+    match rayon::ThreadPoolBuilder::new().num_threads(jobs).build() {
+        Ok(pool) => pool.install(run_all),
+        Err(_) => files.iter().map(|x| f(x)).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testing_tree() -> PathBuf {
+        let root = std::env::temp_dir().join(format!("pngme-batch-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("sub/deeper")).unwrap();
+        for path in [
+            "a.png",
+            "b.PNG",
+            "notes.txt",
+            "sub/c.png",
+            "sub/deeper/d.mng",
+        ] {
+            std::fs::write(root.join(path), b"").unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_expand() {
+        let root = testing_tree();
+        let names = |files: Vec<PathBuf>| -> Vec<String> {
+            files
+                .iter()
+                .map(|x| {
+                    x.strip_prefix(&root)
+                        .unwrap()
+                        .to_string_lossy()
+                        .replace('\\', "/")
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            names(expand(std::slice::from_ref(&root), false).unwrap()),
+            ["a.png", "b.PNG"]
+        );
+        assert_eq!(
+            names(expand(std::slice::from_ref(&root), true).unwrap()),
+            ["a.png", "b.PNG", "sub/c.png", "sub/deeper/d.mng"]
+        );
+        let pattern = root.join("*.png");
+        assert_eq!(
+            names(expand(&[pattern, root.join("a.png"), root.join("new.png")], false).unwrap()),
+            ["a.png", "new.png"]
+        );
+        assert_eq!(
+            expand(&[root.join("*.gif")], false),
+            Err(BatchError::NoMatchingFiles)
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_run_keeps_order() {
+        let files: Vec<PathBuf> = (0..20).map(|x| PathBuf::from(x.to_string())).collect();
+        let results = run(&files, 4, |path| path.to_string_lossy().to_string());
+        let expected: Vec<String> = (0..20).map(|x| x.to_string()).collect();
+        assert_eq!(results, expected);
+    }
+}
@@ -0,0 +1,127 @@
+use derive_more::{Display, Error};
+
+use crate::chunk_type::ChunkType;
+use crate::filter::{filter, unfilter, FilterError, FilterType};
+use crate::ihdr::ColourType;
+use crate::limits::Limits;
+use crate::png::{Png, PngError};
+use crate::zlib;
+
+#[derive(PartialEq, Debug, Display, Error)]
+pub enum CgbiError {
+    NotCgbi,
+    /// Only 8-bit truecolour images, with or without alpha, are converted.
+    UnsupportedFormat,
+    InvalidImageData(FilterError),
+    InvalidPng(PngError),
+}
+
+impl From<FilterError> for CgbiError {
+    fn from(e: FilterError) -> Self {
+        CgbiError::InvalidImageData(e)
+    }
+}
+
+impl From<PngError> for CgbiError {
+    fn from(e: PngError) -> Self {
+        CgbiError::InvalidPng(e)
+    }
+}
+
+/// Converts a CgBI file into a standard PNG: drops `CgBI`, swaps BGR(A) to RGB(A),
+/// undoes alpha premultiplication and rewraps the image data in zlib.
+pub fn normalize(png: &mut Png, limits: &Limits) -> Result<(), CgbiError> {
+    if !png.is_cgbi() {
+        return Err(CgbiError::NotCgbi);
+    }
+    png.remove_chunks_by_type(&ChunkType::CgBI);
+
+    let ihdr = png.ihdr()?;
+    let channels = match (ihdr.colour_type, ihdr.bit_depth) {
+        (ColourType::Truecolour, 8) => 3,
+        (ColourType::TruecolourAlpha, 8) => 4,
+        _ => return Err(CgbiError::UnsupportedFormat),
+    };
+
+    let filtered = zlib::inflate_raw(&png.image_data(), limits)?;
+    let mut raw = unfilter(&filtered, &ihdr)?;
+    for pixel in raw.chunks_exact_mut(channels) {
+        pixel.swap(0, 2);
+        if channels == 4 {
+            unpremultiply(pixel);
+        }
+    }
+    let data = zlib::deflate(&filter(&raw, &ihdr, FilterType::Paeth));
+    png.set_image_data(vec![data]);
+    Ok(())
+}
+
+fn unpremultiply(pixel: &mut [u8]) {
+    let alpha = pixel[3] as u32;
+    if alpha == 0 || alpha == 255 {
+        return;
+    }
+    for x in pixel[..3].iter_mut() {
+        *x = ((*x as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::ihdr::Ihdr;
+
+    fn cgbi_png(colour_type: ColourType, pixels: &[u8]) -> Png {
+        let ihdr = Ihdr {
+            width: 2,
+            height: 1,
+            bit_depth: 8,
+            colour_type,
+            interlaced: false,
+        };
+        let filtered = filter(pixels, &ihdr, FilterType::Sub);
+        let data = miniz_oxide::deflate::compress_to_vec(&filtered, zlib::DEFAULT_LEVEL);
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::CgBI, vec![0x50, 0, 0x20, 0x06]),
+            Chunk::new(ChunkType::IHDR, ihdr.as_bytes()),
+            Chunk::new(ChunkType::IDAT, data),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ])
+    }
+
+    fn pixels(png: &Png) -> Vec<u8> {
+        let filtered = zlib::inflate(&png.image_data(), &Limits::default()).unwrap();
+        unfilter(&filtered, &png.ihdr().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_normalize_bgra() {
+        // Blue-green-red-alpha, with the second pixel premultiplied by half opacity.
+        let mut png = cgbi_png(
+            ColourType::TruecolourAlpha,
+            &[30, 20, 10, 255, 50, 0, 100, 128],
+        );
+        normalize(&mut png, &Limits::default()).unwrap();
+        assert_eq!(*png.chunks()[0].chunk_type(), ChunkType::IHDR);
+        assert!(png.verify_crcs().is_ok());
+        assert_eq!(pixels(&png), vec![10, 20, 30, 255, 199, 0, 100, 128]);
+    }
+
+    #[test]
+    fn test_normalize_errors() {
+        let mut png = cgbi_png(ColourType::Truecolour, &[3, 2, 1, 6, 5, 4]);
+        normalize(&mut png, &Limits::default()).unwrap();
+        assert_eq!(pixels(&png), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(
+            normalize(&mut png, &Limits::default()),
+            Err(CgbiError::NotCgbi)
+        );
+
+        let mut png = cgbi_png(ColourType::Greyscale, &[0, 0]);
+        assert_eq!(
+            normalize(&mut png, &Limits::default()),
+            Err(CgbiError::UnsupportedFormat)
+        );
+    }
+}
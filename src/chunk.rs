@@ -1,36 +1,43 @@
 use std::{fmt::Display, string::FromUtf8Error};
 
-use crc::{Crc, CRC_32_ISO_HDLC};
+use crc::{Crc, Table, CRC_32_ISO_HDLC};
+use derive_more::{Display, Error};
 
+use crate::chunk_ref::ChunkRef;
 use crate::chunk_type::ChunkType;
 
-#[derive(PartialEq, Debug)]
+/// Shared CRC-32 engine; its slice-by-16 lookup table is built at compile time.
+static CRC: Crc<u32, Table<16>> = Crc::<u32, Table<16>>::new(&CRC_32_ISO_HDLC);
+
+#[derive(PartialEq, Debug, Display, Error)]
 pub enum ChunkError {
-    CreationError,
+    UnexpectedEof,
+    InvalidChunkType,
+    CrcMismatch,
 }
 
+#[derive(PartialEq, Debug)]
 pub struct Chunk {
     _chunk_type: ChunkType,
     _data: Vec<u8>,
-    _length: usize,
     _crc: u32,
 }
 
 impl Chunk {
     pub fn new(chunk_type: ChunkType, data: Vec<u8>) -> Chunk {
-        let bytes = Chunk::_as_bytes(&chunk_type, data.as_ref());
-        let crc = Crc::<u32>::new(&CRC_32_ISO_HDLC);
-        let length = data.len();
-
+        let crc = Chunk::checksum(&chunk_type, data.as_ref());
+        Chunk::with_crc(chunk_type, data, crc)
+    }
+    /// Builds a chunk that keeps `crc` as stored, even if it does not match the data.
+    pub fn with_crc(chunk_type: ChunkType, data: Vec<u8>, crc: u32) -> Chunk {
         Chunk {
             _chunk_type: chunk_type,
             _data: data,
-            _length: length,
-            _crc: crc.checksum(bytes.as_slice()),
+            _crc: crc,
         }
     }
     pub fn length(&self) -> u32 {
-        self._length as u32
+        self._data.len() as u32
     }
     pub fn chunk_type(&self) -> &ChunkType {
         &self._chunk_type
@@ -41,27 +48,48 @@ impl Chunk {
     pub fn crc(&self) -> u32 {
         self._crc
     }
+    pub fn computed_crc(&self) -> u32 {
+        Chunk::checksum(self.chunk_type(), self.data())
+    }
+    pub fn is_crc_valid(&self) -> bool {
+        self.crc() == self.computed_crc()
+    }
+    pub fn fix_crc(&mut self) {
+        self._crc = self.computed_crc();
+    }
+    /// Replaces the data; the CRC is recomputed so the chunk stays consistent.
+    pub fn set_data(&mut self, data: Vec<u8>) {
+        self._data = data;
+        self.fix_crc();
+    }
+    /// Edits the data in place; the CRC is recomputed afterwards.
+    pub fn map_data<F: FnOnce(&mut Vec<u8>)>(&mut self, f: F) {
+        f(&mut self._data);
+        self.fix_crc();
+    }
+    pub fn set_chunk_type(&mut self, chunk_type: ChunkType) {
+        self._chunk_type = chunk_type;
+        self.fix_crc();
+    }
+    pub fn into_data(self) -> Vec<u8> {
+        self._data
+    }
     pub fn data_as_string(&self) -> Result<String, FromUtf8Error> {
         String::from_utf8(self._data.clone())
     }
-    fn _as_bytes(chunk_type: &ChunkType, data: &[u8]) -> Vec<u8> {
-        chunk_type
-            .bytes()
-            .iter()
-            .chain(data.iter())
-            .copied()
-            .collect()
+    pub(crate) fn checksum(chunk_type: &ChunkType, data: &[u8]) -> u32 {
+        let mut digest = CRC.digest();
+        digest.update(&chunk_type.bytes());
+        digest.update(data);
+        digest.finalize()
     }
     pub fn as_bytes(&self) -> Vec<u8> {
-        let bytes = Chunk::_as_bytes(self.chunk_type(), self.data());
-        let crc = self.crc();
-        self.length()
-            .to_be_bytes()
-            .iter()
-            .chain(bytes.iter())
-            .chain(crc.to_be_bytes().iter())
-            .copied()
-            .collect()
+        let mut bytes = Vec::with_capacity(self._data.len() + 12);
+        bytes.extend_from_slice(&self.length().to_be_bytes());
+        bytes.extend_from_slice(&self.chunk_type().bytes());
+        bytes.extend_from_slice(self.data());
+        bytes.extend_from_slice(&self.crc().to_be_bytes());
+        bytes
     }
 }
 
@@ -69,31 +97,7 @@ impl TryFrom<&[u8]> for Chunk {
     type Error = ChunkError;
 
     fn try_from(arr: &[u8]) -> Result<Self, Self::Error> {
-        let data_length = u32::from_be_bytes(arr[0..4].try_into().unwrap()) as usize;
-        let chunk_type_bytes: [u8; 4] = arr[4..8].try_into().unwrap();
-        let mut message_bytes = Vec::new();
-        for i in 0..data_length {
-            let idx = 8 + i;
-            message_bytes.push(arr[idx]);
-        }
-
-        let crc = u32::from_be_bytes([
-            arr[8 + data_length + 0],
-            arr[8 + data_length + 1],
-            arr[8 + data_length + 2],
-            arr[8 + data_length + 3],
-        ]);
-
-        if let Ok(x) = ChunkType::try_from(chunk_type_bytes) {
-            let chunk = Chunk::new(x, message_bytes);
-            if chunk.crc() == crc {
-                Ok(chunk)
-            } else {
-                Err(ChunkError::CreationError)
-            }
-        } else {
-            Err(ChunkError::CreationError)
-        }
+        Ok(ChunkRef::try_from(arr)?.to_chunk())
     }
 }
 
@@ -212,6 +216,51 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    fn test_truncated_chunk_from_bytes() {
+        let chunk_data: Vec<u8> = testing_chunk().as_bytes();
+
+        let chunk = Chunk::try_from(&chunk_data[..chunk_data.len() - 1]);
+        assert_eq!(chunk.err(), Some(ChunkError::UnexpectedEof));
+
+        let chunk = Chunk::try_from(&chunk_data[..5]);
+        assert_eq!(chunk.err(), Some(ChunkError::UnexpectedEof));
+    }
+
+    #[test]
+    fn test_chunk_with_bad_crc() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let data = "This is where your secret message will be!"
+            .as_bytes()
+            .to_vec();
+        let mut chunk = Chunk::with_crc(chunk_type, data, 2882656333);
+        assert!(!chunk.is_crc_valid());
+
+        chunk.fix_crc();
+        assert!(chunk.is_crc_valid());
+        assert_eq!(chunk.crc(), 2882656334);
+    }
+
+    #[test]
+    fn test_chunk_mutation_keeps_crc() {
+        let mut chunk = testing_chunk();
+        chunk.set_data(b"Short".to_vec());
+        assert_eq!(chunk.length(), 5);
+        assert!(chunk.is_crc_valid());
+
+        chunk.map_data(|data| data.extend_from_slice(b" and longer"));
+        assert_eq!(chunk.data_as_string().unwrap(), "Short and longer");
+        assert_eq!(chunk.length(), 16);
+        assert!(chunk.is_crc_valid());
+
+        chunk.set_chunk_type(ChunkType::tEXt);
+        assert_eq!(chunk.chunk_type(), &ChunkType::tEXt);
+        assert_eq!(
+            chunk,
+            Chunk::new(ChunkType::tEXt, b"Short and longer".to_vec())
+        );
+    }
+
     #[test]
     pub fn test_chunk_trait_impls() {
         let data_length: u32 = 42;
@@ -0,0 +1,157 @@
+use std::{fmt::Display, str::Utf8Error};
+
+use crate::chunk::{Chunk, ChunkError};
+use crate::chunk_type::ChunkType;
+
+/// A chunk whose data borrows from the buffer it was parsed from.
+#[derive(PartialEq, Debug)]
+pub struct ChunkRef<'a> {
+    _chunk_type: ChunkType,
+    _data: &'a [u8],
+    _crc: u32,
+}
+
+impl<'a> ChunkRef<'a> {
+    /// Parses the leading chunk of `arr` without verifying its CRC.
+    pub fn parse(arr: &'a [u8]) -> Result<ChunkRef<'a>, ChunkError> {
+        if arr.len() < 12 {
+            return Err(ChunkError::UnexpectedEof);
+        }
+        let data_length = u32::from_be_bytes(arr[0..4].try_into().unwrap()) as usize;
+        let chunk_type_bytes: [u8; 4] = arr[4..8].try_into().unwrap();
+        let chunk_type =
+            ChunkType::try_from(chunk_type_bytes).map_err(|_| ChunkError::InvalidChunkType)?;
+
+        if arr.len() - 12 < data_length {
+            return Err(ChunkError::UnexpectedEof);
+        }
+        let data = &arr[8..8 + data_length];
+        let crc = u32::from_be_bytes(arr[8 + data_length..12 + data_length].try_into().unwrap());
+
+        Ok(ChunkRef {
+            _chunk_type: chunk_type,
+            _data: data,
+            _crc: crc,
+        })
+    }
+    pub(crate) fn from_parts(chunk_type: ChunkType, data: &'a [u8], crc: u32) -> ChunkRef<'a> {
+        ChunkRef {
+            _chunk_type: chunk_type,
+            _data: data,
+            _crc: crc,
+        }
+    }
+    pub fn length(&self) -> u32 {
+        self._data.len() as u32
+    }
+    /// Size of the whole chunk including the length, type and CRC fields.
+    pub fn total_length(&self) -> usize {
+        self._data.len() + 12
+    }
+    pub fn chunk_type(&self) -> &ChunkType {
+        &self._chunk_type
+    }
+    pub fn data(&self) -> &'a [u8] {
+        self._data
+    }
+    pub fn crc(&self) -> u32 {
+        self._crc
+    }
+    pub fn computed_crc(&self) -> u32 {
+        Chunk::checksum(self.chunk_type(), self.data())
+    }
+    pub fn is_crc_valid(&self) -> bool {
+        self.crc() == self.computed_crc()
+    }
+    pub fn fix_crc(&mut self) {
+        self._crc = self.computed_crc();
+    }
+    pub fn data_as_str(&self) -> Result<&'a str, Utf8Error> {
+        std::str::from_utf8(self._data)
+    }
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::with_crc(self._chunk_type, self._data.to_vec(), self._crc)
+    }
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.total_length());
+        bytes.extend_from_slice(&self.length().to_be_bytes());
+        bytes.extend_from_slice(&self.chunk_type().bytes());
+        bytes.extend_from_slice(self.data());
+        bytes.extend_from_slice(&self.crc().to_be_bytes());
+        bytes
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for ChunkRef<'a> {
+    type Error = ChunkError;
+
+    fn try_from(arr: &'a [u8]) -> Result<Self, Self::Error> {
+        let chunk = ChunkRef::parse(arr)?;
+        if chunk.is_crc_valid() {
+            Ok(chunk)
+        } else {
+            Err(ChunkError::CrcMismatch)
+        }
+    }
+}
+
+impl Display for ChunkRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(self.data()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn testing_bytes() -> Vec<u8> {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let data = "This is where your secret message will be!"
+            .as_bytes()
+            .to_vec();
+        Chunk::new(chunk_type, data).as_bytes()
+    }
+
+    #[test]
+    fn test_chunk_ref_borrows_data() {
+        let bytes = testing_bytes();
+        let chunk = ChunkRef::try_from(bytes.as_ref()).unwrap();
+
+        assert_eq!(chunk.length(), 42);
+        assert_eq!(chunk.total_length(), bytes.len());
+        assert_eq!(chunk.chunk_type().to_string(), "RuSt");
+        assert_eq!(chunk.crc(), 2882656334);
+        assert_eq!(
+            chunk.data_as_str().unwrap(),
+            "This is where your secret message will be!"
+        );
+        assert_eq!(chunk.data().as_ptr(), bytes[8..].as_ptr());
+        assert_eq!(chunk.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_chunk_ref_to_chunk() {
+        let bytes = testing_bytes();
+        let chunk = ChunkRef::try_from(bytes.as_ref()).unwrap().to_chunk();
+        assert_eq!(chunk, Chunk::try_from(bytes.as_ref()).unwrap());
+    }
+
+    #[test]
+    fn test_chunk_ref_bad_crc() {
+        let mut bytes = testing_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+
+        assert_eq!(
+            ChunkRef::try_from(bytes.as_ref()).err(),
+            Some(ChunkError::CrcMismatch)
+        );
+
+        let mut chunk = ChunkRef::parse(bytes.as_ref()).unwrap();
+        assert!(!chunk.is_crc_valid());
+        chunk.fix_crc();
+        assert_eq!(chunk.crc(), 2882656334);
+    }
+}
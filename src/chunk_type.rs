@@ -8,13 +8,85 @@ pub enum ChunkTypeError {
     NotASCIILetters,
 }
 
-#[derive(PartialEq, Debug)]
+/// Where the specification allows a chunk to appear, in file order.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Placement {
+    Header,
+    BeforePalette,
+    Palette,
+    /// After `PLTE`, if present, and before `IDAT`.
+    AfterPalette,
+    BeforeImageData,
+    ImageData,
+    /// Anywhere between the header and the end chunk.
+    Anywhere,
+    End,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
 pub struct ChunkType(u8, u8, u8, u8);
 
-const CHECK_BIT: u8 = 32;
+/// The bit of each chunk type byte that carries its property; it is the ASCII case bit.
+pub const CHECK_BIT: u8 = 32;
 
+#[allow(non_upper_case_globals)]
+#[allow(
+    clippy::needless_bool,
+    clippy::if_same_then_else,
+    clippy::manual_range_contains
+)]
 impl ChunkType {
-    pub fn bytes(&self) -> [u8; 4] {
+    pub const IHDR: ChunkType = ChunkType::known(*b"IHDR");
+    pub const PLTE: ChunkType = ChunkType::known(*b"PLTE");
+    pub const IDAT: ChunkType = ChunkType::known(*b"IDAT");
+    pub const IEND: ChunkType = ChunkType::known(*b"IEND");
+    pub const tRNS: ChunkType = ChunkType::known(*b"tRNS");
+    pub const cHRM: ChunkType = ChunkType::known(*b"cHRM");
+    pub const gAMA: ChunkType = ChunkType::known(*b"gAMA");
+    pub const iCCP: ChunkType = ChunkType::known(*b"iCCP");
+    pub const sBIT: ChunkType = ChunkType::known(*b"sBIT");
+    pub const sRGB: ChunkType = ChunkType::known(*b"sRGB");
+    pub const cICP: ChunkType = ChunkType::known(*b"cICP");
+    pub const mDCv: ChunkType = ChunkType::known(*b"mDCv");
+    pub const cLLi: ChunkType = ChunkType::known(*b"cLLi");
+    pub const tEXt: ChunkType = ChunkType::known(*b"tEXt");
+    pub const zTXt: ChunkType = ChunkType::known(*b"zTXt");
+    pub const iTXt: ChunkType = ChunkType::known(*b"iTXt");
+    pub const bKGD: ChunkType = ChunkType::known(*b"bKGD");
+    pub const hIST: ChunkType = ChunkType::known(*b"hIST");
+    pub const pHYs: ChunkType = ChunkType::known(*b"pHYs");
+    pub const sPLT: ChunkType = ChunkType::known(*b"sPLT");
+    pub const eXIf: ChunkType = ChunkType::known(*b"eXIf");
+    pub const tIME: ChunkType = ChunkType::known(*b"tIME");
+    pub const acTL: ChunkType = ChunkType::known(*b"acTL");
+    pub const fcTL: ChunkType = ChunkType::known(*b"fcTL");
+    pub const fdAT: ChunkType = ChunkType::known(*b"fdAT");
+    pub const MHDR: ChunkType = ChunkType::known(*b"MHDR");
+    pub const MEND: ChunkType = ChunkType::known(*b"MEND");
+    pub const JHDR: ChunkType = ChunkType::known(*b"JHDR");
+    pub const JDAT: ChunkType = ChunkType::known(*b"JDAT");
+    pub const JSEP: ChunkType = ChunkType::known(*b"JSEP");
+    pub const CgBI: ChunkType = ChunkType::known(*b"CgBI");
+
+    /// Builds a chunk type, checking that every byte is an ASCII letter.
+    pub const fn new(bytes: [u8; 4]) -> Result<ChunkType, ChunkTypeError> {
+        let mut i = 0;
+        while i < 4 {
+            if !bytes[i].is_ascii_alphabetic() {
+                return Err(ChunkTypeError::NotASCIILetters);
+            }
+            i += 1;
+        }
+        Ok(ChunkType(bytes[0], bytes[1], bytes[2], bytes[3]))
+    }
+    /// Like [`ChunkType::new`], but fails at compile time when used in a constant.
+    pub const fn known(bytes: [u8; 4]) -> ChunkType {
+        match ChunkType::new(bytes) {
+            Ok(x) => x,
+            Err(_) => panic!("chunk type must consist of ASCII letters"),
+        }
+    }
+    pub const fn bytes(&self) -> [u8; 4] {
         [self.0, self.1, self.2, self.3]
     }
     pub fn is_valid(&self) -> bool {
@@ -24,34 +96,95 @@ impl ChunkType {
             true
         }
     }
-    pub fn is_critical(&self) -> bool {
+    pub const fn is_critical(&self) -> bool {
         if (self.0 & CHECK_BIT) == 0 {
             true
         } else {
             false
         }
     }
-    pub fn is_public(&self) -> bool {
+    pub const fn is_public(&self) -> bool {
         if (self.1 & CHECK_BIT) == 0 {
             true
         } else {
             false
         }
     }
-    pub fn is_reserved_bit_valid(&self) -> bool {
+    pub const fn is_reserved_bit_valid(&self) -> bool {
         if (self.2 & CHECK_BIT) == 0 {
             true
         } else {
             false
         }
     }
-    pub fn is_safe_to_copy(&self) -> bool {
+    pub const fn is_safe_to_copy(&self) -> bool {
         if (self.3 & CHECK_BIT) > 0 {
             true
         } else {
             false
         }
     }
+    pub const fn with_critical(self, critical: bool) -> ChunkType {
+        ChunkType(set_property(self.0, !critical), self.1, self.2, self.3)
+    }
+    pub const fn with_public(self, public: bool) -> ChunkType {
+        ChunkType(self.0, set_property(self.1, !public), self.2, self.3)
+    }
+    pub const fn with_reserved_bit_valid(self, valid: bool) -> ChunkType {
+        ChunkType(self.0, self.1, set_property(self.2, !valid), self.3)
+    }
+    pub const fn with_safe_to_copy(self, safe: bool) -> ChunkType {
+        ChunkType(self.0, self.1, self.2, set_property(self.3, safe))
+    }
+    /// The closest chunk type that is valid, ancillary and private, keeping the safe-to-copy bit.
+    pub const fn nearest_private(self) -> ChunkType {
+        self.with_critical(false)
+            .with_public(false)
+            .with_reserved_bit_valid(true)
+    }
+    /// A short description if this is a chunk type registered in the PNG specification or its extensions.
+    pub fn registered_name(&self) -> Option<&'static str> {
+        REGISTERED
+            .iter()
+            .find(|(chunk_type, _)| chunk_type == self)
+            .map(|(_, name)| *name)
+    }
+    /// Where this chunk type may appear; unknown types may appear anywhere.
+    pub fn placement(&self) -> Placement {
+        match *self {
+            ChunkType::IHDR | ChunkType::CgBI => Placement::Header,
+            ChunkType::cHRM
+            | ChunkType::gAMA
+            | ChunkType::iCCP
+            | ChunkType::sBIT
+            | ChunkType::sRGB
+            | ChunkType::cICP
+            | ChunkType::mDCv
+            | ChunkType::cLLi => Placement::BeforePalette,
+            ChunkType::PLTE => Placement::Palette,
+            ChunkType::tRNS | ChunkType::bKGD | ChunkType::hIST => Placement::AfterPalette,
+            ChunkType::pHYs | ChunkType::sPLT | ChunkType::eXIf | ChunkType::acTL => {
+                Placement::BeforeImageData
+            }
+            ChunkType::IDAT => Placement::ImageData,
+            ChunkType::IEND => Placement::End,
+            _ => Placement::Anywhere,
+        }
+    }
+    /// Whether a file may hold more than one chunk of this type; unknown types may repeat.
+    pub fn allows_multiple(&self) -> bool {
+        REGISTERED.iter().all(|(chunk_type, _)| chunk_type != self)
+            || matches!(
+                *self,
+                ChunkType::IDAT
+                    | ChunkType::sPLT
+                    | ChunkType::tEXt
+                    | ChunkType::zTXt
+                    | ChunkType::iTXt
+                    | ChunkType::fcTL
+                    | ChunkType::fdAT
+            )
+    }
     pub fn is_valid_ascii(&self) -> bool {
         for u in self.bytes().iter() {
             let c = (*u) as char;
@@ -65,17 +198,95 @@ impl ChunkType {
         }
         true
     }
+    /// Derives a valid ancillary, private, safe-to-copy chunk type from `key`, skipping every
+    /// type for which `is_taken` returns true. The same key and taken set give the same type.
+    pub fn derive_private<F: Fn(&ChunkType) -> bool>(key: &[u8], is_taken: F) -> ChunkType {
+        let mut hash = fnv1a(key);
+        loop {
+            let letter = |shift: u32| (hash >> shift) % 26;
+            let chunk_type = ChunkType(
+                b'a' + letter(0) as u8,
+                b'a' + letter(8) as u8,
+                b'A' + letter(16) as u8,
+                b'a' + letter(24) as u8,
+            );
+            if !is_taken(&chunk_type) {
+                return chunk_type;
+            }
+            hash = fnv1a(&hash.to_be_bytes());
+        }
+    }
+}
+
+const REGISTERED: &[(ChunkType, &str)] = &[
+    (ChunkType::IHDR, "image header"),
+    (ChunkType::PLTE, "palette"),
+    (ChunkType::IDAT, "image data"),
+    (ChunkType::IEND, "image trailer"),
+    (ChunkType::tRNS, "transparency"),
+    (ChunkType::cHRM, "primary chromaticities and white point"),
+    (ChunkType::gAMA, "image gamma"),
+    (ChunkType::iCCP, "embedded ICC profile"),
+    (ChunkType::sBIT, "significant bits"),
+    (ChunkType::sRGB, "standard RGB colour space"),
+    (ChunkType::cICP, "coding-independent code points"),
+    (ChunkType::mDCv, "mastering display colour volume"),
+    (ChunkType::cLLi, "content light level information"),
+    (ChunkType::tEXt, "textual data"),
+    (ChunkType::zTXt, "compressed textual data"),
+    (ChunkType::iTXt, "international textual data"),
+    (ChunkType::bKGD, "background colour"),
+    (ChunkType::hIST, "image histogram"),
+    (ChunkType::pHYs, "physical pixel dimensions"),
+    (ChunkType::sPLT, "suggested palette"),
+    (ChunkType::eXIf, "exchangeable image file profile"),
+    (ChunkType::tIME, "image last-modification time"),
+    (ChunkType::acTL, "animation control"),
+    (ChunkType::fcTL, "frame control"),
+    (ChunkType::fdAT, "frame data"),
+    (ChunkType::MHDR, "MNG header"),
+    (ChunkType::MEND, "MNG trailer"),
+    (ChunkType::JHDR, "JNG header"),
+    (ChunkType::JDAT, "JNG image data"),
+    (ChunkType::JSEP, "JNG 8-bit/12-bit image separator"),
+    (ChunkType::known(*b"IJNG"), "JNG image data in MNG"),
+    (ChunkType::known(*b"LOOP"), "MNG loop start"),
+    (ChunkType::known(*b"ENDL"), "MNG loop end"),
+    (ChunkType::known(*b"DEFI"), "MNG object definition"),
+    (ChunkType::known(*b"FRAM"), "MNG frame definition"),
+    (ChunkType::known(*b"TERM"), "MNG termination action"),
+    (ChunkType::known(*b"BACK"), "MNG background"),
+    (ChunkType::CgBI, "Apple iOS optimised PNG marker"),
+    (ChunkType::known(*b"oFFs"), "image offset"),
+    (ChunkType::known(*b"pCAL"), "pixel value calibration"),
+    (
+        ChunkType::known(*b"sCAL"),
+        "physical scale of image subject",
+    ),
+    (ChunkType::known(*b"gIFg"), "GIF graphic control extension"),
+    (ChunkType::known(*b"gIFx"), "GIF application extension"),
+    (ChunkType::known(*b"sTER"), "stereo image indicator"),
+    (ChunkType::known(*b"dSIG"), "digital signature"),
+];
+
+const fn set_property(byte: u8, lowercase: bool) -> u8 {
+    if lowercase {
+        byte | CHECK_BIT
+    } else {
+        byte & !CHECK_BIT
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, b| {
+        (hash ^ *b as u32).wrapping_mul(0x0100_0193)
+    })
 }
 
 impl TryFrom<[u8; 4]> for ChunkType {
     type Error = ChunkTypeError;
     fn try_from(value: [u8; 4]) -> Result<Self, Self::Error> {
-        let v = ChunkType(value[0], value[1], value[2], value[3]);
-        if v.is_valid_ascii() {
-            Ok(v)
-        } else {
-            Err(ChunkTypeError::NotASCIILetters)
-        }
+        ChunkType::new(value)
     }
 }
 
@@ -199,4 +410,77 @@ mod tests {
         let _chunk_string = format!("{}", chunk_type_1);
         let _are_chunks_equal = chunk_type_1 == chunk_type_2;
     }
+
+    #[test]
+    pub fn test_known_chunk_types() {
+        assert_eq!(ChunkType::IHDR, ChunkType::from_str("IHDR").unwrap());
+        assert_eq!(ChunkType::tEXt.to_string(), "tEXt");
+        assert!(ChunkType::IDAT.is_critical());
+        assert!(!ChunkType::tIME.is_critical());
+        assert_eq!(
+            ChunkType::new(*b"ru1t"),
+            Err(ChunkTypeError::NotASCIILetters)
+        );
+    }
+
+    #[test]
+    pub fn test_chunk_type_as_hash_key() {
+        use std::collections::HashMap;
+
+        let mut counts: HashMap<ChunkType, usize> = HashMap::new();
+        for chunk_type in [ChunkType::IDAT, ChunkType::tEXt, ChunkType::IDAT] {
+            *counts.entry(chunk_type).or_default() += 1;
+        }
+        assert_eq!(counts[&ChunkType::IDAT], 2);
+        assert_eq!(counts[&ChunkType::tEXt], 1);
+    }
+
+    #[test]
+    pub fn test_derive_private_chunk_type() {
+        let chunk_type = ChunkType::derive_private(b"key", |_| false);
+        assert!(chunk_type.is_valid());
+        assert!(!chunk_type.is_critical());
+        assert!(!chunk_type.is_public());
+        assert!(chunk_type.is_safe_to_copy());
+        assert_eq!(chunk_type, ChunkType::derive_private(b"key", |_| false));
+
+        let other = ChunkType::derive_private(b"key", |x| *x == chunk_type);
+        assert_ne!(other, chunk_type);
+        assert!(other.is_valid() && !other.is_critical() && other.is_safe_to_copy());
+    }
+
+    #[test]
+    pub fn test_chunk_type_with_properties() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        assert_eq!(chunk.with_critical(false).to_string(), "ruSt");
+        assert_eq!(chunk.with_public(true).to_string(), "RUSt");
+        assert_eq!(chunk.with_reserved_bit_valid(false).to_string(), "Rust");
+        assert_eq!(chunk.with_safe_to_copy(false).to_string(), "RuST");
+        assert_eq!(chunk.with_critical(true), chunk);
+    }
+
+    #[test]
+    pub fn test_nearest_private_chunk_type() {
+        let chunk = ChunkType::from_str("RUst").unwrap().nearest_private();
+        assert_eq!(chunk.to_string(), "ruSt");
+        assert!(chunk.is_valid() && !chunk.is_critical() && !chunk.is_public());
+    }
+
+    #[test]
+    pub fn test_placement() {
+        assert_eq!(ChunkType::iCCP.placement(), Placement::BeforePalette);
+        assert_eq!(ChunkType::tRNS.placement(), Placement::AfterPalette);
+        assert_eq!(ChunkType::tEXt.placement(), Placement::Anywhere);
+        assert!(Placement::BeforeImageData < Placement::ImageData);
+        assert!(ChunkType::tEXt.allows_multiple());
+        assert!(ChunkType::from_str("ruSt").unwrap().allows_multiple());
+        assert!(!ChunkType::iCCP.allows_multiple());
+    }
+
+    #[test]
+    pub fn test_registered_name() {
+        assert_eq!(ChunkType::IHDR.registered_name(), Some("image header"));
+        assert_eq!(ChunkType::MEND.registered_name(), Some("MNG trailer"));
+        assert_eq!(ChunkType::from_str("ruSt").unwrap().registered_name(), None);
+    }
 }
@@ -0,0 +1,232 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::marker::PhantomData;
+
+use derive_more::{Display, Error};
+
+use crate::ancillary::{
+    Bkgd, Chrm, Cicp, Clli, Exif, Gama, Hist, Iccp, Itxt, Mdcv, Phys, Sbit, Splt, Srgb, Text, Time,
+    Trns,
+};
+use crate::apng::{Actl, Fctl, Fdat};
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::ihdr::{Ihdr, IhdrError};
+use crate::mng::{Jhdr, Mhdr};
+use crate::palette::Palette;
+
+#[derive(PartialEq, Debug, Display, Error)]
+pub enum CodecError {
+    InvalidLength,
+    InvalidValue,
+    InvalidKeyword,
+    InvalidIhdr(IhdrError),
+    WrongType,
+}
+
+impl From<IhdrError> for CodecError {
+    fn from(e: IhdrError) -> Self {
+        CodecError::InvalidIhdr(e)
+    }
+}
+
+/// A decoded chunk value that can be printed and downcast back to its concrete type.
+pub trait Describe: Display + Debug {
+    fn as_any(&self) -> &dyn Any;
+}
+
+// Bounded on `ChunkData` so `Box<dyn Describe>` does not itself become a `Describe`.
+impl<T: ChunkData> Describe for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Converts the data of one chunk type to and from a typed value.
+pub trait ChunkCodec: Send + Sync {
+    fn chunk_type(&self) -> ChunkType;
+    fn decode(&self, data: &[u8]) -> Result<Box<dyn Describe>, CodecError>;
+    fn encode(&self, value: &dyn Describe) -> Result<Vec<u8>, CodecError>;
+}
+
+/// A typed chunk payload; every implementor gets a [`ChunkCodec`] through [`Registry::register_type`].
+pub trait ChunkData: Display + Debug + Sized + 'static {
+    const CHUNK_TYPE: ChunkType;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError>;
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError>;
+
+    fn from_chunk(chunk: &Chunk) -> Result<Self, CodecError> {
+        if *chunk.chunk_type() == Self::CHUNK_TYPE {
+            Self::from_bytes(chunk.data())
+        } else {
+            Err(CodecError::WrongType)
+        }
+    }
+    fn to_chunk(&self) -> Result<Chunk, CodecError> {
+        Ok(Chunk::new(Self::CHUNK_TYPE, self.to_bytes()?))
+    }
+}
+
+struct TypedCodec<T>(PhantomData<fn() -> T>);
+
+impl<T: ChunkData> ChunkCodec for TypedCodec<T> {
+    fn chunk_type(&self) -> ChunkType {
+        T::CHUNK_TYPE
+    }
+    fn decode(&self, data: &[u8]) -> Result<Box<dyn Describe>, CodecError> {
+        Ok(Box::new(T::from_bytes(data)?))
+    }
+    fn encode(&self, value: &dyn Describe) -> Result<Vec<u8>, CodecError> {
+        match value.as_any().downcast_ref::<T>() {
+            Some(x) => x.to_bytes(),
+            None => Err(CodecError::WrongType),
+        }
+    }
+}
+
+/// Maps chunk types to the codecs used to decode them.
+pub struct Registry {
+    _codecs: HashMap<ChunkType, Box<dyn ChunkCodec>>,
+}
+
+impl Registry {
+    /// A registry without any codecs.
+    pub fn empty() -> Registry {
+        Registry {
+            _codecs: HashMap::new(),
+        }
+    }
+    /// Registers `codec`, replacing any codec previously registered for the same chunk type.
+    pub fn register(&mut self, codec: Box<dyn ChunkCodec>) {
+        self._codecs.insert(codec.chunk_type(), codec);
+    }
+    pub fn register_type<T: ChunkData>(&mut self) {
+        self.register(Box::new(TypedCodec::<T>(PhantomData)));
+    }
+    pub fn get(&self, chunk_type: &ChunkType) -> Option<&dyn ChunkCodec> {
+        self._codecs.get(chunk_type).map(|x| x.as_ref())
+    }
+    /// Decodes `data` if a codec is registered for `chunk_type`.
+    pub fn decode(
+        &self,
+        chunk_type: &ChunkType,
+        data: &[u8],
+    ) -> Option<Result<Box<dyn Describe>, CodecError>> {
+        self.get(chunk_type).map(|codec| codec.decode(data))
+    }
+}
+
+impl Default for Registry {
+    /// A registry with codecs for every chunk type pngme understands.
+    fn default() -> Self {
+        let mut registry = Registry::empty();
+        registry.register_type::<Ihdr>();
+        registry.register_type::<Palette>();
+        registry.register_type::<Gama>();
+        registry.register_type::<Chrm>();
+        registry.register_type::<Srgb>();
+        registry.register_type::<Iccp>();
+        registry.register_type::<Phys>();
+        registry.register_type::<Sbit>();
+        registry.register_type::<Bkgd>();
+        registry.register_type::<Trns>();
+        registry.register_type::<Hist>();
+        registry.register_type::<Splt>();
+        registry.register_type::<Time>();
+        registry.register_type::<Exif>();
+        registry.register_type::<Cicp>();
+        registry.register_type::<Mdcv>();
+        registry.register_type::<Clli>();
+        registry.register_type::<Text>();
+        registry.register_type::<Itxt>();
+        registry.register_type::<Actl>();
+        registry.register_type::<Fctl>();
+        registry.register_type::<Fdat>();
+        registry.register_type::<Mhdr>();
+        registry.register_type::<Jhdr>();
+        registry
+    }
+}
+
+impl ChunkData for Ihdr {
+    const CHUNK_TYPE: ChunkType = ChunkType::IHDR;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        Ok(Ihdr::try_from(data)?)
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        Ok(self.as_bytes())
+    }
+}
+
+impl Display for Ihdr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}x{}, {}-bit {:?}{}",
+            self.width,
+            self.height,
+            self.bit_depth,
+            self.colour_type,
+            if self.interlaced { ", interlaced" } else { "" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(PartialEq, Debug, derive_more::Display)]
+    #[display(fmt = "answer {}", _0)]
+    struct Answer(u8);
+
+    impl ChunkData for Answer {
+        const CHUNK_TYPE: ChunkType = ChunkType::known(*b"anSw");
+
+        fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+            match data {
+                [x] => Ok(Answer(*x)),
+                _ => Err(CodecError::InvalidLength),
+            }
+        }
+        fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+            Ok(vec![self.0])
+        }
+    }
+
+    #[test]
+    fn test_default_registry() {
+        let registry = Registry::default();
+        let gama = registry
+            .decode(&ChunkType::gAMA, &[0, 0, 177, 143])
+            .unwrap()
+            .unwrap();
+        assert_eq!(gama.to_string(), "gamma 0.45455");
+        assert!(registry.get(&ChunkType::known(*b"anSw")).is_none());
+    }
+
+    #[test]
+    fn test_register_third_party_type() {
+        let mut registry = Registry::default();
+        registry.register_type::<Answer>();
+
+        let codec = registry.get(&Answer::CHUNK_TYPE).unwrap();
+        let value = codec.decode(&[42]).unwrap();
+        assert_eq!(value.to_string(), "answer 42");
+        assert_eq!(value.as_any().downcast_ref::<Answer>(), Some(&Answer(42)));
+        assert_eq!(codec.encode(value.as_ref()), Ok(vec![42]));
+        assert_eq!(codec.encode(&Gama { gamma: 1 }), Err(CodecError::WrongType));
+        assert_eq!(codec.decode(&[]).err(), Some(CodecError::InvalidLength));
+    }
+
+    #[test]
+    fn test_chunk_data_round_trip() {
+        let chunk = Answer(7).to_chunk().unwrap();
+        assert_eq!(chunk.chunk_type(), &Answer::CHUNK_TYPE);
+        assert_eq!(Answer::from_chunk(&chunk), Ok(Answer(7)));
+        assert_eq!(Gama::from_chunk(&chunk).err(), Some(CodecError::WrongType));
+    }
+}
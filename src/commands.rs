@@ -1,82 +1,1052 @@
 use std::error::Error;
+use std::fmt::Write as _;
 use std::fs::File;
 use std::io::prelude::*;
+use std::path::Path;
 use std::str::FromStr;
 
 use clap::Parser;
 use derive_more::{Display, Error};
 
-use crate::args::PngMeArgs;
+use crate::ancillary::{Chrm, Cicp, Gama, Iccp, Phys, Srgb, Time};
+use crate::apng::{self, Animation};
+use crate::args::{
+    ApngArgs, ApngCommand, BatchTarget, ColourArgs, ColourCommand, ColourSetArgs, DecodeArgs,
+    DpiArgs, DpiCommand, EncodeArgs, ExifArgs, ExifCommand, NormalizeCgbiArgs, OptimizeArgs,
+    PaletteArgs, PaletteCommand, PngMeArgs, PrintArgs, RemoveArgs, ReorderArgs, RepairArgs,
+    Trailing, XmpArgs, XmpCommand,
+};
+use crate::batch;
+use crate::cgbi;
 use crate::chunk::Chunk;
-use crate::chunk_type::ChunkType;
-use crate::png::{Png, PngError};
+use crate::chunk_type::{ChunkType, CHECK_BIT};
+use crate::codec::{ChunkData, CodecError, Registry};
+use crate::copy::copy_chunks;
+use crate::diff;
+use crate::exif::{check_placement, find_tag, tag_name, ExifData, ExifError, IfdKind};
+use crate::ihdr::ColourType;
+use crate::limits::Limits;
+use crate::loader::FileBytes;
+use crate::optimize::{self, OptimizeOptions};
+use crate::palette::{
+    parse_colour, remap_indices, reorder_palette, Palette, PaletteError, Transparency,
+};
+use crate::png::{ParseOptions, Png, Repair};
+use crate::png_ref::PngRef;
+use crate::reorder::{self, ReorderOptions};
+use crate::xmp;
 
 #[derive(PartialEq, Debug, Display, Error)]
 pub enum CommandError {
     NotExistingChunkType,
+    InvalidChunkType,
+    CriticalChunkType,
+    SequencedChunkType,
+    OutputWithManyFiles,
+    BatchFailed,
 }
 
-pub(crate) fn cli() -> Result<(), Box<dyn Error>> {
+/// Colour-management chunks, which must all precede `PLTE` and `IDAT`.
+const COLOUR_CHUNK_TYPES: [ChunkType; 5] = [
+    ChunkType::cICP,
+    ChunkType::iCCP,
+    ChunkType::sRGB,
+    ChunkType::gAMA,
+    ChunkType::cHRM,
+];
+const BEFORE_IMAGE_DATA: [ChunkType; 2] = [ChunkType::PLTE, ChunkType::IDAT];
+
+pub fn cli() -> Result<(), Box<dyn Error>> {
     let args = PngMeArgs::parse();
 
     match args {
-        PngMeArgs::Encode(args) => {
-            if args.filepath.as_path().exists() {
-                let contents = std::fs::read(args.filepath.as_path())?;
-                let mut png = Png::try_from(contents.as_ref())?;
-                let chunk = parse_chunk(args.chunk_type, args.data)?;
-                png.append_chunk(chunk);
-
-                let to_write = png.as_bytes();
-                std::fs::write(args.filepath.as_path(), to_write.as_slice())?;
+        PngMeArgs::Encode(Trailing(args)) => for_each_file(&args, encode),
+        PngMeArgs::Decode(Trailing(args)) => for_each_file(&args, decode),
+        PngMeArgs::Remove(Trailing(args)) => for_each_file(&args, remove),
+        PngMeArgs::Print(args) => for_each_file(&args, print),
+        PngMeArgs::Repair(args) => for_each_file(&args, repair),
+        PngMeArgs::ChunkType(args) => {
+            let chunk_type = ChunkType::from_str(args.chunk_type.as_str())?;
+            print!("{}", explain_chunk_type(&chunk_type));
+            Ok(())
+        }
+        PngMeArgs::Palette(args) => palette(args),
+        PngMeArgs::Colour(args) => colour(args),
+        PngMeArgs::Dpi(args) => dpi(args),
+        PngMeArgs::Exif(args) => exif(args),
+        PngMeArgs::Xmp(args) => xmp(args),
+        PngMeArgs::Apng(args) => apng(args),
+        PngMeArgs::NormalizeCgbi(args) => for_each_file(&args, normalize_cgbi),
+        PngMeArgs::Reorder(args) => for_each_file(&args, reorder),
+        PngMeArgs::Optimize(args) => for_each_file(&args, optimize),
+        PngMeArgs::CopyChunks(args) => {
+            let src = read_png(args.from.as_path(), (&args.limits).into())?;
+            for_each_file(&args, |path, args, out| {
+                let limits = (&args.limits).into();
+                let mut dst = read_png(path, limits)?;
+                let copied = copy_chunks(&src, &mut dst, &args.types, args.force, &limits)?;
+                writeln!(out, "Copied {} chunks", copied.count)?;
+                for chunk_type in copied.dropped {
+                    writeln!(
+                        out,
+                        "Removed {}, which conflicts with a copied chunk",
+                        chunk_type
+                    )?;
+                }
+                let output = args.output.unwrap_or_else(|| path.to_path_buf());
+                std::fs::write(output.as_path(), dst.as_bytes())?;
                 Ok(())
+            })
+        }
+        PngMeArgs::Diff(args) => {
+            let limits = (&args.limits).into();
+            let old = read_png(args.old.as_path(), limits)?;
+            let new = read_png(args.new.as_path(), limits)?;
+            let diff = diff::diff(&old, &new, args.pixels, &limits);
+            if diff.is_empty() {
+                println!("No differences");
             } else {
-                let mut file = File::create(args.filepath.as_path())?;
-                let png = Png::from_chunks(vec![parse_chunk(args.chunk_type, args.data)?]);
+                print!("{}", diff);
+            }
+            Ok(())
+        }
+    }
+}
 
-                file.write_all(png.as_bytes().as_ref())?;
-                Ok(())
+/// Runs `f` on every file `args` selects. With several files, each file's output is printed
+/// under its name, followed by a summary; the command fails if any file failed.
+fn for_each_file<A, F>(args: &A, f: F) -> Result<(), Box<dyn Error>>
+where
+    A: BatchTarget,
+    F: Fn(&Path, A, &mut String) -> Result<(), Box<dyn Error>> + Sync,
+{
+    let (paths, options) = args.batch();
+    let files = batch::expand(paths, options.recursive)?;
+
+    if let [file] = files.as_slice() {
+        let mut out = String::new();
+        let result = f(file, args.clone(), &mut out);
+        print!("{}", out);
+        return result;
+    }
+    if args.output().is_some() {
+        return Err(Box::new(CommandError::OutputWithManyFiles));
+    }
+
+    let results = batch::run(&files, options.jobs.unwrap_or(0), |path| {
+        let mut out = String::new();
+        let result = f(path, args.clone(), &mut out).map_err(|e| e.to_string());
+        (out, result)
+    });
+    let mut failed = 0;
+    for (path, (out, result)) in files.iter().zip(results) {
+        println!("==> {} <==", path.display());
+        print!("{}", out);
+        if let Err(e) = result {
+            eprintln!("{}: {}", path.display(), e);
+            failed += 1;
+        }
+    }
+    println!(
+        "{} files: {} succeeded, {} failed",
+        files.len(),
+        files.len() - failed,
+        failed
+    );
+    if failed > 0 {
+        Err(Box::new(CommandError::BatchFailed))
+    } else {
+        Ok(())
+    }
+}
+
+fn decode(path: &Path, args: DecodeArgs, out: &mut String) -> Result<(), Box<dyn Error>> {
+    let limits = (&args.limits).into();
+    let contents = read_file(path, &limits)?;
+    let png = parse_png_ref(contents.as_ref(), limits)?;
+
+    let chunk = png.chunk_by_type(args.chunk_type.as_str());
+
+    match chunk {
+        Some(x) => {
+            writeln!(out, "{}", x)?;
+            Ok(())
+        }
+        None => Err(Box::new(CommandError::NotExistingChunkType)),
+    }
+}
+
+fn remove(path: &Path, args: RemoveArgs, _: &mut String) -> Result<(), Box<dyn Error>> {
+    let sequenced = apng::SEQUENCED_CHUNKS
+        .iter()
+        .any(|x| x.to_string() == args.chunk_type);
+    if sequenced {
+        return Err(Box::new(CommandError::SequencedChunkType));
+    }
+    if path.exists() {
+        let mut png = read_png(path, (&args.limits).into())?;
+        png.remove_chunk(args.chunk_type.as_str())?;
+        if png.chunk_by_type("acTL").is_some() {
+            apng::renumber(&mut png)?;
+            // Removing IDAT or the last fdAT of a frame still leaves an invalid animation.
+            Animation::from_png(&png)?;
+        }
+        if args.touch {
+            touch(&mut png)?;
+        }
+
+        let to_write = png.as_bytes();
+        std::fs::write(path, to_write.as_slice())?;
+        Ok(())
+    } else {
+        Err(Box::new(CommandError::NotExistingChunkType))
+    }
+}
+
+fn print(path: &Path, args: PrintArgs, out: &mut String) -> Result<(), Box<dyn Error>> {
+    writeln!(out, "Print: {}", path.display())?;
+    let limits = (&args.limits).into();
+    let contents = read_file(path, &limits)?;
+    let png = parse_png_ref(contents.as_ref(), limits)?;
+    if png.is_cgbi() {
+        writeln!(
+            out,
+            "Apple CgBI image; convert it with `pngme normalize-cgbi`"
+        )?;
+    }
+    // Without --verbose only the chunk headers are read, so large images stay cheap to list.
+    let registry = if args.verbose {
+        Registry::default()
+    } else {
+        Registry::empty()
+    };
+    *out += &describe_chunks(&png, &registry);
+    Ok(())
+}
+
+fn repair(path: &Path, args: RepairArgs, out: &mut String) -> Result<(), Box<dyn Error>> {
+    let options = ParseOptions {
+        limits: (&args.limits).into(),
+        ..ParseOptions::repair()
+    };
+    let (png, repairs) = read_png_with(path, options)?;
+
+    if repairs.is_empty() {
+        writeln!(out, "No problems found")?;
+        return Ok(());
+    }
+    for repair in repairs.iter() {
+        writeln!(out, "{}", repair)?;
+    }
+
+    if !args.dry_run {
+        let output = args.output.unwrap_or_else(|| path.to_path_buf());
+        std::fs::write(output.as_path(), png.as_bytes().as_slice())?;
+    }
+    Ok(())
+}
+
+fn explain_chunk_type(chunk_type: &ChunkType) -> String {
+    let bytes = chunk_type.bytes();
+    let describe = |byte: u8, set: &str, unset: &str| {
+        let bit = u8::from(byte & CHECK_BIT != 0);
+        let meaning = if bit == 0 { unset } else { set };
+        format!(
+            "{} (bit {} = {}): {}",
+            byte as char,
+            CHECK_BIT.trailing_zeros(),
+            bit,
+            meaning
+        )
+    };
+
+    let mut out = format!("Chunk type: {}\n", chunk_type);
+    out += &format!(
+        "  ancillary bit:    {}\n",
+        describe(bytes[0], "ancillary", "critical")
+    );
+    out += &format!(
+        "  private bit:      {}\n",
+        describe(bytes[1], "private", "public")
+    );
+    out += &format!(
+        "  reserved bit:     {}\n",
+        describe(bytes[2], "invalid, must be uppercase", "valid")
+    );
+    out += &format!(
+        "  safe-to-copy bit: {}\n",
+        describe(bytes[3], "safe to copy", "unsafe to copy")
+    );
+
+    match chunk_type.registered_name() {
+        Some(name) => out += &format!("Registered: yes, {}\n", name),
+        None => out += "Registered: no\n",
+    }
+
+    let nearest = chunk_type.nearest_private();
+    if nearest != *chunk_type {
+        out += &format!("Nearest valid ancillary private type: {}\n", nearest);
+    }
+    out
+}
+
+fn describe_chunks(png: &PngRef, registry: &Registry) -> String {
+    let mut out = String::new();
+    for (i, chunk) in png.chunks().iter().enumerate() {
+        out += &format!(
+            "{:>4} {} {:>8} bytes",
+            i,
+            chunk.chunk_type(),
+            chunk.length()
+        );
+        match registry.decode(chunk.chunk_type(), chunk.data()) {
+            Some(Ok(value)) => out += &format!("  {}", value),
+            Some(Err(e)) => out += &format!("  invalid: {}", e),
+            None => (),
+        }
+        out += "\n";
+    }
+    out
+}
+
+fn encode(path: &Path, args: EncodeArgs, out: &mut String) -> Result<(), Box<dyn Error>> {
+    let mut png = if path.exists() {
+        read_png(path, (&args.limits).into())?
+    } else {
+        Png::from_chunks(vec![])
+    };
+
+    // Without --auto-type the chunk type is read from the command line.
+    let chunk_type = match args.chunk_type {
+        Some(chunk_type) => ChunkType::from_str(chunk_type.as_str())?,
+        None => {
+            let key = args.key.unwrap_or_else(|| String::from("pngme"));
+            let chunk_type = ChunkType::derive_private(key.as_bytes(), |x| {
+                png.chunks().iter().any(|chunk| chunk.chunk_type() == x)
+            });
+            writeln!(out, "Chunk type: {}", chunk_type)?;
+            chunk_type
+        }
+    };
+    check_encode_chunk_type(&chunk_type, args.force)?;
+
+    let chunk = Chunk::new(chunk_type, args.data.into_bytes());
+    // Keep animations readable by players that stop at IEND.
+    match png.position("IEND") {
+        Some(i) if png.chunk_by_type("acTL").is_some() => png.insert_chunk(i, chunk),
+        _ => png.append_chunk(chunk),
+    }
+    if args.touch {
+        touch(&mut png)?;
+    }
+    let mut file = File::create(path)?;
+    file.write_all(png.as_bytes().as_ref())?;
+    Ok(())
+}
+
+fn palette(args: PaletteArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        PaletteCommand::Show(args) => for_each_file(&args, |path, args, out| {
+            let png = read_png(path, (&args.limits).into())?;
+            write!(out, "{}", describe_palette(&png)?)?;
+            Ok(())
+        }),
+        PaletteCommand::Set(Trailing(args)) => for_each_file(&args, |path, args, _| {
+            let mut png = read_png(path, (&args.limits).into())?;
+            set_palette_entry(
+                &mut png,
+                args.index,
+                parse_colour(&args.colour)?,
+                args.alpha,
+            )?;
+            std::fs::write(path, png.as_bytes())?;
+            Ok(())
+        }),
+        PaletteCommand::Reorder(Trailing(args)) => for_each_file(&args, |path, args, _| {
+            let limits = (&args.limits).into();
+            let mut png = read_png(path, limits)?;
+            reorder_palette(&mut png, &args.order)?;
+            if !args.keep_pixels {
+                remap_indices(&mut png, &args.order, &limits)?;
             }
+            std::fs::write(path, png.as_bytes())?;
+            Ok(())
+        }),
+    }
+}
+
+fn describe_palette(png: &Png) -> Result<String, Box<dyn Error>> {
+    let ihdr = png.ihdr()?;
+    let palette = Palette::from_png(png)?.ok_or(PaletteError::MissingPalette)?;
+    palette.validate(&ihdr)?;
+    let transparency = Transparency::from_png(png, &ihdr)?;
+
+    let mut out = String::new();
+    for (i, [r, g, b]) in palette.entries.iter().enumerate() {
+        out += &format!("{:>3} #{:02x}{:02x}{:02x}", i, r, g, b);
+        if let Some(alpha) = transparency.as_ref().and_then(|x| x.alpha(i)) {
+            out += &format!(" alpha {}", alpha);
         }
-        PngMeArgs::Decode(args) => {
-            let contents = std::fs::read(args.filepath.as_path())?;
-            let png = Png::try_from(contents.as_ref())?;
+        out += "\n";
+    }
+    Ok(out)
+}
 
-            let chunk = png.chunk_by_type(args.chunk_type.as_str());
+fn set_palette_entry(
+    png: &mut Png,
+    index: usize,
+    colour: [u8; 3],
+    alpha: Option<u8>,
+) -> Result<(), Box<dyn Error>> {
+    let ihdr = png.ihdr()?;
+    let mut palette = Palette::from_png(png)?.ok_or(PaletteError::MissingPalette)?;
+    palette.set(index, colour)?;
+    png.chunk_by_type_mut("PLTE")
+        .unwrap()
+        .set_data(palette.to_bytes()?);
 
-            match chunk {
-                Some(x) => {
-                    println!("{}", x);
-                    Ok(())
-                }
-                None => Err(Box::new(CommandError::NotExistingChunkType)),
+    if let Some(alpha) = alpha {
+        // Only indexed images give palette entries an alpha; other colour types use tRNS differently.
+        if ihdr.colour_type != ColourType::Indexed {
+            return Err(Box::new(PaletteError::TransparencyNotAllowed));
+        }
+        let mut transparency = match Transparency::from_png(png, &ihdr)? {
+            Some(transparency) => transparency,
+            // Entries without alpha are already opaque.
+            None if alpha == 255 => return Ok(()),
+            None => Transparency::Alpha(vec![]),
+        };
+        transparency.set_alpha(index, alpha)?;
+        transparency.validate(&ihdr, Some(&palette))?;
+        match png.chunk_by_type_mut("tRNS") {
+            Some(chunk) => chunk.set_data(transparency.to_bytes()),
+            None => {
+                // tRNS must follow PLTE and precede the first IDAT.
+                let index = png.position("PLTE").unwrap() + 1;
+                png.insert_chunk(index, Chunk::new(ChunkType::tRNS, transparency.to_bytes()));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn colour(args: ColourArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        ColourCommand::Show(args) => for_each_file(&args, |path, args, out| {
+            let limits = (&args.limits).into();
+            let png = read_png(path, limits)?;
+            write!(out, "{}", describe_colour(&png, &limits))?;
+            Ok(())
+        }),
+        ColourCommand::Set(args) => for_each_file(&args, |path, args, _| {
+            let mut png = read_png(path, (&args.limits).into())?;
+            set_colour(&mut png, &args)?;
+            std::fs::write(path, png.as_bytes())?;
+            Ok(())
+        }),
+        ColourCommand::Strip(args) => for_each_file(&args, |path, args, out| {
+            let mut png = read_png(path, (&args.limits).into())?;
+            let removed: usize = COLOUR_CHUNK_TYPES
+                .iter()
+                .map(|x| png.remove_chunks_by_type(x))
+                .sum();
+            writeln!(out, "Removed {} chunks", removed)?;
+            std::fs::write(path, png.as_bytes())?;
+            Ok(())
+        }),
+        ColourCommand::ExportIcc(args) => {
+            let limits = (&args.limits).into();
+            let png = read_png(args.filepath.as_path(), limits)?;
+            let chunk = png
+                .chunk_by_type("iCCP")
+                .ok_or(CommandError::NotExistingChunkType)?;
+            let profile = Iccp::from_chunk(chunk)?.profile(&limits)?;
+            std::fs::write(args.output.as_path(), profile)?;
+            Ok(())
+        }
+        ColourCommand::ImportIcc(Trailing(args)) => for_each_file(&args, |path, args, _| {
+            let limits = (&args.limits).into();
+            let mut png = read_png(path, limits)?;
+            let profile = read_file(args.profile.as_path(), &limits)?;
+            let iccp = Iccp::from_profile(&args.name, profile.as_ref());
+            // iCCP and sRGB must not both be present.
+            png.remove_chunks_by_type(&ChunkType::sRGB);
+            png.put_chunk_before(iccp.to_chunk()?, &BEFORE_IMAGE_DATA);
+            std::fs::write(path, png.as_bytes())?;
+            Ok(())
+        }),
+    }
+}
+
+fn describe_colour(png: &Png, limits: &Limits) -> String {
+    let registry = Registry::default();
+    let mut out = String::new();
+    for chunk in png.chunks() {
+        if !COLOUR_CHUNK_TYPES.contains(chunk.chunk_type()) {
+            continue;
+        }
+        out += &format!("{}: ", chunk.chunk_type());
+        match registry.decode(chunk.chunk_type(), chunk.data()).unwrap() {
+            Ok(value) => out += &value.to_string(),
+            Err(e) => out += &format!("invalid: {}", e),
+        }
+        if let Ok(iccp) = Iccp::from_chunk(chunk) {
+            match iccp.profile(limits) {
+                Ok(profile) => out += &format!(", {} bytes uncompressed", profile.len()),
+                Err(e) => out += &format!(", cannot decompress: {}", e),
             }
         }
-        PngMeArgs::Remove(args) => {
-            if args.filepath.as_path().exists() {
-                let contents = std::fs::read(args.filepath.as_path())?;
-                let mut png = Png::try_from(contents.as_ref())?;
-                png.remove_chunk(args.chunk_type.as_str())?;
+        out += "\n";
+    }
+    if out.is_empty() {
+        out += "No colour-management chunks\n";
+    }
+    out
+}
 
-                let to_write = png.as_bytes();
-                std::fs::write(args.filepath.as_path(), to_write.as_slice())?;
-                Ok(())
+fn set_colour(png: &mut Png, args: &ColourSetArgs) -> Result<(), CodecError> {
+    let mut chunks = vec![];
+    if let Some(x) = &args.cicp {
+        chunks.push(
+            Cicp {
+                colour_primaries: x[0],
+                transfer_function: x[1],
+                matrix_coefficients: x[2],
+                video_full_range: x[3] != 0,
+            }
+            .to_chunk()?,
+        );
+    }
+    if let Some(intent) = args.srgb {
+        // iCCP and sRGB must not both be present.
+        png.remove_chunks_by_type(&ChunkType::iCCP);
+        chunks.push(Srgb { intent }.to_chunk()?);
+    }
+    if let Some(x) = args.gamma {
+        chunks.push(Gama::from_value(x)?.to_chunk()?);
+    }
+    if let Some(x) = &args.chromaticities {
+        chunks.push(Chrm::from_values(*x)?.to_chunk()?);
+    }
+    for chunk in chunks {
+        png.put_chunk_before(chunk, &BEFORE_IMAGE_DATA);
+    }
+    Ok(())
+}
+
+fn dpi(args: DpiArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        DpiCommand::Get(args) => for_each_file(&args, |path, args, out| {
+            let png = read_png(path, (&args.limits).into())?;
+            let chunk = png
+                .chunk_by_type("pHYs")
+                .ok_or(CommandError::NotExistingChunkType)?;
+            writeln!(out, "{}", describe_dpi(&Phys::from_chunk(chunk)?))?;
+            Ok(())
+        }),
+        DpiCommand::Set(Trailing(args)) => for_each_file(&args, |path, args, _| {
+            let mut png = read_png(path, (&args.limits).into())?;
+            let phys = Phys::from_dpi(args.x, args.y.unwrap_or(args.x));
+            png.put_chunk_before(phys.to_chunk()?, &[ChunkType::IDAT]);
+            std::fs::write(path, png.as_bytes())?;
+            Ok(())
+        }),
+    }
+}
+
+fn describe_dpi(phys: &Phys) -> String {
+    // Pixels per metre are whole numbers, so hide the error of converting them back to inches.
+    let round = |dpi: f64| {
+        let dpi = (dpi * 10.0).round() / 10.0;
+        if dpi.fract() == 0.0 {
+            format!("{:.0}", dpi)
+        } else {
+            format!("{:.1}", dpi)
+        }
+    };
+    match phys.dpi().map(|(x, y)| (round(x), round(y))) {
+        Some((x, y)) if x == y => format!("{} dpi", x),
+        Some((x, y)) => format!("{} x {} dpi", x, y),
+        None => format!(
+            "Unknown unit, pixel aspect ratio {}:{}",
+            phys.pixels_per_unit_x, phys.pixels_per_unit_y
+        ),
+    }
+}
+
+fn exif(args: ExifArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        ExifCommand::Show(args) => for_each_file(&args, |path, args, out| {
+            let png = read_png(path, (&args.limits).into())?;
+            if let Err(e) = check_placement(&png) {
+                writeln!(out, "Warning: {}", e)?;
+            }
+            write!(out, "{}", describe_exif(&read_exif(&png)?))?;
+            Ok(())
+        }),
+        ExifCommand::Get(Trailing(args)) => for_each_file(&args, |path, args, out| {
+            let png = read_png(path, (&args.limits).into())?;
+            let exif = read_exif(&png)?;
+            let value = resolve_tag(&exif, &args.tag)?
+                .and_then(|(kind, tag)| exif.get(kind, tag))
+                .ok_or(ExifError::UnknownTag)?;
+            writeln!(out, "{}", value)?;
+            Ok(())
+        }),
+        ExifCommand::Set(Trailing(args)) => for_each_file(&args, |path, args, _| {
+            let mut png = read_png(path, (&args.limits).into())?;
+            let mut exif = match png.chunk_by_type("eXIf") {
+                Some(chunk) => ExifData::parse(chunk.data())?,
+                None => ExifData::new(),
+            };
+            let (kind, tag) = match resolve_tag(&exif, &args.tag)? {
+                Some(x) => x,
+                None => (args.ifd, parse_tag_number(&args.tag)?),
+            };
+            match args.field_type {
+                Some(type_id) => exif.set_as(kind, tag, type_id, &args.value)?,
+                None => exif.set(kind, tag, &args.value)?,
+            }
+            write_exif(&mut png, &exif)?;
+            std::fs::write(path, png.as_bytes())?;
+            Ok(())
+        }),
+        ExifCommand::RemoveGps(args) => for_each_file(&args, |path, args, out| {
+            let mut png = read_png(path, (&args.limits).into())?;
+            let mut exif = read_exif(&png)?;
+            if exif.remove_gps() {
+                write_exif(&mut png, &exif)?;
+                std::fs::write(path, png.as_bytes())?;
             } else {
-                Err(Box::new(CommandError::NotExistingChunkType))
+                writeln!(out, "No GPS tags")?;
+            }
+            Ok(())
+        }),
+        ExifCommand::Strip(args) => for_each_file(&args, |path, args, _| {
+            let mut png = read_png(path, (&args.limits).into())?;
+            if png.remove_chunks_by_type(&ChunkType::eXIf) == 0 {
+                return Err(Box::new(CommandError::NotExistingChunkType));
+            }
+            std::fs::write(path, png.as_bytes())?;
+            Ok(())
+        }),
+    }
+}
+
+fn read_exif(png: &Png) -> Result<ExifData, Box<dyn Error>> {
+    let chunk = png
+        .chunk_by_type("eXIf")
+        .ok_or(CommandError::NotExistingChunkType)?;
+    Ok(ExifData::parse(chunk.data())?)
+}
+
+/// Stores `exif` in an `eXIf` chunk before the image data.
+fn write_exif(png: &mut Png, exif: &ExifData) -> Result<(), ExifError> {
+    let chunk = Chunk::new(ChunkType::eXIf, exif.to_bytes()?);
+    png.put_chunk_before(chunk, &[ChunkType::IDAT]);
+    Ok(())
+}
+
+/// Resolves a tag name, or a number looked up in every IFD of `exif`.
+fn resolve_tag(exif: &ExifData, name: &str) -> Result<Option<(IfdKind, u16)>, ExifError> {
+    if let Some(x) = find_tag(name) {
+        return Ok(Some(x));
+    }
+    let tag = parse_tag_number(name)?;
+    Ok(exif
+        .entries()
+        .find(|(_, entry)| entry.tag == tag)
+        .map(|(kind, _)| (kind, tag)))
+}
+
+/// Parses a tag number written in decimal or as `0x` hexadecimal.
+fn parse_tag_number(name: &str) -> Result<u16, ExifError> {
+    match name.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => name.parse(),
+    }
+    .map_err(|_| ExifError::UnknownTag)
+}
+
+fn describe_exif(exif: &ExifData) -> String {
+    let mut out = String::new();
+    for (kind, entry) in exif.entries() {
+        let name = tag_name(kind, entry.tag).unwrap_or("Unknown");
+        out += &format!(
+            "{:<8}{} (0x{:04x}): {}\n",
+            kind, name, entry.tag, entry.value
+        );
+    }
+    if let Some((_, jpeg)) = exif.thumbnail.as_ref() {
+        out += &format!("Thumbnail: {} bytes\n", jpeg.len());
+    }
+    out
+}
+
+fn xmp(args: XmpArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        XmpCommand::Get(args) => for_each_file(&args, |path, args, out| {
+            let limits = (&args.limits).into();
+            let png = read_png(path, limits)?;
+            let packet = png
+                .xmp_with(&limits)?
+                .ok_or(CommandError::NotExistingChunkType)?;
+            writeln!(out, "{}", packet)?;
+            Ok(())
+        }),
+        XmpCommand::Set(Trailing(args)) => for_each_file(&args, |path, args, _| {
+            let mut png = read_png(path, (&args.limits).into())?;
+            png.set_xmp(&std::fs::read_to_string(args.packet.as_path())?)?;
+            std::fs::write(path, png.as_bytes())?;
+            Ok(())
+        }),
+        XmpCommand::Merge(Trailing(args)) => for_each_file(&args, |path, args, _| {
+            let limits = (&args.limits).into();
+            let mut png = read_png(path, limits)?;
+            let other = std::fs::read_to_string(args.packet.as_path())?;
+            let packet = match png.xmp_with(&limits)? {
+                Some(packet) => xmp::merge(&packet, &other)?,
+                None => other,
+            };
+            png.set_xmp(&packet)?;
+            std::fs::write(path, png.as_bytes())?;
+            Ok(())
+        }),
+    }
+}
+
+fn apng(args: ApngArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        ApngCommand::Info(args) => for_each_file(&args, |path, args, out| {
+            let png = read_png(path, (&args.limits).into())?;
+            write!(out, "{}", describe_animation(&Animation::from_png(&png)?))?;
+            Ok(())
+        }),
+        ApngCommand::Extract(args) => {
+            let png = read_png(args.filepath.as_path(), (&args.limits).into())?;
+            let animation = Animation::from_png(&png)?;
+            std::fs::create_dir_all(args.output.as_path())?;
+            for (i, frame) in animation.frames.iter().enumerate() {
+                let path = args.output.join(format!("frame_{:03}.png", i));
+                std::fs::write(path, apng::extract_frame(&png, frame)?.as_bytes())?;
             }
+            println!("Extracted {} frames", animation.frames.len());
+            Ok(())
         }
-        PngMeArgs::Print(args) => {
-            println!("Print: {}", args.filepath.as_path().display());
-            let contents = std::fs::read(args.filepath.as_path())?;
-            let png = Png::try_from(contents.as_ref())?;
-            println!("{}", png);
+        ApngCommand::Assemble(args) => {
+            let frames = args
+                .frames
+                .iter()
+                .map(|x| read_png(x.as_path(), (&args.limits).into()))
+                .collect::<Result<Vec<Png>, _>>()?;
+            let png = apng::assemble(&frames, args.delay, args.plays)?;
+            std::fs::write(args.output.as_path(), png.as_bytes())?;
             Ok(())
         }
     }
 }
 
-fn parse_chunk(chunk_type: String, data: String) -> Result<Chunk, Box<dyn Error>> {
-    let chunk_type = ChunkType::from_str(chunk_type.as_str())?;
-    let chunk = Chunk::new(chunk_type, data.bytes().collect());
-    Ok(chunk)
+fn normalize_cgbi(
+    path: &Path,
+    args: NormalizeCgbiArgs,
+    _: &mut String,
+) -> Result<(), Box<dyn Error>> {
+    let limits = (&args.limits).into();
+    let mut png = read_png(path, limits)?;
+    cgbi::normalize(&mut png, &limits)?;
+    let output = args.output.unwrap_or_else(|| path.to_path_buf());
+    std::fs::write(output.as_path(), png.as_bytes())?;
+    Ok(())
+}
+
+fn reorder(path: &Path, args: ReorderArgs, out: &mut String) -> Result<(), Box<dyn Error>> {
+    let mut png = read_png(path, (&args.limits).into())?;
+    let options = ReorderOptions {
+        idat_size: (!args.keep_idat).then_some(args.idat_size),
+        remove_duplicates: !args.keep_duplicates,
+    };
+    let original = png.as_bytes();
+    let summary = reorder::reorder(&mut png, &options)?;
+    if png.as_bytes() == original {
+        writeln!(out, "Already in canonical order")?;
+        return Ok(());
+    }
+    write!(out, "{}", summary)?;
+    let output = args.output.unwrap_or_else(|| path.to_path_buf());
+    std::fs::write(output.as_path(), png.as_bytes())?;
+    Ok(())
+}
+
+fn optimize(path: &Path, args: OptimizeArgs, out: &mut String) -> Result<(), Box<dyn Error>> {
+    let limits = (&args.limits).into();
+    let mut png = read_png(path, limits)?;
+    let options = OptimizeOptions {
+        strategies: args.strategies,
+        levels: args.levels,
+        reduce: args.reduce,
+    };
+    let report = optimize::optimize(&mut png, &options, &limits)?;
+    if report.is_smaller() {
+        write!(out, "{}", report)?;
+    } else {
+        writeln!(out, "Already optimal for the strategies and levels tried")?;
+    }
+    // The input is only rewritten when it shrinks; an explicit output is always written.
+    match args.output {
+        Some(output) => std::fs::write(output.as_path(), png.as_bytes())?,
+        None if report.is_smaller() => std::fs::write(path, png.as_bytes())?,
+        None => {}
+    }
+    Ok(())
+}
+
+fn describe_animation(animation: &Animation) -> String {
+    let mut out = format!("Animation: {}\n", animation.control);
+    for (i, frame) in animation.frames.iter().enumerate() {
+        let c = &frame.control;
+        out += &format!(
+            "{:>4} {}x{} at ({}, {}) delay {:.3}s dispose {:?} blend {:?}",
+            i,
+            c.width,
+            c.height,
+            c.x_offset,
+            c.y_offset,
+            c.delay(),
+            c.dispose_op,
+            c.blend_op
+        );
+        if frame.is_default_image {
+            out += " (default image)";
+        }
+        out += "\n";
+    }
+    out
+}
+
+/// Records the current time as the last modification in `tIME`.
+fn touch(png: &mut Png) -> Result<(), CodecError> {
+    png.put_chunk_before(Time::now().to_chunk()?, &[]);
+    Ok(())
+}
+
+fn check_encode_chunk_type(chunk_type: &ChunkType, force: bool) -> Result<(), CommandError> {
+    if !chunk_type.is_valid() {
+        Err(CommandError::InvalidChunkType)
+    } else if chunk_type.is_critical() && !force {
+        Err(CommandError::CriticalChunkType)
+    } else if apng::SEQUENCED_CHUNKS.contains(chunk_type) {
+        // These carry APNG sequence numbers that a message would break.
+        Err(CommandError::SequencedChunkType)
+    } else {
+        Ok(())
+    }
+}
+
+fn read_png(path: &Path, limits: Limits) -> Result<Png, Box<dyn Error>> {
+    let options = ParseOptions {
+        limits,
+        ..Default::default()
+    };
+    let (png, _) = read_png_with(path, options)?;
+    Ok(png)
+}
+
+fn read_png_with(path: &Path, options: ParseOptions) -> Result<(Png, Vec<Repair>), Box<dyn Error>> {
+    let contents = read_file(path, &options.limits)?;
+    Ok(Png::parse_with(contents.as_ref(), options)?)
+}
+
+fn parse_png_ref(contents: &[u8], limits: Limits) -> Result<PngRef<'_>, Box<dyn Error>> {
+    let options = ParseOptions {
+        limits,
+        ..Default::default()
+    };
+    let (png, _) = PngRef::parse_with(contents, options)?;
+    Ok(png)
+}
+
+fn read_file(path: &Path, limits: &Limits) -> Result<FileBytes, Box<dyn Error>> {
+    // Check the size before reading so oversized files are never loaded into memory.
+    let size = std::fs::metadata(path)?.len();
+    limits.check_file_size(usize::try_from(size).unwrap_or(usize::MAX))?;
+
+    Ok(FileBytes::open(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_chunk_type() {
+        let explanation = explain_chunk_type(&ChunkType::from_str("Rust").unwrap());
+        assert!(explanation.contains("ancillary bit:    R (bit 5 = 0): critical"));
+        assert!(explanation.contains("reserved bit:     s (bit 5 = 1): invalid"));
+        assert!(explanation.contains("Registered: no"));
+        assert!(explanation.contains("Nearest valid ancillary private type: ruSt"));
+
+        let explanation = explain_chunk_type(&ChunkType::tEXt);
+        assert!(explanation.contains("Registered: yes, textual data"));
+        assert!(explanation.contains("Nearest valid ancillary private type: teXt"));
+    }
+
+    #[test]
+    fn test_describe_chunks() {
+        let bytes = std::fs::read("tests/fixtures/dice.png").unwrap();
+        let png = PngRef::try_from(bytes.as_ref()).unwrap();
+        let out = describe_chunks(&png, &Registry::default());
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), png.chunks().len());
+        assert!(lines[0].starts_with("   0 IHDR       13 bytes  "));
+        assert!(out.contains("gAMA        4 bytes  gamma 0.45455"));
+
+        let out = describe_chunks(&png, &Registry::empty());
+        assert!(out.starts_with("   0 IHDR       13 bytes\n"));
+    }
+
+    fn indexed_png() -> Png {
+        let ihdr = crate::ihdr::Ihdr {
+            width: 1,
+            height: 1,
+            bit_depth: 8,
+            colour_type: ColourType::Indexed,
+            interlaced: false,
+        };
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, ihdr.as_bytes()),
+            Chunk::new(ChunkType::PLTE, vec![255, 0, 0, 0, 255, 0]),
+            Chunk::new(ChunkType::IDAT, vec![]),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ])
+    }
+
+    #[test]
+    fn test_palette_edit() {
+        let mut png = indexed_png();
+        assert_eq!(
+            describe_palette(&png).unwrap(),
+            "  0 #ff0000\n  1 #00ff00\n"
+        );
+
+        set_palette_entry(&mut png, 1, [0, 0, 255], Some(0)).unwrap();
+        assert_eq!(png.position("tRNS"), Some(2));
+        assert_eq!(
+            describe_palette(&png).unwrap(),
+            "  0 #ff0000 alpha 255\n  1 #0000ff alpha 0\n"
+        );
+        assert!(set_palette_entry(&mut png, 2, [0, 0, 0], None).is_err());
+
+        let mut png = indexed_png();
+        set_palette_entry(&mut png, 0, [0, 0, 0], Some(255)).unwrap();
+        assert_eq!(png.position("tRNS"), None);
+
+        let mut ihdr = png.ihdr().unwrap();
+        ihdr.colour_type = ColourType::Truecolour;
+        png.chunk_by_type_mut("IHDR")
+            .unwrap()
+            .set_data(ihdr.as_bytes());
+        assert!(set_palette_entry(&mut png, 0, [0, 0, 0], Some(0)).is_err());
+        assert_eq!(png.position("tRNS"), None);
+    }
+
+    #[test]
+    fn test_colour_edit() {
+        let mut png = indexed_png();
+        assert_eq!(
+            describe_colour(&png, &Limits::default()),
+            "No colour-management chunks\n"
+        );
+
+        png.put_chunk_before(
+            Iccp::from_profile("test", &[0; 100]).to_chunk().unwrap(),
+            &BEFORE_IMAGE_DATA,
+        );
+        let args = PngMeArgs::parse_from([
+            "pngme", "color", "set", "x.png", "--gamma", "0.45455", "--srgb", "absolute",
+        ]);
+        if let PngMeArgs::Colour(ColourArgs {
+            command: ColourCommand::Set(args),
+        }) = args
+        {
+            set_colour(&mut png, &args).unwrap();
+        }
+        assert_eq!(png.position("iCCP"), None);
+        assert_eq!(png.position("sRGB"), Some(1));
+        assert_eq!(png.position("gAMA"), Some(2));
+        assert_eq!(png.position("PLTE"), Some(3));
+        assert_eq!(
+            describe_colour(&png, &Limits::default()),
+            "sRGB: rendering intent AbsoluteColorimetric\ngAMA: gamma 0.45455\n"
+        );
+
+        png.put_chunk_before(
+            Iccp::from_profile("test", &[0; 100]).to_chunk().unwrap(),
+            &BEFORE_IMAGE_DATA,
+        );
+        assert!(describe_colour(&png, &Limits::default())
+            .contains("bytes compressed, 100 bytes uncompressed"));
+    }
+
+    #[test]
+    fn test_dpi_and_touch() {
+        assert_eq!(describe_dpi(&Phys::from_dpi(72.0, 72.0)), "72 dpi");
+        assert_eq!(describe_dpi(&Phys::from_dpi(300.0, 150.0)), "300 x 150 dpi");
+        assert_eq!(describe_dpi(&Phys::from_dpi(96.5, 96.5)), "96.5 dpi");
+
+        let mut png = indexed_png();
+        touch(&mut png).unwrap();
+        touch(&mut png).unwrap();
+        assert_eq!(png.position("tIME"), Some(3));
+        assert_eq!(png.chunks().len(), 5);
+        assert!(Time::from_chunk(&png.chunks()[3]).is_ok());
+    }
+
+    #[test]
+    fn test_exif_edit() {
+        let mut exif = ExifData::new();
+        exif.set(IfdKind::Primary, 0x0112, "6").unwrap();
+        exif.set(IfdKind::Gps, 0x0001, "N").unwrap();
+
+        let mut png = indexed_png();
+        write_exif(&mut png, &exif).unwrap();
+        assert_eq!(png.position("eXIf"), Some(2));
+        assert_eq!(check_placement(&png), Ok(()));
+
+        let exif = read_exif(&png).unwrap();
+        assert_eq!(
+            describe_exif(&exif),
+            "IFD0    Orientation (0x0112): 6\nGPS     GPSLatitudeRef (0x0001): N\n"
+        );
+        assert_eq!(
+            resolve_tag(&exif, "orientation"),
+            Ok(Some((IfdKind::Primary, 0x0112)))
+        );
+        assert_eq!(resolve_tag(&exif, "0x0001"), Ok(Some((IfdKind::Gps, 1))));
+        assert_eq!(resolve_tag(&exif, "0x0002"), Ok(None));
+        assert_eq!(resolve_tag(&exif, "bogus"), Err(ExifError::UnknownTag));
+    }
+
+    #[test]
+    fn test_describe_animation() {
+        let frame = || read_png(Path::new("tests/fixtures/dice.png"), Limits::default()).unwrap();
+        let png = apng::assemble(&[frame(), frame()], (1, 20), 2).unwrap();
+        let out = describe_animation(&Animation::from_png(&png).unwrap());
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "Animation: 2 frames, 2 plays");
+        assert!(lines[1].ends_with("delay 0.050s dispose None blend Source (default image)"));
+        assert!(!lines[2].ends_with("(default image)"));
+    }
+
+    #[test]
+    fn test_check_encode_chunk_type() {
+        let check = |chunk_type: &str, force: bool| {
+            check_encode_chunk_type(&ChunkType::from_str(chunk_type).unwrap(), force)
+        };
+        assert_eq!(check("ruSt", false), Ok(()));
+        assert_eq!(check("RuSt", false), Err(CommandError::CriticalChunkType));
+        assert_eq!(check("RuSt", true), Ok(()));
+        assert_eq!(check("rust", false), Err(CommandError::InvalidChunkType));
+        assert_eq!(check("Rust", true), Err(CommandError::InvalidChunkType));
+        assert_eq!(check("fdAT", true), Err(CommandError::SequencedChunkType));
+    }
 }
@@ -0,0 +1,222 @@
+use derive_more::{Display, Error};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::{ChunkType, Placement};
+use crate::diff::{diff_pixels, PixelDiff};
+use crate::limits::Limits;
+use crate::png::{Png, PngError};
+
+#[derive(PartialEq, Debug, Display, Error)]
+pub enum CopyError {
+    CriticalChunkType,
+    /// An unsafe-to-copy chunk was requested but the critical chunks of both files differ.
+    UnsafeToCopy,
+    /// The copied chunks leave the destination without a valid structure.
+    InvalidPng(PngError),
+}
+
+impl From<PngError> for CopyError {
+    fn from(e: PngError) -> Self {
+        CopyError::InvalidPng(e)
+    }
+}
+
+/// Chunk types that must not appear together; copying one removes the other from the destination.
+const CONFLICTS: [(ChunkType, ChunkType); 2] = [
+    (ChunkType::iCCP, ChunkType::sRGB),
+    (ChunkType::sRGB, ChunkType::iCCP),
+];
+
+/// The changes made by [`copy_chunks`].
+#[derive(PartialEq, Debug, Default)]
+pub struct Copied {
+    pub count: usize,
+    /// Chunk types removed from the destination because they conflict with a copied chunk.
+    pub dropped: Vec<ChunkType>,
+}
+
+/// Whether `IHDR`, `PLTE` and the decoded pixels of both files match, so unsafe-to-copy chunks stay valid.
+pub fn critical_data_matches(a: &Png, b: &Png, limits: &Limits) -> bool {
+    let same = |chunk_type: &str| {
+        a.chunk_by_type(chunk_type).map(Chunk::data) == b.chunk_by_type(chunk_type).map(Chunk::data)
+    };
+    same("IHDR") && same("PLTE") && diff_pixels(a, b, limits) == PixelDiff::Identical
+}
+
+/// Copies every chunk of `types` from `src` into `dst` and checks that `dst` is still valid.
+///
+/// Types that may only appear once replace the chunk in `dst`, in place for `IHDR`; others are
+/// added unless an identical chunk is already there. Critical types, and unsafe-to-copy types
+/// when the critical data differs, are refused unless `force` is set.
+pub fn copy_chunks(
+    src: &Png,
+    dst: &mut Png,
+    types: &[ChunkType],
+    force: bool,
+    limits: &Limits,
+) -> Result<Copied, CopyError> {
+    if !force {
+        if types.iter().any(ChunkType::is_critical) {
+            return Err(CopyError::CriticalChunkType);
+        }
+        let unsafe_requested = types.iter().any(|x| !x.is_safe_to_copy());
+        if unsafe_requested && !critical_data_matches(src, dst, limits) {
+            return Err(CopyError::UnsafeToCopy);
+        }
+    }
+
+    let src_image_data = src.position("IDAT").unwrap_or(usize::MAX);
+    let mut copied = Copied::default();
+    for chunk_type in types {
+        let chunks: Vec<(usize, &Chunk)> = src
+            .chunks()
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| x.chunk_type() == chunk_type)
+            .collect();
+        if *chunk_type == ChunkType::IDAT {
+            // The image data is one stream, so it is replaced as a whole.
+            if !chunks.is_empty() && src.image_data() != dst.image_data() {
+                dst.set_image_data(chunks.iter().map(|(_, x)| x.data().to_vec()).collect());
+                copied.count += chunks.len();
+            }
+            continue;
+        }
+        for (i, chunk) in chunks {
+            let duplicate = dst
+                .chunks()
+                .iter()
+                .any(|x| x.chunk_type() == chunk_type && x.data() == chunk.data());
+            if duplicate {
+                continue;
+            }
+            let new_chunk = Chunk::new(*chunk_type, chunk.data().to_vec());
+            if let Some(index) = dst.position(&chunk_type.to_string()) {
+                if chunk_type.placement() == Placement::Header {
+                    dst.chunks_mut()[index] = new_chunk;
+                    copied.count += 1;
+                    continue;
+                }
+            }
+            if !chunk_type.allows_multiple() {
+                dst.remove_chunks_by_type(chunk_type);
+            }
+            for (copied_type, conflict) in CONFLICTS.iter() {
+                if copied_type == chunk_type && dst.remove_chunks_by_type(conflict) > 0 {
+                    copied.dropped.push(*conflict);
+                }
+            }
+            match chunk_type.placement() {
+                Placement::Header => dst.insert_chunk(0, new_chunk),
+                placement => {
+                    let before: &[ChunkType] = match placement {
+                        Placement::BeforePalette => &[ChunkType::PLTE, ChunkType::IDAT],
+                        Placement::Palette => &[
+                            ChunkType::tRNS,
+                            ChunkType::bKGD,
+                            ChunkType::hIST,
+                            ChunkType::IDAT,
+                        ],
+                        Placement::Anywhere | Placement::End if i > src_image_data => &[],
+                        _ => &[ChunkType::IDAT],
+                    };
+                    dst.insert_chunk_before(new_chunk, before);
+                }
+            }
+            copied.count += 1;
+        }
+    }
+    dst.validate()?;
+    Ok(copied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{chunk, chunk_types, testing_png};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_copy_chunks() {
+        let mut src = testing_png();
+        src.insert_chunk(1, chunk("tEXt", b"Title\0Dice"));
+        src.insert_chunk(1, chunk("iCCP", b"profile\0\0data"));
+        let mut dst = testing_png();
+        dst.remove_chunks_by_type(&ChunkType::from_str("RuSt").unwrap());
+        dst.remove_chunks_by_type(&ChunkType::gAMA);
+        dst.insert_chunk(1, chunk("PLTE", &[0, 0, 0]));
+
+        let types = ["iCCP", "tEXt", "gAMA", "RuSt"].map(|x| ChunkType::from_str(x).unwrap());
+        let limits = Limits::default();
+        assert_eq!(
+            copy_chunks(&src, &mut dst, &types[..3], false, &limits),
+            Err(CopyError::UnsafeToCopy)
+        );
+        assert_eq!(
+            copy_chunks(&src, &mut dst, &types, false, &limits),
+            Err(CopyError::CriticalChunkType)
+        );
+        let copied = copy_chunks(&src, &mut dst, &types, true, &limits).unwrap();
+        assert_eq!(copied.count, 4);
+        // iCCP and sRGB must not coexist, so the copied profile replaces sRGB.
+        assert_eq!(copied.dropped, [ChunkType::sRGB]);
+        assert_eq!(
+            chunk_types(&dst),
+            ["IHDR", "iCCP", "gAMA", "PLTE", "pHYs", "tEXt", "IDAT", "RuSt", "IEND"]
+        );
+        // Copying again changes nothing.
+        assert_eq!(
+            copy_chunks(&src, &mut dst, &types, true, &limits),
+            Ok(Copied::default())
+        );
+        assert_eq!(chunk_types(&dst).len(), 9);
+    }
+
+    #[test]
+    fn test_copy_critical_chunks() {
+        let mut src = testing_png();
+        let mut ihdr = src.chunks()[0].data().to_vec();
+        ihdr[12] = 1;
+        src.chunks_mut()[0] = Chunk::new(ChunkType::IHDR, ihdr.clone());
+        src.insert_chunk(1, chunk("PLTE", &[1, 2, 3]));
+        let mut dst = testing_png();
+        dst.insert_chunk(1, chunk("bKGD", &[0; 6]));
+
+        let types = [ChunkType::IHDR, ChunkType::PLTE];
+        let copied = copy_chunks(&src, &mut dst, &types, true, &Limits::default()).unwrap();
+        assert_eq!(copied.count, 2);
+        assert_eq!(dst.chunks()[0].data(), ihdr);
+        assert_eq!(
+            chunk_types(&dst),
+            ["IHDR", "PLTE", "bKGD", "sRGB", "gAMA", "pHYs", "IDAT", "RuSt", "IEND"]
+        );
+
+        dst.remove_chunks_by_type(&ChunkType::IDAT);
+        assert_eq!(
+            copy_chunks(
+                &src,
+                &mut dst,
+                &[ChunkType::tEXt],
+                false,
+                &Limits::default()
+            ),
+            Err(CopyError::InvalidPng(PngError::MissingIdat))
+        );
+    }
+
+    #[test]
+    fn test_copy_unsafe_when_critical_data_matches() {
+        let mut src = testing_png();
+        src.insert_chunk(1, chunk("iCCP", b"profile\0\0data"));
+        src.insert_chunk(1, chunk("tEXt", b"Title\0Dice"));
+        let mut dst = testing_png();
+        dst.remove_chunks_by_type(&ChunkType::sRGB);
+        let types = [ChunkType::iCCP, ChunkType::tEXt];
+        assert_eq!(
+            copy_chunks(&src, &mut dst, &types, false, &Limits::default()).map(|x| x.count),
+            Ok(2)
+        );
+        assert_eq!(dst.position("iCCP"), Some(3));
+        assert_eq!(dst.position("tEXt"), Some(4));
+    }
+}
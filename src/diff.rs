@@ -0,0 +1,463 @@
+use std::fmt::Display;
+
+use crate::ancillary::{latin1, Itxt, Text};
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::codec::ChunkData;
+use crate::filter::unfilter;
+use crate::limits::Limits;
+use crate::png::Png;
+use crate::zlib;
+
+/// How a chunk's contents changed between two files.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Detail {
+    /// Line-by-line changes of a text chunk or message, each prefixed with `-` or `+`.
+    Text(Vec<String>),
+    Binary {
+        old_length: usize,
+        new_length: usize,
+        /// Differing bytes over the common length, and the offset of the first one.
+        differing: usize,
+        first_offset: Option<usize>,
+    },
+}
+
+/// One difference between two files; indices are chunk positions in the old and new file.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Change {
+    Added {
+        index: usize,
+        chunk_type: ChunkType,
+    },
+    Removed {
+        index: usize,
+        chunk_type: ChunkType,
+    },
+    Moved {
+        from: usize,
+        to: usize,
+        chunk_type: ChunkType,
+    },
+    Modified {
+        from: usize,
+        to: usize,
+        chunk_type: ChunkType,
+        detail: Detail,
+    },
+}
+
+/// The result of comparing decoded image data instead of the compressed `IDAT` bytes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PixelDiff {
+    Identical,
+    Different {
+        differing: usize,
+        total: usize,
+    },
+    /// The headers differ, so the pixels cannot be compared byte for byte.
+    IncomparableHeaders,
+    Undecodable,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Diff {
+    pub changes: Vec<Change>,
+    pub pixels: Option<PixelDiff>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.pixels.is_none_or(|x| x == PixelDiff::Identical)
+    }
+}
+
+/// Compares the chunks of two files; with `compare_pixels`, `IDAT` chunks are compared by decoded pixels.
+pub fn diff(old: &Png, new: &Png, compare_pixels: bool, limits: &Limits) -> Diff {
+    let skip = |chunk: &&Chunk| !(compare_pixels && *chunk.chunk_type() == ChunkType::IDAT);
+    let old_chunks: Vec<(usize, &Chunk)> = old
+        .chunks()
+        .iter()
+        .enumerate()
+        .filter(|x| skip(&x.1))
+        .collect();
+    let new_chunks: Vec<(usize, &Chunk)> = new
+        .chunks()
+        .iter()
+        .enumerate()
+        .filter(|x| skip(&x.1))
+        .collect();
+
+    // Pair the k-th chunk of each type in one file with the k-th of that type in the other.
+    let mut pairs = vec![];
+    let mut changes = vec![];
+    let mut used = vec![false; new_chunks.len()];
+    for &(i, chunk) in old_chunks.iter() {
+        let matching = new_chunks
+            .iter()
+            .enumerate()
+            .find(|(k, (_, x))| !used[*k] && x.chunk_type() == chunk.chunk_type());
+        match matching {
+            Some((k, &(j, other))) => {
+                used[k] = true;
+                pairs.push((i, j, chunk, other));
+            }
+            None => changes.push(Change::Removed {
+                index: i,
+                chunk_type: *chunk.chunk_type(),
+            }),
+        }
+    }
+    for (k, &(j, chunk)) in new_chunks.iter().enumerate() {
+        if !used[k] {
+            changes.push(Change::Added {
+                index: j,
+                chunk_type: *chunk.chunk_type(),
+            });
+        }
+    }
+
+    // Pairs outside the longest run that keeps its relative order have been moved.
+    let positions: Vec<usize> = pairs.iter().map(|x| x.1).collect();
+    let in_order = longest_increasing(&positions);
+    for (n, &(i, j, a, b)) in pairs.iter().enumerate() {
+        let chunk_type = *a.chunk_type();
+        if a.data() != b.data() {
+            changes.push(Change::Modified {
+                from: i,
+                to: j,
+                chunk_type,
+                detail: compare_data(a, b, limits),
+            });
+        } else if !in_order[n] {
+            changes.push(Change::Moved {
+                from: i,
+                to: j,
+                chunk_type,
+            });
+        }
+    }
+    changes.sort_by_key(|x| match x {
+        Change::Removed { index, .. } => (*index, 0),
+        Change::Added { index, .. } => (*index, 1),
+        Change::Moved { from, .. } | Change::Modified { from, .. } => (*from, 2),
+    });
+
+    let pixels = compare_pixels.then(|| diff_pixels(old, new, limits));
+    Diff { changes, pixels }
+}
+
+fn compare_data(old: &Chunk, new: &Chunk, limits: &Limits) -> Detail {
+    if let (Some(a), Some(b)) = (chunk_text(old, limits), chunk_text(new, limits)) {
+        if let Some(lines) = diff_lines(&a, &b) {
+            return Detail::Text(lines);
+        }
+    }
+    let (a, b) = (old.data(), new.data());
+    let differing: Vec<usize> = (0..a.len().min(b.len()))
+        .filter(|&i| a[i] != b[i])
+        .collect();
+    Detail::Binary {
+        old_length: a.len(),
+        new_length: b.len(),
+        differing: differing.len(),
+        first_offset: differing.first().copied(),
+    }
+}
+
+/// The text of textual chunks, and of any other chunk holding printable UTF-8 such as a hidden message.
+fn chunk_text(chunk: &Chunk, limits: &Limits) -> Option<String> {
+    let data = chunk.data();
+    match *chunk.chunk_type() {
+        ChunkType::tEXt => Text::from_bytes(data).ok().map(|x| x.to_string()),
+        ChunkType::iTXt => {
+            let itxt = Itxt::from_bytes(data).ok()?;
+            let text = itxt.text(limits).ok()?;
+            Some(format!("{}: {}", itxt.keyword, text))
+        }
+        ChunkType::zTXt => {
+            let nul = data.iter().position(|&x| x == 0)?;
+            let text = zlib::inflate(data.get(nul + 2..)?, limits).ok()?;
+            Some(format!("{}: {}", latin1(&data[..nul]), latin1(&text)))
+        }
+        _ => {
+            let text = std::str::from_utf8(data).ok()?;
+            let printable = text.chars().all(|c| !c.is_control() || c.is_whitespace());
+            printable.then(|| text.to_string())
+        }
+    }
+}
+
+/// Marks which items belong to a longest strictly increasing subsequence, found by patience sorting.
+pub(crate) fn longest_increasing(values: &[usize]) -> Vec<bool> {
+    // tails[k] is the index of the smallest value ending an increasing run of length k + 1.
+    let mut tails: Vec<usize> = vec![];
+    let mut previous = vec![None; values.len()];
+    for (i, &value) in values.iter().enumerate() {
+        let k = tails.partition_point(|&t| values[t] < value);
+        previous[i] = k.checked_sub(1).map(|x| tails[x]);
+        if k == tails.len() {
+            tails.push(i);
+        } else {
+            tails[k] = i;
+        }
+    }
+    let mut keep = vec![false; values.len()];
+    let mut i = tails.last().copied();
+    while let Some(x) = i {
+        keep[x] = true;
+        i = previous[x];
+    }
+    keep
+}
+
+/// Texts with more lines than this are compared as bytes, since the line diff needs a table of
+/// one entry per pair of lines.
+const MAX_DIFF_LINES: usize = 2000;
+
+/// Lists removed and added lines using the longest common subsequence of both texts, or `None`
+/// if either has more than [`MAX_DIFF_LINES`] lines.
+fn diff_lines(old: &str, new: &str) -> Option<Vec<String>> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    if a.len() > MAX_DIFF_LINES || b.len() > MAX_DIFF_LINES {
+        return None;
+    }
+    let mut common = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i][j] = if a[i] == b[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut out = vec![];
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && common[i + 1][j] >= common[i][j + 1]) {
+            out.push(format!("- {}", a[i]));
+            i += 1;
+        } else {
+            out.push(format!("+ {}", b[j]));
+            j += 1;
+        }
+    }
+    Some(out)
+}
+
+/// Compares the decoded image data of two files with the same `IHDR`.
+pub fn diff_pixels(old: &Png, new: &Png, limits: &Limits) -> PixelDiff {
+    let (Ok(a), Ok(b)) = (old.ihdr(), new.ihdr()) else {
+        return PixelDiff::Undecodable;
+    };
+    if a != b {
+        return PixelDiff::IncomparableHeaders;
+    }
+    let decode = |png: &Png| {
+        let data = zlib::inflate(&png.image_data(), limits).ok()?;
+        unfilter(&data, &a).ok()
+    };
+    match (decode(old), decode(new)) {
+        (Some(x), Some(y)) if x == y => PixelDiff::Identical,
+        (Some(x), Some(y)) => PixelDiff::Different {
+            differing: x.iter().zip(y.iter()).filter(|(p, q)| p != q).count(),
+            total: x.len(),
+        },
+        _ => PixelDiff::Undecodable,
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Added { index, chunk_type } => write!(f, "+ {} {} added", index, chunk_type),
+            Change::Removed { index, chunk_type } => {
+                write!(f, "- {} {} removed", index, chunk_type)
+            }
+            Change::Moved {
+                from,
+                to,
+                chunk_type,
+            } => write!(f, "~ {} -> {} {} moved", from, to, chunk_type),
+            Change::Modified {
+                from,
+                to,
+                chunk_type,
+                detail,
+            } => {
+                write!(f, "* {} -> {} {} modified", from, to, chunk_type)?;
+                match detail {
+                    Detail::Text(lines) => {
+                        for line in lines {
+                            write!(f, "\n    {}", line)?;
+                        }
+                        Ok(())
+                    }
+                    Detail::Binary {
+                        old_length,
+                        new_length,
+                        differing,
+                        first_offset,
+                    } => {
+                        write!(f, ": {} -> {} bytes", old_length, new_length)?;
+                        match first_offset {
+                            Some(x) => write!(f, ", {} bytes differ from offset {}", differing, x),
+                            None => Ok(()),
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Display for PixelDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PixelDiff::Identical => write!(f, "identical"),
+            PixelDiff::Different { differing, total } => {
+                write!(f, "{} of {} bytes differ", differing, total)
+            }
+            PixelDiff::IncomparableHeaders => write!(f, "not comparable, IHDR differs"),
+            PixelDiff::Undecodable => write!(f, "could not decode image data"),
+        }
+    }
+}
+
+impl Display for Diff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for change in self.changes.iter() {
+            writeln!(f, "{}", change)?;
+        }
+        if let Some(pixels) = self.pixels {
+            writeln!(f, "Pixels: {}", pixels)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::{filter, FilterType};
+    use crate::testing::{chunk, testing_png};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_identical() {
+        let diff = diff(&testing_png(), &testing_png(), true, &Limits::default());
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "Pixels: identical\n");
+    }
+
+    #[test]
+    fn test_chunk_changes() {
+        let old = Png::from_chunks(vec![
+            chunk("IHDR", b"header"),
+            chunk("tEXt", b"Comment\0one\ntwo\nthree"),
+            chunk("gAMA", &[0, 0, 177, 143]),
+            chunk("pHYs", &[0, 0, 0, 1, 0, 0, 0, 1, 1]),
+            chunk("ruSt", b"old"),
+            chunk("IEND", b""),
+        ]);
+        let new = Png::from_chunks(vec![
+            chunk("IHDR", b"header"),
+            chunk("pHYs", &[0, 0, 0, 2, 0, 0, 0, 1, 1]),
+            chunk("tEXt", b"Comment\0one\n2\nthree"),
+            chunk("gAMA", &[0, 0, 177, 143]),
+            chunk("tIME", &[7, 234, 1, 1, 0, 0, 0]),
+            chunk("IEND", b""),
+        ]);
+        let diff = diff(&old, &new, false, &Limits::default());
+        let lines: Vec<String> = diff.changes.iter().map(|x| x.to_string()).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "* 1 -> 2 tEXt modified\n    - two\n    + 2",
+                "* 3 -> 1 pHYs modified: 9 -> 9 bytes, 1 bytes differ from offset 3",
+                "- 4 ruSt removed",
+                "+ 4 tIME added",
+            ]
+        );
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn test_moved() {
+        let old = Png::from_chunks(vec![
+            chunk("aaAa", b""),
+            chunk("bbBb", b""),
+            chunk("ccCc", b""),
+        ]);
+        let new = Png::from_chunks(vec![
+            chunk("ccCc", b""),
+            chunk("aaAa", b""),
+            chunk("bbBb", b""),
+        ]);
+        let diff = diff(&old, &new, false, &Limits::default());
+        assert_eq!(
+            diff.changes,
+            vec![Change::Moved {
+                from: 2,
+                to: 0,
+                chunk_type: ChunkType::from_str("ccCc").unwrap()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_recompressed_pixels() {
+        let old = testing_png();
+        let mut new = testing_png();
+        let ihdr = new.ihdr().unwrap();
+        let data = zlib::inflate(&new.image_data(), &Limits::default()).unwrap();
+        let raw = unfilter(&data, &ihdr).unwrap();
+        let data =
+            miniz_oxide::deflate::compress_to_vec_zlib(&filter(&raw, &ihdr, FilterType::Up), 9);
+        let half = data.len() / 2;
+        new.set_image_data(vec![data[..half].to_vec(), data[half..].to_vec()]);
+
+        let by_bytes = diff(&old, &new, false, &Limits::default());
+        assert!(by_bytes
+            .changes
+            .iter()
+            .any(|x| matches!(x, Change::Added { .. })));
+        let by_pixels = diff(&old, &new, true, &Limits::default());
+        assert!(by_pixels.is_empty());
+    }
+
+    #[test]
+    fn test_longest_increasing() {
+        assert_eq!(
+            longest_increasing(&[2, 0, 1, 3]),
+            vec![false, true, true, true]
+        );
+        assert_eq!(
+            longest_increasing(&[3, 1, 4, 1, 5, 9, 2, 6]),
+            vec![false, true, true, false, true, false, false, true]
+        );
+        assert_eq!(longest_increasing(&[]), Vec::<bool>::new());
+    }
+
+    #[test]
+    fn test_diff_lines_limit() {
+        assert_eq!(
+            diff_lines("a\nb\nc", "a\nc\nd"),
+            Some(vec!["- b".to_string(), "+ d".to_string()])
+        );
+        let long = "x\n".repeat(MAX_DIFF_LINES + 1);
+        assert_eq!(diff_lines(&long, "x"), None);
+
+        let old = Chunk::new(ChunkType::tEXt, format!("Comment\0{}", long).into_bytes());
+        let new = Chunk::new(ChunkType::tEXt, b"Comment\0x".to_vec());
+        assert!(matches!(
+            compare_data(&old, &new, &Limits::default()),
+            Detail::Binary { .. }
+        ));
+    }
+}
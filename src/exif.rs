@@ -0,0 +1,924 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use derive_more::{Display, Error};
+
+use crate::png::Png;
+
+#[derive(PartialEq, Debug, Display, Error)]
+pub enum ExifError {
+    InvalidHeader,
+    UnexpectedEof,
+    InvalidIfd,
+    UnknownTag,
+    InvalidValue,
+    AfterImageData,
+    /// Counts and offsets must fit the fixed-width TIFF fields.
+    TooLarge,
+}
+
+const EXIF_POINTER: u16 = 0x8769;
+const GPS_POINTER: u16 = 0x8825;
+const INTEROP_POINTER: u16 = 0xa005;
+const THUMBNAIL_OFFSET: u16 = 0x0201;
+const THUMBNAIL_LENGTH: u16 = 0x0202;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ByteOrder {
+    LittleEndian,
+    BigEndian,
+}
+
+impl ByteOrder {
+    fn u16(self, b: &[u8]) -> u16 {
+        let b = [b[0], b[1]];
+        match self {
+            ByteOrder::LittleEndian => u16::from_le_bytes(b),
+            ByteOrder::BigEndian => u16::from_be_bytes(b),
+        }
+    }
+    fn u32(self, b: &[u8]) -> u32 {
+        let b = [b[0], b[1], b[2], b[3]];
+        match self {
+            ByteOrder::LittleEndian => u32::from_le_bytes(b),
+            ByteOrder::BigEndian => u32::from_be_bytes(b),
+        }
+    }
+    fn u64(self, b: &[u8]) -> u64 {
+        let b: [u8; 8] = b[..8].try_into().unwrap();
+        match self {
+            ByteOrder::LittleEndian => u64::from_le_bytes(b),
+            ByteOrder::BigEndian => u64::from_be_bytes(b),
+        }
+    }
+    fn put_u16(self, out: &mut Vec<u8>, x: u16) {
+        match self {
+            ByteOrder::LittleEndian => out.extend(x.to_le_bytes()),
+            ByteOrder::BigEndian => out.extend(x.to_be_bytes()),
+        }
+    }
+    fn put_u32(self, out: &mut Vec<u8>, x: u32) {
+        match self {
+            ByteOrder::LittleEndian => out.extend(x.to_le_bytes()),
+            ByteOrder::BigEndian => out.extend(x.to_be_bytes()),
+        }
+    }
+    fn put_u64(self, out: &mut Vec<u8>, x: u64) {
+        match self {
+            ByteOrder::LittleEndian => out.extend(x.to_le_bytes()),
+            ByteOrder::BigEndian => out.extend(x.to_be_bytes()),
+        }
+    }
+    fn header(self) -> &'static [u8; 4] {
+        match self {
+            ByteOrder::LittleEndian => b"II*\0",
+            ByteOrder::BigEndian => b"MM\0*",
+        }
+    }
+}
+
+/// Checks for the `II*\0` or `MM\0*` TIFF header.
+pub fn byte_order(data: &[u8]) -> Result<ByteOrder, ExifError> {
+    match data.get(0..4) {
+        Some(b"II*\0") => Ok(ByteOrder::LittleEndian),
+        Some(b"MM\0*") => Ok(ByteOrder::BigEndian),
+        _ => Err(ExifError::InvalidHeader),
+    }
+}
+
+/// The image file directories an EXIF block can contain.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum IfdKind {
+    Primary,
+    Exif,
+    Gps,
+    Interop,
+    Thumbnail,
+}
+
+impl IfdKind {
+    const ALL: [IfdKind; 5] = [
+        IfdKind::Primary,
+        IfdKind::Exif,
+        IfdKind::Gps,
+        IfdKind::Interop,
+        IfdKind::Thumbnail,
+    ];
+}
+
+impl FromStr for IfdKind {
+    type Err = ExifError;
+
+    /// Parses the names `Display` prints, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        IfdKind::ALL
+            .into_iter()
+            .find(|x| x.to_string().eq_ignore_ascii_case(s))
+            .ok_or(ExifError::InvalidIfd)
+    }
+}
+
+impl Display for IfdKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            IfdKind::Primary => "IFD0",
+            IfdKind::Exif => "Exif",
+            IfdKind::Gps => "GPS",
+            IfdKind::Interop => "Interop",
+            IfdKind::Thumbnail => "IFD1",
+        };
+        f.pad(name)
+    }
+}
+
+const BYTE: u16 = 1;
+const ASCII: u16 = 2;
+const SHORT: u16 = 3;
+const LONG: u16 = 4;
+const RATIONAL: u16 = 5;
+const UNDEFINED: u16 = 7;
+
+const TYPE_NAMES: [&str; 12] = [
+    "byte",
+    "ascii",
+    "short",
+    "long",
+    "rational",
+    "sbyte",
+    "undefined",
+    "sshort",
+    "slong",
+    "srational",
+    "float",
+    "double",
+];
+
+/// Parses a TIFF field type name such as `short` or `rational` into its type id.
+pub fn parse_type(name: &str) -> Result<u16, ExifError> {
+    TYPE_NAMES
+        .iter()
+        .position(|x| x.eq_ignore_ascii_case(name))
+        .map(|i| i as u16 + 1)
+        .ok_or(ExifError::InvalidValue)
+}
+
+/// Well-known tags with the field type used when a tag is newly added.
+const TAGS: &[(IfdKind, u16, &str, u16)] = &[
+    (IfdKind::Primary, 0x010e, "ImageDescription", ASCII),
+    (IfdKind::Primary, 0x010f, "Make", ASCII),
+    (IfdKind::Primary, 0x0110, "Model", ASCII),
+    (IfdKind::Primary, 0x0112, "Orientation", SHORT),
+    (IfdKind::Primary, 0x011a, "XResolution", RATIONAL),
+    (IfdKind::Primary, 0x011b, "YResolution", RATIONAL),
+    (IfdKind::Primary, 0x0128, "ResolutionUnit", SHORT),
+    (IfdKind::Primary, 0x0131, "Software", ASCII),
+    (IfdKind::Primary, 0x0132, "DateTime", ASCII),
+    (IfdKind::Primary, 0x013b, "Artist", ASCII),
+    (IfdKind::Primary, 0x8298, "Copyright", ASCII),
+    (IfdKind::Exif, 0x829a, "ExposureTime", RATIONAL),
+    (IfdKind::Exif, 0x829d, "FNumber", RATIONAL),
+    (IfdKind::Exif, 0x8827, "ISOSpeedRatings", SHORT),
+    (IfdKind::Exif, 0x9000, "ExifVersion", UNDEFINED),
+    (IfdKind::Exif, 0x9003, "DateTimeOriginal", ASCII),
+    (IfdKind::Exif, 0x9004, "DateTimeDigitized", ASCII),
+    (IfdKind::Exif, 0x9010, "OffsetTime", ASCII),
+    (IfdKind::Exif, 0x9011, "OffsetTimeOriginal", ASCII),
+    (IfdKind::Exif, 0x920a, "FocalLength", RATIONAL),
+    (IfdKind::Exif, 0x927c, "MakerNote", UNDEFINED),
+    (IfdKind::Exif, 0x9286, "UserComment", UNDEFINED),
+    (IfdKind::Exif, 0xa001, "ColorSpace", SHORT),
+    (IfdKind::Exif, 0xa002, "PixelXDimension", LONG),
+    (IfdKind::Exif, 0xa003, "PixelYDimension", LONG),
+    (IfdKind::Exif, 0xa433, "LensMake", ASCII),
+    (IfdKind::Exif, 0xa434, "LensModel", ASCII),
+    (IfdKind::Gps, 0x0000, "GPSVersionID", BYTE),
+    (IfdKind::Gps, 0x0001, "GPSLatitudeRef", ASCII),
+    (IfdKind::Gps, 0x0002, "GPSLatitude", RATIONAL),
+    (IfdKind::Gps, 0x0003, "GPSLongitudeRef", ASCII),
+    (IfdKind::Gps, 0x0004, "GPSLongitude", RATIONAL),
+    (IfdKind::Gps, 0x0005, "GPSAltitudeRef", BYTE),
+    (IfdKind::Gps, 0x0006, "GPSAltitude", RATIONAL),
+    (IfdKind::Gps, 0x0007, "GPSTimeStamp", RATIONAL),
+    (IfdKind::Gps, 0x001d, "GPSDateStamp", ASCII),
+    (IfdKind::Interop, 0x0001, "InteroperabilityIndex", ASCII),
+];
+
+pub fn tag_name(kind: IfdKind, tag: u16) -> Option<&'static str> {
+    TAGS.iter().find(|x| x.0 == kind && x.1 == tag).map(|x| x.2)
+}
+
+/// Looks up a well-known tag by name, ignoring case.
+pub fn find_tag(name: &str) -> Option<(IfdKind, u16)> {
+    TAGS.iter()
+        .find(|x| x.2.eq_ignore_ascii_case(name))
+        .map(|x| (x.0, x.1))
+}
+
+fn default_type(kind: IfdKind, tag: u16) -> Option<u16> {
+    TAGS.iter().find(|x| x.0 == kind && x.1 == tag).map(|x| x.3)
+}
+
+/// A decoded TIFF field value.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Byte(Vec<u8>),
+    Ascii(String),
+    Short(Vec<u16>),
+    Long(Vec<u32>),
+    Rational(Vec<(u32, u32)>),
+    SByte(Vec<i8>),
+    Undefined(Vec<u8>),
+    SShort(Vec<i16>),
+    SLong(Vec<i32>),
+    SRational(Vec<(i32, i32)>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+}
+
+impl Value {
+    fn element_size(type_id: u16) -> Option<usize> {
+        match type_id {
+            1 | 2 | 6 | 7 => Some(1),
+            3 | 8 => Some(2),
+            4 | 9 | 11 => Some(4),
+            5 | 10 | 12 => Some(8),
+            _ => None,
+        }
+    }
+    pub fn type_id(&self) -> u16 {
+        match self {
+            Value::Byte(_) => 1,
+            Value::Ascii(_) => 2,
+            Value::Short(_) => 3,
+            Value::Long(_) => 4,
+            Value::Rational(_) => 5,
+            Value::SByte(_) => 6,
+            Value::Undefined(_) => 7,
+            Value::SShort(_) => 8,
+            Value::SLong(_) => 9,
+            Value::SRational(_) => 10,
+            Value::Float(_) => 11,
+            Value::Double(_) => 12,
+        }
+    }
+    fn decode(type_id: u16, data: &[u8], order: ByteOrder) -> Value {
+        let u16s = || data.chunks(2).map(|x| order.u16(x));
+        let u32s = || data.chunks(4).map(|x| order.u32(x));
+        let pairs = || data.chunks(8).map(|x| (order.u32(x), order.u32(&x[4..])));
+        match type_id {
+            1 => Value::Byte(data.to_vec()),
+            2 => Value::Ascii(
+                String::from_utf8_lossy(data)
+                    .trim_end_matches('\0')
+                    .to_string(),
+            ),
+            3 => Value::Short(u16s().collect()),
+            4 => Value::Long(u32s().collect()),
+            5 => Value::Rational(pairs().collect()),
+            6 => Value::SByte(data.iter().map(|x| *x as i8).collect()),
+            8 => Value::SShort(u16s().map(|x| x as i16).collect()),
+            9 => Value::SLong(u32s().map(|x| x as i32).collect()),
+            10 => Value::SRational(pairs().map(|(n, d)| (n as i32, d as i32)).collect()),
+            11 => Value::Float(u32s().map(f32::from_bits).collect()),
+            12 => Value::Double(
+                data.chunks(8)
+                    .map(|x| f64::from_bits(order.u64(x)))
+                    .collect(),
+            ),
+            _ => Value::Undefined(data.to_vec()),
+        }
+    }
+    /// Number of elements, as stored in the field's count.
+    fn count(&self) -> usize {
+        match self {
+            Value::Byte(x) | Value::Undefined(x) => x.len(),
+            Value::Ascii(x) => x.len() + 1,
+            Value::Short(x) => x.len(),
+            Value::Long(x) => x.len(),
+            Value::Rational(x) => x.len(),
+            Value::SByte(x) => x.len(),
+            Value::SShort(x) => x.len(),
+            Value::SLong(x) => x.len(),
+            Value::SRational(x) => x.len(),
+            Value::Float(x) => x.len(),
+            Value::Double(x) => x.len(),
+        }
+    }
+    fn encode(&self, order: ByteOrder) -> Vec<u8> {
+        let mut out = vec![];
+        match self {
+            Value::Byte(x) | Value::Undefined(x) => out.extend(x),
+            Value::Ascii(x) => {
+                out.extend(x.as_bytes());
+                out.push(0);
+            }
+            Value::Short(x) => x.iter().for_each(|x| order.put_u16(&mut out, *x)),
+            Value::Long(x) => x.iter().for_each(|x| order.put_u32(&mut out, *x)),
+            Value::Rational(x) => x.iter().for_each(|(n, d)| {
+                order.put_u32(&mut out, *n);
+                order.put_u32(&mut out, *d);
+            }),
+            Value::SByte(x) => out.extend(x.iter().map(|x| *x as u8)),
+            Value::SShort(x) => x.iter().for_each(|x| order.put_u16(&mut out, *x as u16)),
+            Value::SLong(x) => x.iter().for_each(|x| order.put_u32(&mut out, *x as u32)),
+            Value::SRational(x) => x.iter().for_each(|(n, d)| {
+                order.put_u32(&mut out, *n as u32);
+                order.put_u32(&mut out, *d as u32);
+            }),
+            Value::Float(x) => x.iter().for_each(|x| order.put_u32(&mut out, x.to_bits())),
+            Value::Double(x) => x.iter().for_each(|x| order.put_u64(&mut out, x.to_bits())),
+        }
+        out
+    }
+    /// Parses `s` as a value of field type `type_id`; lists are comma separated and rationals written `n/d`.
+    pub fn parse(type_id: u16, s: &str) -> Result<Value, ExifError> {
+        fn list<T: FromStr>(s: &str) -> Result<Vec<T>, ExifError> {
+            s.split(',')
+                .map(|x| x.trim().parse().map_err(|_| ExifError::InvalidValue))
+                .collect()
+        }
+        fn fractions<T: FromStr>(s: &str) -> Result<Vec<(T, T)>, ExifError> {
+            s.split(',')
+                .map(|x| {
+                    let (n, d) = x.trim().split_once('/').unwrap_or((x.trim(), "1"));
+                    match (n.parse(), d.parse()) {
+                        (Ok(n), Ok(d)) => Ok((n, d)),
+                        _ => Err(ExifError::InvalidValue),
+                    }
+                })
+                .collect()
+        }
+        match type_id {
+            1 => Ok(Value::Byte(list(s)?)),
+            2 => Ok(Value::Ascii(s.to_string())),
+            3 => Ok(Value::Short(list(s)?)),
+            4 => Ok(Value::Long(list(s)?)),
+            5 => Ok(Value::Rational(fractions(s)?)),
+            6 => Ok(Value::SByte(list(s)?)),
+            7 => Ok(Value::Undefined(s.as_bytes().to_vec())),
+            8 => Ok(Value::SShort(list(s)?)),
+            9 => Ok(Value::SLong(list(s)?)),
+            10 => Ok(Value::SRational(fractions(s)?)),
+            11 => Ok(Value::Float(list(s)?)),
+            12 => Ok(Value::Double(list(s)?)),
+            _ => Err(ExifError::InvalidValue),
+        }
+    }
+    /// The first element as an unsigned integer, for offsets and enumerated tags.
+    pub fn as_u32(&self) -> Option<u32> {
+        match self {
+            Value::Byte(x) => x.first().map(|x| *x as u32),
+            Value::Short(x) => x.first().map(|x| *x as u32),
+            Value::Long(x) => x.first().copied(),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Ascii(x) => Some(x.as_str()),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn join<T: Display>(x: impl Iterator<Item = T>) -> String {
+            x.map(|x| x.to_string()).collect::<Vec<_>>().join(", ")
+        }
+        let s = match self {
+            Value::Ascii(x) => x.clone(),
+            Value::Undefined(x) if x.iter().all(|b| b.is_ascii_graphic() || *b == b' ') => {
+                String::from_utf8_lossy(x).to_string()
+            }
+            Value::Undefined(x) => format!("{} bytes", x.len()),
+            Value::Byte(x) => join(x.iter()),
+            Value::Short(x) => join(x.iter()),
+            Value::Long(x) => join(x.iter()),
+            Value::Rational(x) => join(x.iter().map(|(n, d)| format!("{}/{}", n, d))),
+            Value::SByte(x) => join(x.iter()),
+            Value::SShort(x) => join(x.iter()),
+            Value::SLong(x) => join(x.iter()),
+            Value::SRational(x) => join(x.iter().map(|(n, d)| format!("{}/{}", n, d))),
+            Value::Float(x) => join(x.iter()),
+            Value::Double(x) => join(x.iter()),
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Entry {
+    pub tag: u16,
+    pub value: Value,
+}
+
+/// An image file directory; entries are kept sorted by tag as TIFF requires.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Ifd {
+    pub entries: Vec<Entry>,
+}
+
+impl Ifd {
+    pub fn get(&self, tag: u16) -> Option<&Value> {
+        self.entries.iter().find(|x| x.tag == tag).map(|x| &x.value)
+    }
+    pub fn set(&mut self, tag: u16, value: Value) {
+        match self.entries.binary_search_by_key(&tag, |x| x.tag) {
+            Ok(i) => self.entries[i].value = value,
+            Err(i) => self.entries.insert(i, Entry { tag, value }),
+        }
+    }
+    pub fn remove(&mut self, tag: u16) -> Option<Value> {
+        let i = self.entries.iter().position(|x| x.tag == tag)?;
+        Some(self.entries.remove(i).value)
+    }
+    /// Removes a pointer entry and returns the offset it held.
+    fn take_offset(&mut self, tag: u16) -> Option<usize> {
+        self.remove(tag)
+            .and_then(|x| x.as_u32())
+            .map(|x| x as usize)
+    }
+    /// Encoded size: count, entries, next-IFD offset and word-aligned out-of-line values.
+    fn size(&self) -> usize {
+        let values: usize = self
+            .entries
+            .iter()
+            .map(|x| x.value.encode(ByteOrder::BigEndian).len())
+            .filter(|x| *x > 4)
+            .map(|x| x + x % 2)
+            .sum();
+        2 + 12 * self.entries.len() + 4 + values
+    }
+    fn write(&self, out: &mut Vec<u8>, order: ByteOrder, next: u32) -> Result<(), ExifError> {
+        let base = out.len();
+        let mut values = vec![];
+        let values_offset = base + 2 + 12 * self.entries.len() + 4;
+        order.put_u16(out, to_u16(self.entries.len())?);
+        for entry in self.entries.iter() {
+            let encoded = entry.value.encode(order);
+            order.put_u16(out, entry.tag);
+            order.put_u16(out, entry.value.type_id());
+            order.put_u32(out, to_u32(entry.value.count())?);
+            if encoded.len() <= 4 {
+                out.extend(&encoded);
+                out.extend(std::iter::repeat_n(0, 4 - encoded.len()));
+            } else {
+                order.put_u32(out, to_u32(values_offset + values.len())?);
+                values.extend(encoded);
+                if values.len() % 2 == 1 {
+                    values.push(0);
+                }
+            }
+        }
+        order.put_u32(out, next);
+        out.extend(values);
+        Ok(())
+    }
+}
+
+fn to_u16(x: usize) -> Result<u16, ExifError> {
+    u16::try_from(x).map_err(|_| ExifError::TooLarge)
+}
+
+fn to_u32(x: usize) -> Result<u32, ExifError> {
+    u32::try_from(x).map_err(|_| ExifError::TooLarge)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    order: ByteOrder,
+    visited: HashSet<usize>,
+}
+
+impl Reader<'_> {
+    fn slice(&self, offset: usize, length: usize) -> Result<&[u8], ExifError> {
+        offset
+            .checked_add(length)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or(ExifError::UnexpectedEof)
+    }
+    /// Reads the IFD at `offset`, returning it with the offset of the next IFD.
+    fn read_ifd(&mut self, offset: usize) -> Result<(Ifd, usize), ExifError> {
+        // Each IFD may only be visited once, so malicious offsets cannot loop.
+        if !self.visited.insert(offset) {
+            return Err(ExifError::InvalidIfd);
+        }
+        let count = self.order.u16(self.slice(offset, 2)?) as usize;
+        let mut ifd = Ifd::default();
+        for i in 0..count {
+            let field = self.slice(offset + 2 + 12 * i, 12)?;
+            let tag = self.order.u16(field);
+            let type_id = self.order.u16(&field[2..]);
+            let count = self.order.u32(&field[4..]) as usize;
+            // Fields of unknown types cannot be sized, so they are dropped.
+            let Some(size) = Value::element_size(type_id) else {
+                continue;
+            };
+            let length = size.checked_mul(count).ok_or(ExifError::UnexpectedEof)?;
+            let data = if length <= 4 {
+                &field[8..8 + length]
+            } else {
+                self.slice(self.order.u32(&field[8..]) as usize, length)?
+            };
+            ifd.set(tag, Value::decode(type_id, data, self.order));
+        }
+        let next = self.order.u32(self.slice(offset + 2 + 12 * count, 4)?);
+        Ok((ifd, next as usize))
+    }
+}
+
+/// EXIF data parsed from the TIFF structure of an `eXIf` chunk.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ExifData {
+    pub byte_order: ByteOrder,
+    pub primary: Ifd,
+    pub exif: Option<Ifd>,
+    pub gps: Option<Ifd>,
+    pub interop: Option<Ifd>,
+    /// The IFD1 tags and the JPEG thumbnail they describe.
+    pub thumbnail: Option<(Ifd, Vec<u8>)>,
+}
+
+impl ExifData {
+    /// Empty EXIF data in network byte order, like the rest of a PNG file.
+    pub fn new() -> ExifData {
+        ExifData {
+            byte_order: ByteOrder::BigEndian,
+            primary: Ifd::default(),
+            exif: None,
+            gps: None,
+            interop: None,
+            thumbnail: None,
+        }
+    }
+    pub fn parse(data: &[u8]) -> Result<ExifData, ExifError> {
+        let order = byte_order(data)?;
+        let mut reader = Reader {
+            data,
+            order,
+            visited: HashSet::new(),
+        };
+        let (mut primary, next) = reader.read_ifd(order.u32(reader.slice(4, 4)?) as usize)?;
+
+        let mut sub_ifd = |ifd: &mut Ifd, tag: u16| match ifd.take_offset(tag) {
+            Some(offset) => reader.read_ifd(offset).map(|x| Some(x.0)),
+            None => Ok(None),
+        };
+        let mut exif = sub_ifd(&mut primary, EXIF_POINTER)?;
+        let gps = sub_ifd(&mut primary, GPS_POINTER)?;
+        let interop = match exif.as_mut() {
+            Some(x) => sub_ifd(x, INTEROP_POINTER)?,
+            None => None,
+        };
+
+        let thumbnail = if next == 0 {
+            None
+        } else {
+            let (mut ifd, _) = reader.read_ifd(next)?;
+            let offset = ifd.take_offset(THUMBNAIL_OFFSET);
+            let length = ifd.take_offset(THUMBNAIL_LENGTH);
+            let jpeg = match (offset, length) {
+                (Some(offset), Some(length)) => reader.slice(offset, length)?.to_vec(),
+                _ => vec![],
+            };
+            Some((ifd, jpeg))
+        };
+
+        Ok(ExifData {
+            byte_order: order,
+            primary,
+            exif,
+            gps,
+            interop,
+            thumbnail,
+        })
+    }
+    /// Serialises the IFDs one after another, rewriting every offset.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ExifError> {
+        let order = self.byte_order;
+        let pointer = |ifd: &mut Ifd, tag: u16, offset: usize| {
+            ifd.set(tag, Value::Long(vec![to_u32(offset)?]));
+            Ok::<_, ExifError>(())
+        };
+
+        // Pointers hold placeholders first; they are inline, so IFD sizes do not change.
+        let mut primary = self.primary.clone();
+        let mut exif = self.exif.clone();
+        let gps = self.gps.clone();
+        let interop = self.interop.clone();
+        let mut thumbnail = self.thumbnail.as_ref().map(|x| x.0.clone());
+        if exif.is_some() {
+            pointer(&mut primary, EXIF_POINTER, 0)?;
+        }
+        if gps.is_some() {
+            pointer(&mut primary, GPS_POINTER, 0)?;
+        }
+        if let (Some(exif), Some(_)) = (exif.as_mut(), interop.as_ref()) {
+            pointer(exif, INTEROP_POINTER, 0)?;
+        }
+        if let Some(ifd) = thumbnail.as_mut() {
+            pointer(ifd, THUMBNAIL_OFFSET, 0)?;
+            pointer(
+                ifd,
+                THUMBNAIL_LENGTH,
+                self.thumbnail.as_ref().unwrap().1.len(),
+            )?;
+        }
+
+        let mut offset = 8 + primary.size();
+        let mut place = |ifd: &Option<Ifd>| {
+            let start = offset;
+            offset += ifd.as_ref().map_or(0, Ifd::size);
+            start
+        };
+        let exif_offset = place(&exif);
+        let interop_offset = place(&interop);
+        let gps_offset = place(&gps);
+        let thumbnail_offset = place(&thumbnail);
+        let jpeg_offset = offset;
+
+        if exif.is_some() {
+            pointer(&mut primary, EXIF_POINTER, exif_offset)?;
+        }
+        if gps.is_some() {
+            pointer(&mut primary, GPS_POINTER, gps_offset)?;
+        }
+        if let (Some(exif), Some(_)) = (exif.as_mut(), interop.as_ref()) {
+            pointer(exif, INTEROP_POINTER, interop_offset)?;
+        }
+        if let Some(ifd) = thumbnail.as_mut() {
+            pointer(ifd, THUMBNAIL_OFFSET, jpeg_offset)?;
+        }
+
+        let mut out = order.header().to_vec();
+        order.put_u32(&mut out, 8);
+        let next = if thumbnail.is_some() {
+            thumbnail_offset
+        } else {
+            0
+        };
+        primary.write(&mut out, order, to_u32(next)?)?;
+        for ifd in [exif, interop, gps, thumbnail].iter().flatten() {
+            ifd.write(&mut out, order, 0)?;
+        }
+        if let Some((_, jpeg)) = self.thumbnail.as_ref() {
+            out.extend(jpeg);
+        }
+        Ok(out)
+    }
+    pub fn ifd(&self, kind: IfdKind) -> Option<&Ifd> {
+        match kind {
+            IfdKind::Primary => Some(&self.primary),
+            IfdKind::Exif => self.exif.as_ref(),
+            IfdKind::Gps => self.gps.as_ref(),
+            IfdKind::Interop => self.interop.as_ref(),
+            IfdKind::Thumbnail => self.thumbnail.as_ref().map(|x| &x.0),
+        }
+    }
+    /// The IFD of `kind`, created if missing; a thumbnail IFD is never created.
+    pub fn ifd_mut(&mut self, kind: IfdKind) -> Option<&mut Ifd> {
+        match kind {
+            IfdKind::Primary => Some(&mut self.primary),
+            IfdKind::Exif => Some(self.exif.get_or_insert_with(Ifd::default)),
+            IfdKind::Gps => Some(self.gps.get_or_insert_with(Ifd::default)),
+            IfdKind::Interop => {
+                self.exif.get_or_insert_with(Ifd::default);
+                Some(self.interop.get_or_insert_with(Ifd::default))
+            }
+            IfdKind::Thumbnail => self.thumbnail.as_mut().map(|x| &mut x.0),
+        }
+    }
+    pub fn get(&self, kind: IfdKind, tag: u16) -> Option<&Value> {
+        self.ifd(kind)?.get(tag)
+    }
+    /// Every entry together with the IFD it belongs to.
+    pub fn entries(&self) -> impl Iterator<Item = (IfdKind, &Entry)> {
+        IfdKind::ALL.into_iter().flat_map(move |kind| {
+            self.ifd(kind)
+                .into_iter()
+                .flat_map(move |ifd| ifd.entries.iter().map(move |x| (kind, x)))
+        })
+    }
+    /// Sets `tag`, parsing `value` with the tag's existing or default field type.
+    pub fn set(&mut self, kind: IfdKind, tag: u16, value: &str) -> Result<(), ExifError> {
+        let type_id = match self.get(kind, tag) {
+            Some(x) => x.type_id(),
+            None => default_type(kind, tag).ok_or(ExifError::UnknownTag)?,
+        };
+        self.set_as(kind, tag, type_id, value)
+    }
+    /// Sets `tag`, parsing `value` as field type `type_id`; needed for tags missing from the table.
+    pub fn set_as(
+        &mut self,
+        kind: IfdKind,
+        tag: u16,
+        type_id: u16,
+        value: &str,
+    ) -> Result<(), ExifError> {
+        let value = Value::parse(type_id, value)?;
+        self.ifd_mut(kind)
+            .ok_or(ExifError::UnknownTag)?
+            .set(tag, value);
+        Ok(())
+    }
+    pub fn orientation(&self) -> Option<u32> {
+        self.get(IfdKind::Primary, 0x0112)?.as_u32()
+    }
+    /// Camera make and model.
+    pub fn camera(&self) -> Option<String> {
+        let make = self.get(IfdKind::Primary, 0x010f).and_then(Value::as_str);
+        let model = self.get(IfdKind::Primary, 0x0110).and_then(Value::as_str);
+        match (make, model) {
+            (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+            (Some(x), None) | (None, Some(x)) => Some(x.to_string()),
+            (None, None) => None,
+        }
+    }
+    /// When the photo was taken, falling back to the modification time.
+    pub fn date_time(&self) -> Option<&str> {
+        self.get(IfdKind::Exif, 0x9003)
+            .or_else(|| self.get(IfdKind::Primary, 0x0132))
+            .and_then(Value::as_str)
+    }
+    /// Latitude and longitude in signed decimal degrees.
+    pub fn gps_position(&self) -> Option<(f64, f64)> {
+        let coordinate = |tag: u16, negative: &str| {
+            let degrees = match self.get(IfdKind::Gps, tag + 1)? {
+                Value::Rational(x) if x.len() == 3 && x.iter().all(|x| x.1 != 0) => x
+                    .iter()
+                    .zip([1.0, 60.0, 3600.0])
+                    .map(|((n, d), scale)| *n as f64 / *d as f64 / scale)
+                    .sum::<f64>(),
+                _ => return None,
+            };
+            match self.get(IfdKind::Gps, tag)?.as_str()? {
+                x if x == negative => Some(-degrees),
+                _ => Some(degrees),
+            }
+        };
+        Some((coordinate(0x0001, "S")?, coordinate(0x0003, "W")?))
+    }
+    /// Drops the GPS IFD, returning whether there was one.
+    pub fn remove_gps(&mut self) -> bool {
+        self.gps.take().is_some()
+    }
+}
+
+impl Default for ExifData {
+    fn default() -> Self {
+        ExifData::new()
+    }
+}
+
+/// Checks that `eXIf` comes before the image data, where the specification requires it.
+pub fn check_placement(png: &Png) -> Result<(), ExifError> {
+    match (png.position("eXIf"), png.position("IDAT")) {
+        (Some(exif), Some(idat)) if exif > idat => Err(ExifError::AfterImageData),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+
+    fn testing_exif(order: ByteOrder) -> ExifData {
+        let mut exif = ExifData {
+            byte_order: order,
+            ..ExifData::new()
+        };
+        exif.set(IfdKind::Primary, 0x010f, "Canon").unwrap();
+        exif.set(IfdKind::Primary, 0x0110, "EOS 5D").unwrap();
+        exif.set(IfdKind::Primary, 0x0112, "6").unwrap();
+        exif.set(IfdKind::Exif, 0x9003, "2024:01:02 03:04:05")
+            .unwrap();
+        exif.set(IfdKind::Exif, 0x9000, "0232").unwrap();
+        exif.set(IfdKind::Gps, 0x0001, "S").unwrap();
+        exif.set(IfdKind::Gps, 0x0002, "33/1,52/1,0/1").unwrap();
+        exif.set(IfdKind::Gps, 0x0003, "E").unwrap();
+        exif.set(IfdKind::Gps, 0x0004, "151/1,12/1,36/1").unwrap();
+        exif.set(IfdKind::Interop, 0x0001, "R98").unwrap();
+        exif.thumbnail = Some((
+            Ifd {
+                entries: vec![Entry {
+                    tag: 0x0103,
+                    value: Value::Short(vec![6]),
+                }],
+            },
+            vec![0xff, 0xd8, 0xff, 0xd9],
+        ));
+        exif
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for order in [ByteOrder::BigEndian, ByteOrder::LittleEndian] {
+            let exif = testing_exif(order);
+            let bytes = exif.to_bytes().unwrap();
+            assert_eq!(byte_order(&bytes), Ok(order));
+            let parsed = ExifData::parse(&bytes).unwrap();
+            assert_eq!(parsed, exif);
+            assert_eq!(parsed.to_bytes().unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn test_accessors() {
+        let mut exif = testing_exif(ByteOrder::LittleEndian);
+        assert_eq!(exif.orientation(), Some(6));
+        assert_eq!(exif.camera().as_deref(), Some("Canon EOS 5D"));
+        assert_eq!(exif.date_time(), Some("2024:01:02 03:04:05"));
+        let (lat, lon) = exif.gps_position().unwrap();
+        assert_eq!(lat, -(33.0 + 52.0 / 60.0));
+        assert_eq!(lon, 151.0 + 12.0 / 60.0 + 36.0 / 3600.0);
+        assert_eq!(exif.get(IfdKind::Exif, 0x9000).unwrap().to_string(), "0232");
+
+        assert!(exif.remove_gps());
+        assert_eq!(exif.gps_position(), None);
+        let parsed = ExifData::parse(&exif.to_bytes().unwrap()).unwrap();
+        assert_eq!(parsed.gps, None);
+        assert_eq!(parsed.entries().count(), 7);
+    }
+
+    #[test]
+    fn test_tags() {
+        assert_eq!(find_tag("orientation"), Some((IfdKind::Primary, 0x0112)));
+        assert_eq!(find_tag("GPSLatitude"), Some((IfdKind::Gps, 0x0002)));
+        assert_eq!(find_tag("Nonsense"), None);
+        assert_eq!(tag_name(IfdKind::Exif, 0x9003), Some("DateTimeOriginal"));
+
+        let mut exif = ExifData::new();
+        assert_eq!(
+            exif.set(IfdKind::Primary, 0x0112, "up"),
+            Err(ExifError::InvalidValue)
+        );
+        assert_eq!(
+            exif.set(IfdKind::Primary, 0xbeef, "1"),
+            Err(ExifError::UnknownTag)
+        );
+        exif.primary.set(0xbeef, Value::SLong(vec![-1]));
+        exif.set(IfdKind::Primary, 0xbeef, "-5, 7").unwrap();
+        assert_eq!(
+            exif.get(IfdKind::Primary, 0xbeef),
+            Some(&Value::SLong(vec![-5, 7]))
+        );
+
+        let rational = parse_type("Rational").unwrap();
+        exif.set_as(IfdKind::Gps, 0x00ff, rational, "1/3").unwrap();
+        assert_eq!(
+            exif.get(IfdKind::Gps, 0x00ff),
+            Some(&Value::Rational(vec![(1, 3)]))
+        );
+        assert_eq!(parse_type("nibble"), Err(ExifError::InvalidValue));
+        assert_eq!(IfdKind::from_str("gps"), Ok(IfdKind::Gps));
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        assert_eq!(
+            ExifData::parse(b"JUNK\0\0\0\x08"),
+            Err(ExifError::InvalidHeader)
+        );
+        assert_eq!(
+            ExifData::parse(b"MM\0*\0\0\0"),
+            Err(ExifError::UnexpectedEof)
+        );
+        assert_eq!(
+            ExifData::parse(b"MM\0*\0\0\0\x08\0\x01"),
+            Err(ExifError::UnexpectedEof)
+        );
+
+        // IFD0 whose next-IFD offset points back at itself.
+        let looping = b"MM\0*\0\0\0\x08\0\0\0\0\0\x08";
+        assert_eq!(ExifData::parse(looping), Err(ExifError::InvalidIfd));
+
+        // An ASCII field claiming more data than there is.
+        let mut bytes = testing_exif(ByteOrder::BigEndian).to_bytes().unwrap();
+        bytes.truncate(40);
+        assert_eq!(ExifData::parse(&bytes), Err(ExifError::UnexpectedEof));
+
+        // An IFD's entry count is a u16.
+        let mut exif = ExifData::new();
+        for tag in 0..=u16::MAX {
+            exif.primary.set(tag, Value::Byte(vec![0]));
+        }
+        assert_eq!(exif.to_bytes(), Err(ExifError::TooLarge));
+    }
+
+    #[test]
+    fn test_check_placement() {
+        let exif = Chunk::new(ChunkType::eXIf, ExifData::new().to_bytes().unwrap());
+        let idat = Chunk::new(ChunkType::IDAT, vec![]);
+        let png = Png::from_chunks(vec![exif, idat]);
+        assert_eq!(check_placement(&png), Ok(()));
+
+        let exif = Chunk::new(ChunkType::eXIf, ExifData::new().to_bytes().unwrap());
+        let idat = Chunk::new(ChunkType::IDAT, vec![]);
+        let png = Png::from_chunks(vec![idat, exif]);
+        assert_eq!(check_placement(&png), Err(ExifError::AfterImageData));
+    }
+}
@@ -0,0 +1,253 @@
+use derive_more::{Display, Error};
+
+use crate::ihdr::Ihdr;
+
+#[derive(PartialEq, Debug, Display, Error)]
+pub enum FilterError {
+    InvalidFilterType,
+    InvalidLength,
+}
+
+/// The per-row filters of PNG filter method 0.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FilterType {
+    None,
+    Sub,
+    Up,
+    Average,
+    Paeth,
+}
+
+impl FilterType {
+    pub const ALL: [FilterType; 5] = [
+        FilterType::None,
+        FilterType::Sub,
+        FilterType::Up,
+        FilterType::Average,
+        FilterType::Paeth,
+    ];
+}
+
+impl TryFrom<u8> for FilterType {
+    type Error = FilterError;
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        FilterType::ALL
+            .get(value as usize)
+            .copied()
+            .ok_or(FilterError::InvalidFilterType)
+    }
+}
+
+/// Width and height of each reduced image stored in `IDAT`: one for plain images, up to seven for Adam7.
+pub fn passes(ihdr: &Ihdr) -> Vec<(u32, u32)> {
+    if !ihdr.interlaced {
+        return vec![(ihdr.width, ihdr.height)];
+    }
+    const ADAM7: [(u32, u32, u32, u32); 7] = [
+        (0, 0, 8, 8),
+        (4, 0, 8, 8),
+        (0, 4, 4, 8),
+        (2, 0, 4, 4),
+        (0, 2, 2, 4),
+        (1, 0, 2, 2),
+        (0, 1, 1, 2),
+    ];
+    let reduce = |size: u32, start: u32, step: u32| size.saturating_sub(start).div_ceil(step);
+    ADAM7
+        .iter()
+        .map(|&(x, y, dx, dy)| (reduce(ihdr.width, x, dx), reduce(ihdr.height, y, dy)))
+        .filter(|&(w, h)| w > 0 && h > 0)
+        .collect()
+}
+
+/// Bytes in one unfiltered row of `width` pixels.
+pub fn row_length(ihdr: &Ihdr, width: u32) -> usize {
+    (width as usize * ihdr.bits_per_pixel()).div_ceil(8)
+}
+
+/// Distance in bytes to the corresponding byte of the previous pixel, at least 1.
+fn filter_distance(ihdr: &Ihdr) -> usize {
+    ihdr.bits_per_pixel().div_ceil(8)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Predicts a byte from its left (`a`), upper (`b`) and upper-left (`c`) neighbours.
+fn predict(filter_type: FilterType, a: u8, b: u8, c: u8) -> u8 {
+    match filter_type {
+        FilterType::None => 0,
+        FilterType::Sub => a,
+        FilterType::Up => b,
+        FilterType::Average => ((a as u16 + b as u16) / 2) as u8,
+        FilterType::Paeth => paeth(a, b, c),
+    }
+}
+
+/// Removes the row filters from inflated `IDAT` data, returning the rows of every pass back to back.
+pub fn unfilter(data: &[u8], ihdr: &Ihdr) -> Result<Vec<u8>, FilterError> {
+    let bpp = filter_distance(ihdr);
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    for (width, height) in passes(ihdr) {
+        let length = row_length(ihdr, width);
+        let mut prev = vec![0; length];
+        for _ in 0..height {
+            let row = data
+                .get(i..i + 1 + length)
+                .ok_or(FilterError::InvalidLength)?;
+            let filter_type = FilterType::try_from(row[0])?;
+            let mut cur = row[1..].to_vec();
+            for x in 0..length {
+                let a = if x >= bpp { cur[x - bpp] } else { 0 };
+                let b = prev[x];
+                let c = if x >= bpp { prev[x - bpp] } else { 0 };
+                cur[x] = cur[x].wrapping_add(predict(filter_type, a, b, c));
+            }
+            out.extend_from_slice(&cur);
+            prev = cur;
+            i += 1 + length;
+        }
+    }
+    if i == data.len() {
+        Ok(out)
+    } else {
+        Err(FilterError::InvalidLength)
+    }
+}
+
+/// Applies `filter_type` to one row, writing the filter byte and filtered bytes to `out`.
+pub fn filter_row(filter_type: FilterType, row: &[u8], prev: &[u8], bpp: usize, out: &mut Vec<u8>) {
+    out.push(filter_type as u8);
+    for x in 0..row.len() {
+        let a = if x >= bpp { row[x - bpp] } else { 0 };
+        let b = prev[x];
+        let c = if x >= bpp { prev[x - bpp] } else { 0 };
+        out.push(row[x].wrapping_sub(predict(filter_type, a, b, c)));
+    }
+}
+
+/// Filters unfiltered rows laid out as [`unfilter`] returns them, using `filter_type` for every row.
+pub fn filter(raw: &[u8], ihdr: &Ihdr, filter_type: FilterType) -> Vec<u8> {
+    filter_adaptive(raw, ihdr, |_, _, _, _| filter_type)
+}
+
+/// Like [`filter`], but `choose` picks the filter type of each row given the row, the previous row,
+/// the distance to the previous pixel and the filtered output so far.
+pub fn filter_adaptive<F>(raw: &[u8], ihdr: &Ihdr, mut choose: F) -> Vec<u8>
+where
+    F: FnMut(&[u8], &[u8], usize, &[u8]) -> FilterType,
+{
+    let bpp = filter_distance(ihdr);
+    let mut out = Vec::with_capacity(raw.len() + raw.len() / 8);
+    let mut i = 0;
+    for (width, height) in passes(ihdr) {
+        let length = row_length(ihdr, width);
+        let mut prev: &[u8] = &vec![0; length];
+        for _ in 0..height {
+            let row = &raw[i..i + length];
+            let filter_type = choose(row, prev, bpp, &out);
+            filter_row(filter_type, row, prev, bpp, &mut out);
+            prev = row;
+            i += length;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ihdr::ColourType;
+
+    fn testing_ihdr(interlaced: bool) -> Ihdr {
+        Ihdr {
+            width: 5,
+            height: 3,
+            bit_depth: 8,
+            colour_type: ColourType::Truecolour,
+            interlaced,
+        }
+    }
+
+    #[test]
+    fn test_passes() {
+        assert_eq!(passes(&testing_ihdr(false)), vec![(5, 3)]);
+        assert_eq!(
+            passes(&testing_ihdr(true)),
+            vec![(1, 1), (1, 1), (1, 1), (3, 1), (2, 2), (5, 1)]
+        );
+    }
+
+    #[test]
+    fn test_filter_round_trip() {
+        for interlaced in [false, true] {
+            let ihdr = testing_ihdr(interlaced);
+            let raw: Vec<u8> = (0..45u32).map(|x| (x * 37 % 251) as u8).collect();
+            for filter_type in FilterType::ALL {
+                let filtered = filter(&raw, &ihdr, filter_type);
+                assert_eq!(unfilter(&filtered, &ihdr), Ok(raw.clone()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_filter_adaptive() {
+        let ihdr = testing_ihdr(false);
+        let raw: Vec<u8> = (0..45).collect();
+        let mut rows = 0;
+        let filtered = filter_adaptive(&raw, &ihdr, |row, prev, bpp, out| {
+            assert_eq!((row.len(), prev.len(), bpp), (15, 15, 3));
+            assert_eq!(out.len(), rows * 16);
+            rows += 1;
+            FilterType::ALL[rows]
+        });
+        assert_eq!(filtered[..1], [1]);
+        assert_eq!(filtered[16..17], [2]);
+        assert_eq!(filtered[32..33], [3]);
+        assert_eq!(unfilter(&filtered, &ihdr), Ok(raw));
+    }
+
+    #[test]
+    fn test_unfilter_fixture() {
+        let bytes = std::fs::read("tests/fixtures/dice.png").unwrap();
+        let png = crate::png::Png::try_from(bytes.as_ref()).unwrap();
+        let ihdr = png.ihdr().unwrap();
+        let data = crate::zlib::inflate(&png.image_data(), &Default::default()).unwrap();
+        let raw = unfilter(&data, &ihdr).unwrap();
+        assert_eq!(
+            raw.len(),
+            ihdr.height as usize * row_length(&ihdr, ihdr.width)
+        );
+        let filtered = filter(&raw, &ihdr, FilterType::Paeth);
+        assert_eq!(unfilter(&filtered, &ihdr), Ok(raw));
+    }
+
+    #[test]
+    fn test_unfilter_errors() {
+        let ihdr = testing_ihdr(false);
+        let mut filtered = filter(&[0; 45], &ihdr, FilterType::None);
+        assert_eq!(
+            unfilter(&filtered[1..], &ihdr),
+            Err(FilterError::InvalidLength)
+        );
+        filtered[0] = 5;
+        assert_eq!(
+            unfilter(&filtered, &ihdr),
+            Err(FilterError::InvalidFilterType)
+        );
+    }
+}
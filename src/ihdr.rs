@@ -0,0 +1,186 @@
+use derive_more::{Display, Error};
+
+#[derive(PartialEq, Debug, Display, Error)]
+pub enum IhdrError {
+    InvalidLength,
+    InvalidDimensions,
+    InvalidColourType,
+    InvalidBitDepth,
+    InvalidCompressionMethod,
+    InvalidFilterMethod,
+    InvalidInterlaceMethod,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColourType {
+    Greyscale,
+    Truecolour,
+    Indexed,
+    GreyscaleAlpha,
+    TruecolourAlpha,
+}
+
+impl ColourType {
+    pub fn allowed_bit_depths(&self) -> &'static [u8] {
+        match self {
+            ColourType::Greyscale => &[1, 2, 4, 8, 16],
+            ColourType::Truecolour => &[8, 16],
+            ColourType::Indexed => &[1, 2, 4, 8],
+            ColourType::GreyscaleAlpha => &[8, 16],
+            ColourType::TruecolourAlpha => &[8, 16],
+        }
+    }
+    pub fn channels(&self) -> usize {
+        match self {
+            ColourType::Greyscale | ColourType::Indexed => 1,
+            ColourType::GreyscaleAlpha => 2,
+            ColourType::Truecolour => 3,
+            ColourType::TruecolourAlpha => 4,
+        }
+    }
+}
+
+impl TryFrom<u8> for ColourType {
+    type Error = IhdrError;
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ColourType::Greyscale),
+            2 => Ok(ColourType::Truecolour),
+            3 => Ok(ColourType::Indexed),
+            4 => Ok(ColourType::GreyscaleAlpha),
+            6 => Ok(ColourType::TruecolourAlpha),
+            _ => Err(IhdrError::InvalidColourType),
+        }
+    }
+}
+
+impl From<ColourType> for u8 {
+    fn from(value: ColourType) -> Self {
+        match value {
+            ColourType::Greyscale => 0,
+            ColourType::Truecolour => 2,
+            ColourType::Indexed => 3,
+            ColourType::GreyscaleAlpha => 4,
+            ColourType::TruecolourAlpha => 6,
+        }
+    }
+}
+
+/// The decoded contents of an `IHDR` chunk.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Ihdr {
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: u8,
+    pub colour_type: ColourType,
+    pub interlaced: bool,
+}
+
+impl Ihdr {
+    pub const LENGTH: usize = 13;
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        self.width
+            .to_be_bytes()
+            .iter()
+            .chain(self.height.to_be_bytes().iter())
+            .copied()
+            .chain([
+                self.bit_depth,
+                self.colour_type.into(),
+                0,
+                0,
+                self.interlaced as u8,
+            ])
+            .collect()
+    }
+    /// Number of bits used by a single pixel.
+    pub fn bits_per_pixel(&self) -> usize {
+        self.colour_type.channels() * self.bit_depth as usize
+    }
+}
+
+impl TryFrom<&[u8]> for Ihdr {
+    type Error = IhdrError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        if value.len() != Ihdr::LENGTH {
+            return Err(IhdrError::InvalidLength);
+        }
+        let width = u32::from_be_bytes(value[0..4].try_into().unwrap());
+        let height = u32::from_be_bytes(value[4..8].try_into().unwrap());
+        if width == 0 || height == 0 || width > 0x7fff_ffff || height > 0x7fff_ffff {
+            return Err(IhdrError::InvalidDimensions);
+        }
+
+        let colour_type = ColourType::try_from(value[9])?;
+        let bit_depth = value[8];
+        if !colour_type.allowed_bit_depths().contains(&bit_depth) {
+            return Err(IhdrError::InvalidBitDepth);
+        }
+        if value[10] != 0 {
+            return Err(IhdrError::InvalidCompressionMethod);
+        }
+        if value[11] != 0 {
+            return Err(IhdrError::InvalidFilterMethod);
+        }
+        let interlaced = match value[12] {
+            0 => false,
+            1 => true,
+            _ => return Err(IhdrError::InvalidInterlaceMethod),
+        };
+
+        Ok(Ihdr {
+            width,
+            height,
+            bit_depth,
+            colour_type,
+            interlaced,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rustfmt::skip]
+    const DICE_IHDR: [u8; 13] = [
+        0, 0, 0, 50,    // width
+        0, 0, 0, 50,    // height
+        8, 6,           // bit depth, colour type
+        0, 0, 0,        // compression, filter, interlace
+    ];
+
+    #[test]
+    fn test_ihdr_from_bytes() {
+        let ihdr = Ihdr::try_from(&DICE_IHDR[..]).unwrap();
+        assert_eq!(ihdr.width, 50);
+        assert_eq!(ihdr.height, 50);
+        assert_eq!(ihdr.bit_depth, 8);
+        assert_eq!(ihdr.colour_type, ColourType::TruecolourAlpha);
+        assert!(!ihdr.interlaced);
+        assert_eq!(ihdr.bits_per_pixel(), 32);
+        assert_eq!(ihdr.as_bytes(), DICE_IHDR);
+    }
+
+    #[test]
+    fn test_invalid_ihdr() {
+        let with = |i: usize, v: u8| {
+            let mut bytes = DICE_IHDR;
+            bytes[i] = v;
+            Ihdr::try_from(&bytes[..])
+        };
+
+        assert_eq!(with(3, 0), Err(IhdrError::InvalidDimensions));
+        assert_eq!(with(9, 1), Err(IhdrError::InvalidColourType));
+        assert_eq!(with(8, 4), Err(IhdrError::InvalidBitDepth));
+        assert_eq!(with(10, 1), Err(IhdrError::InvalidCompressionMethod));
+        assert_eq!(with(11, 1), Err(IhdrError::InvalidFilterMethod));
+        assert_eq!(with(12, 2), Err(IhdrError::InvalidInterlaceMethod));
+        assert_eq!(
+            Ihdr::try_from(&DICE_IHDR[..12]),
+            Err(IhdrError::InvalidLength)
+        );
+    }
+}
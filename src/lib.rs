@@ -0,0 +1,31 @@
+pub mod ancillary;
+pub mod apng;
+pub mod args;
+pub mod batch;
+pub mod cgbi;
+pub mod chunk;
+pub mod chunk_ref;
+pub mod chunk_type;
+pub mod codec;
+pub mod commands;
+pub mod copy;
+pub mod diff;
+pub mod exif;
+pub mod filter;
+pub mod ihdr;
+pub mod limits;
+pub mod loader;
+pub mod mng;
+pub mod optimize;
+pub mod palette;
+pub mod png;
+pub mod png_ref;
+pub mod reorder;
+pub mod signature;
+#[cfg(test)]
+mod testing;
+pub mod xmp;
+pub mod zlib;
+
+pub type Error = Box<dyn std::error::Error>;
+pub type Result<T> = std::result::Result<T, Error>;
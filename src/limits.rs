@@ -0,0 +1,122 @@
+use crate::png::PngError;
+
+/// Upper bounds applied while parsing, so untrusted input cannot make us allocate or work unboundedly.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Limits {
+    pub max_file_size: usize,
+    pub max_chunk_length: usize,
+    pub max_chunk_count: usize,
+    /// Applies to inflated zTXt, iTXt, iCCP and IDAT data.
+    pub max_decompressed_size: usize,
+    pub max_width: u32,
+    pub max_height: u32,
+}
+
+impl Limits {
+    /// The largest value the PNG specification allows for a length or dimension.
+    pub const SPEC_MAX: u32 = 0x7fff_ffff;
+
+    pub fn unlimited() -> Limits {
+        Limits {
+            max_file_size: usize::MAX,
+            max_chunk_length: Limits::SPEC_MAX as usize,
+            max_chunk_count: usize::MAX,
+            max_decompressed_size: usize::MAX,
+            max_width: Limits::SPEC_MAX,
+            max_height: Limits::SPEC_MAX,
+        }
+    }
+    /// Conservative limits suitable for files uploaded by users.
+    pub fn untrusted() -> Limits {
+        Limits {
+            max_file_size: 64 << 20,
+            max_chunk_length: 16 << 20,
+            max_chunk_count: 10_000,
+            max_decompressed_size: 256 << 20,
+            max_width: 16_384,
+            max_height: 16_384,
+        }
+    }
+    pub fn check_file_size(&self, size: usize) -> Result<(), PngError> {
+        if size > self.max_file_size {
+            Err(PngError::FileTooLarge)
+        } else {
+            Ok(())
+        }
+    }
+    pub fn check_chunk_length(&self, length: usize) -> Result<(), PngError> {
+        if length > self.max_chunk_length {
+            Err(PngError::ChunkTooLarge)
+        } else {
+            Ok(())
+        }
+    }
+    pub fn check_chunk_count(&self, count: usize) -> Result<(), PngError> {
+        if count > self.max_chunk_count {
+            Err(PngError::TooManyChunks)
+        } else {
+            Ok(())
+        }
+    }
+    pub fn check_decompressed_size(&self, size: usize) -> Result<(), PngError> {
+        if size > self.max_decompressed_size {
+            Err(PngError::DecompressedTooLarge)
+        } else {
+            Ok(())
+        }
+    }
+    pub fn check_dimensions(&self, width: u32, height: u32) -> Result<(), PngError> {
+        if width > self.max_width || height > self.max_height {
+            Err(PngError::ImageTooLarge)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits::unlimited()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_is_unlimited() {
+        let limits = Limits::default();
+        assert!(limits.check_file_size(usize::MAX).is_ok());
+        assert!(limits.check_chunk_count(usize::MAX).is_ok());
+        assert!(limits
+            .check_dimensions(Limits::SPEC_MAX, Limits::SPEC_MAX)
+            .is_ok());
+        assert_eq!(
+            limits.check_chunk_length(Limits::SPEC_MAX as usize + 1),
+            Err(PngError::ChunkTooLarge)
+        );
+    }
+
+    #[test]
+    fn test_untrusted_limits() {
+        let limits = Limits::untrusted();
+        assert_eq!(
+            limits.check_file_size(65 << 20),
+            Err(PngError::FileTooLarge)
+        );
+        assert_eq!(
+            limits.check_chunk_count(10_001),
+            Err(PngError::TooManyChunks)
+        );
+        assert_eq!(
+            limits.check_decompressed_size(257 << 20),
+            Err(PngError::DecompressedTooLarge)
+        );
+        assert_eq!(
+            limits.check_dimensions(16_384, 16_385),
+            Err(PngError::ImageTooLarge)
+        );
+        assert!(limits.check_dimensions(16_384, 16_384).is_ok());
+    }
+}
@@ -0,0 +1,82 @@
+use std::fs::File;
+use std::ops::Deref;
+use std::path::Path;
+
+/// The contents of a file, either read into memory or memory-mapped with the `mmap` feature.
+pub enum FileBytes {
+    Owned(Vec<u8>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl FileBytes {
+    pub fn open(path: &Path) -> std::io::Result<FileBytes> {
+        let file = File::open(path)?;
+        FileBytes::from_file(file)
+    }
+    #[cfg(feature = "mmap")]
+    fn from_file(file: File) -> std::io::Result<FileBytes> {
+        // Mapping an empty file fails on some platforms.
+        if file.metadata()?.len() == 0 {
+            return Ok(FileBytes::Owned(vec![]));
+        }
+        // SAFETY: the mapping is read-only; like every mmap user we assume the file is not
+        // truncated by another process while we hold it.
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(FileBytes::Mapped(map))
+    }
+    #[cfg(not(feature = "mmap"))]
+    fn from_file(mut file: File) -> std::io::Result<FileBytes> {
+        use std::io::Read;
+
+        let mut contents = vec![];
+        file.read_to_end(&mut contents)?;
+        Ok(FileBytes::Owned(contents))
+    }
+    pub fn is_mapped(&self) -> bool {
+        !matches!(self, FileBytes::Owned(_))
+    }
+}
+
+impl Deref for FileBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            FileBytes::Owned(x) => x.as_ref(),
+            #[cfg(feature = "mmap")]
+            FileBytes::Mapped(x) => x.as_ref(),
+        }
+    }
+}
+
+impl AsRef<[u8]> for FileBytes {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn test_open_matches_read() {
+        let path = fixture("dice.png");
+        let bytes = FileBytes::open(path.as_path()).unwrap();
+        assert_eq!(&bytes[..], std::fs::read(path).unwrap().as_slice());
+        assert_eq!(bytes.is_mapped(), cfg!(feature = "mmap"));
+    }
+
+    #[test]
+    fn test_open_missing_file() {
+        assert!(FileBytes::open(fixture("missing.png").as_path()).is_err());
+    }
+}
@@ -1,15 +1,3 @@
-use clap::Parser;
-
-mod args;
-mod chunk;
-mod chunk_type;
-mod commands;
-mod png;
-
-pub type Error = Box<dyn std::error::Error>;
-pub type Result<T> = std::result::Result<T, Error>;
-
-fn main() -> Result<()> {
-    let result = commands::cli();
-    result
+fn main() -> pngme::Result<()> {
+    pngme::commands::cli()
 }
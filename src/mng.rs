@@ -0,0 +1,171 @@
+use std::fmt::Display;
+
+use crate::chunk_type::ChunkType;
+use crate::codec::{ChunkData, CodecError};
+
+fn u32_at(data: &[u8], i: usize) -> u32 {
+    u32::from_be_bytes(data[i..i + 4].try_into().unwrap())
+}
+
+/// The decoded contents of an MNG `MHDR` chunk.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Mhdr {
+    pub frame_width: u32,
+    pub frame_height: u32,
+    pub ticks_per_second: u32,
+    pub nominal_layer_count: u32,
+    pub nominal_frame_count: u32,
+    pub nominal_play_time: u32,
+    pub simplicity_profile: u32,
+}
+
+impl ChunkData for Mhdr {
+    const CHUNK_TYPE: ChunkType = ChunkType::MHDR;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        if data.len() != 28 {
+            return Err(CodecError::InvalidLength);
+        }
+        Ok(Mhdr {
+            frame_width: u32_at(data, 0),
+            frame_height: u32_at(data, 4),
+            ticks_per_second: u32_at(data, 8),
+            nominal_layer_count: u32_at(data, 12),
+            nominal_frame_count: u32_at(data, 16),
+            nominal_play_time: u32_at(data, 20),
+            simplicity_profile: u32_at(data, 24),
+        })
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        Ok([
+            self.frame_width,
+            self.frame_height,
+            self.ticks_per_second,
+            self.nominal_layer_count,
+            self.nominal_frame_count,
+            self.nominal_play_time,
+            self.simplicity_profile,
+        ]
+        .iter()
+        .flat_map(|x| x.to_be_bytes())
+        .collect())
+    }
+}
+
+impl Display for Mhdr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}x{}, {} ticks per second, {} frames",
+            self.frame_width, self.frame_height, self.ticks_per_second, self.nominal_frame_count
+        )
+    }
+}
+
+/// The decoded contents of a JNG `JHDR` chunk.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Jhdr {
+    pub width: u32,
+    pub height: u32,
+    pub colour_type: u8,
+    pub sample_depth: u8,
+    pub compression_method: u8,
+    pub interlace_method: u8,
+    pub alpha_sample_depth: u8,
+    pub alpha_compression_method: u8,
+    pub alpha_filter_method: u8,
+    pub alpha_interlace_method: u8,
+}
+
+impl ChunkData for Jhdr {
+    const CHUNK_TYPE: ChunkType = ChunkType::JHDR;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        if data.len() != 16 {
+            return Err(CodecError::InvalidLength);
+        }
+        let jhdr = Jhdr {
+            width: u32_at(data, 0),
+            height: u32_at(data, 4),
+            colour_type: data[8],
+            sample_depth: data[9],
+            compression_method: data[10],
+            interlace_method: data[11],
+            alpha_sample_depth: data[12],
+            alpha_compression_method: data[13],
+            alpha_filter_method: data[14],
+            alpha_interlace_method: data[15],
+        };
+        let valid = jhdr.width > 0
+            && jhdr.height > 0
+            && [8, 10, 12, 14].contains(&jhdr.colour_type)
+            && [8, 12, 20].contains(&jhdr.sample_depth);
+        if valid {
+            Ok(jhdr)
+        } else {
+            Err(CodecError::InvalidValue)
+        }
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = self.width.to_be_bytes().to_vec();
+        bytes.extend(self.height.to_be_bytes());
+        bytes.extend([
+            self.colour_type,
+            self.sample_depth,
+            self.compression_method,
+            self.interlace_method,
+            self.alpha_sample_depth,
+            self.alpha_compression_method,
+            self.alpha_filter_method,
+            self.alpha_interlace_method,
+        ]);
+        Ok(bytes)
+    }
+}
+
+impl Display for Jhdr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let colour = match self.colour_type {
+            8 => "greyscale",
+            10 => "colour",
+            12 => "greyscale with alpha",
+            _ => "colour with alpha",
+        };
+        write!(
+            f,
+            "{}x{}, {}, {}-bit samples",
+            self.width, self.height, colour, self.sample_depth
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mhdr() {
+        let mhdr = Mhdr {
+            frame_width: 64,
+            frame_height: 32,
+            ticks_per_second: 10,
+            nominal_layer_count: 0,
+            nominal_frame_count: 5,
+            nominal_play_time: 0,
+            simplicity_profile: 1,
+        };
+        assert_eq!(Mhdr::from_bytes(&mhdr.to_bytes().unwrap()), Ok(mhdr));
+        assert_eq!(mhdr.to_string(), "64x32, 10 ticks per second, 5 frames");
+        assert_eq!(Mhdr::from_bytes(&[0; 27]), Err(CodecError::InvalidLength));
+    }
+
+    #[test]
+    fn test_jhdr() {
+        let mut bytes = vec![0, 0, 0, 3, 0, 0, 0, 2, 10, 8, 8, 0, 0, 0, 0, 0];
+        let jhdr = Jhdr::from_bytes(&bytes).unwrap();
+        assert_eq!(jhdr.to_bytes().unwrap(), bytes);
+        assert_eq!(jhdr.to_string(), "3x2, colour, 8-bit samples");
+        bytes[8] = 2;
+        assert_eq!(Jhdr::from_bytes(&bytes), Err(CodecError::InvalidValue));
+    }
+}
@@ -0,0 +1,620 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use derive_more::{Display, Error};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::codec::{ChunkData, CodecError};
+use crate::filter::{
+    filter, filter_adaptive, filter_row, passes, unfilter, FilterError, FilterType,
+};
+use crate::ihdr::{ColourType, Ihdr};
+use crate::limits::Limits;
+use crate::palette::{Palette, Transparency};
+use crate::png::{Png, PngError};
+use crate::signature::Signature;
+use crate::zlib;
+
+#[derive(PartialEq, Debug, Display, Error)]
+pub enum OptimizeError {
+    /// Only standard PNG files are optimised; CgBI, MNG and JNG streams are left alone.
+    UnsupportedFormat,
+    InvalidStrategy,
+    /// At least one strategy and one compression level are needed.
+    NothingToTry,
+    InvalidImageData(FilterError),
+    InvalidChunk(CodecError),
+    InvalidPng(PngError),
+}
+
+impl From<FilterError> for OptimizeError {
+    fn from(e: FilterError) -> Self {
+        OptimizeError::InvalidImageData(e)
+    }
+}
+
+impl From<CodecError> for OptimizeError {
+    fn from(e: CodecError) -> Self {
+        OptimizeError::InvalidChunk(e)
+    }
+}
+
+impl From<PngError> for OptimizeError {
+    fn from(e: PngError) -> Self {
+        OptimizeError::InvalidPng(e)
+    }
+}
+
+/// How the filter type of each row is chosen.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Strategy {
+    /// The same filter type for every row.
+    Fixed(FilterType),
+    /// The filter type giving the smallest sum of absolute signed bytes, as libpng does.
+    MinimumSum,
+    /// The filter type giving the lowest Shannon entropy.
+    Entropy,
+    /// The filter type that compresses smallest after the preceding rows.
+    BruteForce,
+}
+
+impl Strategy {
+    /// Every strategy except [`Strategy::BruteForce`], which compresses each row five times.
+    pub const DEFAULT: [Strategy; 7] = [
+        Strategy::Fixed(FilterType::None),
+        Strategy::Fixed(FilterType::Sub),
+        Strategy::Fixed(FilterType::Up),
+        Strategy::Fixed(FilterType::Average),
+        Strategy::Fixed(FilterType::Paeth),
+        Strategy::MinimumSum,
+        Strategy::Entropy,
+    ];
+}
+
+impl FromStr for Strategy {
+    type Err = OptimizeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Strategy::Fixed(FilterType::None)),
+            "sub" => Ok(Strategy::Fixed(FilterType::Sub)),
+            "up" => Ok(Strategy::Fixed(FilterType::Up)),
+            "average" => Ok(Strategy::Fixed(FilterType::Average)),
+            "paeth" => Ok(Strategy::Fixed(FilterType::Paeth)),
+            "minsum" => Ok(Strategy::MinimumSum),
+            "entropy" => Ok(Strategy::Entropy),
+            "brute" => Ok(Strategy::BruteForce),
+            _ => Err(OptimizeError::InvalidStrategy),
+        }
+    }
+}
+
+impl Display for Strategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Strategy::Fixed(FilterType::None) => write!(f, "none"),
+            Strategy::Fixed(FilterType::Sub) => write!(f, "sub"),
+            Strategy::Fixed(FilterType::Up) => write!(f, "up"),
+            Strategy::Fixed(FilterType::Average) => write!(f, "average"),
+            Strategy::Fixed(FilterType::Paeth) => write!(f, "paeth"),
+            Strategy::MinimumSum => write!(f, "minsum"),
+            Strategy::Entropy => write!(f, "entropy"),
+            Strategy::BruteForce => write!(f, "brute"),
+        }
+    }
+}
+
+/// The combinations [`optimize`] tries; the smallest result wins.
+#[derive(Clone, PartialEq, Debug)]
+pub struct OptimizeOptions {
+    pub strategies: Vec<Strategy>,
+    pub levels: Vec<u8>,
+    /// Also try smaller bit depths and colour types that keep every pixel the same.
+    pub reduce: bool,
+}
+
+impl Default for OptimizeOptions {
+    fn default() -> Self {
+        OptimizeOptions {
+            strategies: Strategy::DEFAULT.to_vec(),
+            levels: vec![zlib::DEFAULT_LEVEL, 9],
+            reduce: false,
+        }
+    }
+}
+
+/// The outcome of [`optimize`]. Sizes count the image data plus any `PLTE` and `tRNS` it adds.
+#[derive(PartialEq, Debug)]
+pub struct Report {
+    pub old_size: usize,
+    pub new_size: usize,
+    pub strategy: Strategy,
+    pub level: u8,
+    /// The header before and after a bit depth or colour type reduction.
+    pub reduction: Option<(Ihdr, Ihdr)>,
+}
+
+impl Report {
+    /// Whether the file was rewritten; results that are not smaller are discarded.
+    pub fn is_smaller(&self) -> bool {
+        self.new_size < self.old_size
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Image data: {} -> {} bytes",
+            self.old_size, self.new_size
+        )?;
+        writeln!(f, "Filter: {}, level {}", self.strategy, self.level)?;
+        if let Some((old, new)) = self.reduction {
+            writeln!(
+                f,
+                "Reduced: {:?} {}-bit -> {:?} {}-bit",
+                old.colour_type, old.bit_depth, new.colour_type, new.bit_depth
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Chunks whose meaning depends on the pixel format, or frames that share it; any of them
+/// prevents reduction so that every ancillary chunk can be kept unchanged.
+const BLOCKS_REDUCTION: [ChunkType; 6] = [
+    ChunkType::PLTE,
+    ChunkType::tRNS,
+    ChunkType::bKGD,
+    ChunkType::sBIT,
+    ChunkType::hIST,
+    ChunkType::acTL,
+];
+
+/// Rows of previously filtered output that [`Strategy::BruteForce`] compresses with each candidate.
+const BRUTE_FORCE_CONTEXT: usize = 4;
+
+/// Unfiltered pixels in one pixel format, with the palette chunks that format needs.
+#[derive(PartialEq, Debug)]
+struct Image {
+    ihdr: Ihdr,
+    raw: Vec<u8>,
+    palette: Option<Palette>,
+    transparency: Option<Transparency>,
+}
+
+impl Image {
+    /// Bytes the new `PLTE` and `tRNS` chunks add to the file.
+    fn extra_size(&self) -> usize {
+        let palette = self
+            .palette
+            .as_ref()
+            .map_or(0, |x| 12 + 3 * x.entries.len());
+        let transparency = self
+            .transparency
+            .as_ref()
+            .map_or(0, |x| 12 + x.to_bytes().len());
+        palette + transparency
+    }
+}
+
+/// Filters `row` with every filter type and returns the one `cost` rates lowest.
+fn best_filter<C>(row: &[u8], prev: &[u8], bpp: usize, mut cost: C) -> FilterType
+where
+    C: FnMut(&[u8]) -> u64,
+{
+    let mut candidate = Vec::with_capacity(row.len() + 1);
+    FilterType::ALL
+        .into_iter()
+        .min_by_key(|&filter_type| {
+            candidate.clear();
+            filter_row(filter_type, row, prev, bpp, &mut candidate);
+            cost(&candidate)
+        })
+        .unwrap()
+}
+
+fn absolute_sum(filtered: &[u8]) -> u64 {
+    filtered[1..]
+        .iter()
+        .map(|&x| (x as i8).unsigned_abs() as u64)
+        .sum()
+}
+
+/// Shannon entropy of the filtered bytes in bits, in fixed point so candidates compare as integers.
+fn entropy(filtered: &[u8]) -> u64 {
+    let mut counts = [0u32; 256];
+    for &x in &filtered[1..] {
+        counts[x as usize] += 1;
+    }
+    let total = (filtered.len() - 1) as f64;
+    let bits: f64 = counts
+        .iter()
+        .filter(|&&x| x > 0)
+        .map(|&x| -(x as f64) * (x as f64 / total).log2())
+        .sum();
+    (bits * 1024.0) as u64
+}
+
+fn compress(image: &Image, strategy: Strategy, level: u8) -> Vec<u8> {
+    let (raw, ihdr) = (&image.raw, &image.ihdr);
+    let filtered = match strategy {
+        Strategy::Fixed(filter_type) => filter(raw, ihdr, filter_type),
+        Strategy::MinimumSum => filter_adaptive(raw, ihdr, |row, prev, bpp, _| {
+            best_filter(row, prev, bpp, absolute_sum)
+        }),
+        Strategy::Entropy => filter_adaptive(raw, ihdr, |row, prev, bpp, _| {
+            best_filter(row, prev, bpp, entropy)
+        }),
+        Strategy::BruteForce => filter_adaptive(raw, ihdr, |row, prev, bpp, out| {
+            let start = out
+                .len()
+                .saturating_sub(BRUTE_FORCE_CONTEXT * (row.len() + 1));
+            best_filter(row, prev, bpp, |candidate| {
+                zlib::deflate_with_level(&[&out[start..], candidate].concat(), level).len() as u64
+            })
+        }),
+    };
+    zlib::deflate_with_level(&filtered, level)
+}
+
+/// Packs samples of `bit_depth` bits into bytes, most significant first, padding the last byte.
+fn pack(samples: &[u8], bit_depth: u8, out: &mut Vec<u8>) {
+    let per_byte = 8 / bit_depth as usize;
+    for group in samples.chunks(per_byte) {
+        let mut byte = 0;
+        for (i, sample) in group.iter().enumerate() {
+            byte |= sample << (8 - bit_depth as usize * (i + 1));
+        }
+        out.push(byte);
+    }
+}
+
+/// Indexes 8-bit truecolour pixels into a palette, if there are at most 256 distinct colours.
+fn to_palette(ihdr: &Ihdr, samples: &[u16]) -> Option<Image> {
+    let channels = ihdr.colour_type.channels();
+    let rgba = |x: &[u16]| [x[0], x[1], x[2], x.get(3).copied().unwrap_or(255)].map(|x| x as u8);
+
+    let mut index: HashMap<[u8; 4], u8> = HashMap::new();
+    let mut colours: Vec<[u8; 4]> = vec![];
+    for pixel in samples.chunks_exact(channels) {
+        let colour = rgba(pixel);
+        if let Entry::Vacant(entry) = index.entry(colour) {
+            if colours.len() == Palette::MAX_ENTRIES {
+                return None;
+            }
+            entry.insert(colours.len() as u8);
+            colours.push(colour);
+        }
+    }
+    // Translucent entries first keeps tRNS as short as possible.
+    colours.sort_by_key(|x| x[3] == 255);
+    for (i, colour) in colours.iter().enumerate() {
+        index.insert(*colour, i as u8);
+    }
+
+    let bit_depth = [1, 2, 4, 8]
+        .into_iter()
+        .find(|&x| colours.len() <= 1 << x)
+        .unwrap();
+    let indexed = Ihdr {
+        bit_depth,
+        colour_type: ColourType::Indexed,
+        ..*ihdr
+    };
+    let indices: Vec<u8> = samples
+        .chunks_exact(channels)
+        .map(|x| index[&rgba(x)])
+        .collect();
+    let mut raw = vec![];
+    let mut i = 0;
+    for (width, height) in passes(&indexed) {
+        for _ in 0..height {
+            pack(&indices[i..i + width as usize], bit_depth, &mut raw);
+            i += width as usize;
+        }
+    }
+
+    let alpha: Vec<u8> = colours.iter().map(|x| x[3]).filter(|&x| x < 255).collect();
+    Some(Image {
+        ihdr: indexed,
+        raw,
+        palette: Some(Palette {
+            entries: colours.iter().map(|x| [x[0], x[1], x[2]]).collect(),
+        }),
+        transparency: (!alpha.is_empty()).then_some(Transparency::Alpha(alpha)),
+    })
+}
+
+/// Smaller pixel formats that hold exactly the same pixels as `image`: 16 to 8 bits, dropping an
+/// opaque alpha channel, truecolour to greyscale, and up to 256 colours to a palette.
+fn reductions(png: &Png, image: &Image) -> Vec<Image> {
+    let ihdr = image.ihdr;
+    let blocked = png
+        .chunks()
+        .iter()
+        .any(|x| BLOCKS_REDUCTION.contains(x.chunk_type()));
+    if blocked || ihdr.bit_depth < 8 || ihdr.colour_type == ColourType::Indexed {
+        return vec![];
+    }
+
+    let mut samples: Vec<u16> = match ihdr.bit_depth {
+        16 => image
+            .raw
+            .chunks_exact(2)
+            .map(|x| u16::from_be_bytes([x[0], x[1]]))
+            .collect(),
+        _ => image.raw.iter().map(|&x| x as u16).collect(),
+    };
+    let mut reduced = ihdr;
+    if ihdr.bit_depth == 16 && samples.iter().all(|x| x >> 8 == x & 0xff) {
+        samples.iter_mut().for_each(|x| *x &= 0xff);
+        reduced.bit_depth = 8;
+    }
+
+    let opaque = (1u32 << reduced.bit_depth) as u16 - 1;
+    let channels = reduced.colour_type.channels();
+    let has_alpha = matches!(
+        reduced.colour_type,
+        ColourType::GreyscaleAlpha | ColourType::TruecolourAlpha
+    );
+    if has_alpha
+        && samples
+            .chunks_exact(channels)
+            .all(|x| x[channels - 1] == opaque)
+    {
+        samples = samples
+            .chunks_exact(channels)
+            .flat_map(|x| x[..channels - 1].iter().copied())
+            .collect();
+        reduced.colour_type = match reduced.colour_type {
+            ColourType::GreyscaleAlpha => ColourType::Greyscale,
+            _ => ColourType::Truecolour,
+        };
+    }
+
+    let mut images = vec![];
+    let channels = reduced.colour_type.channels();
+    let truecolour = channels >= 3;
+    if truecolour && reduced.bit_depth == 8 {
+        images.extend(to_palette(&reduced, &samples));
+    }
+    // An RGB ICC profile does not apply to greyscale images.
+    let grey = truecolour
+        && samples
+            .chunks_exact(channels)
+            .all(|x| x[0] == x[1] && x[1] == x[2]);
+    if grey && png.chunk_by_type("iCCP").is_none() {
+        samples = samples
+            .chunks_exact(channels)
+            .flat_map(|x| x[2..].iter().copied())
+            .collect();
+        reduced.colour_type = match reduced.colour_type {
+            ColourType::TruecolourAlpha => ColourType::GreyscaleAlpha,
+            _ => ColourType::Greyscale,
+        };
+    }
+
+    if reduced != ihdr {
+        let raw = match reduced.bit_depth {
+            16 => samples.iter().flat_map(|x| x.to_be_bytes()).collect(),
+            _ => samples.iter().map(|&x| x as u8).collect(),
+        };
+        images.push(Image {
+            ihdr: reduced,
+            raw,
+            palette: None,
+            transparency: None,
+        });
+    }
+    images
+}
+
+/// Refilters and recompresses the image data with every combination in `options`, keeping the
+/// smallest result if it beats the current image data. Ancillary chunks are left untouched.
+pub fn optimize(
+    png: &mut Png,
+    options: &OptimizeOptions,
+    limits: &Limits,
+) -> Result<Report, OptimizeError> {
+    if png.signature() != Signature::Png || png.is_cgbi() {
+        return Err(OptimizeError::UnsupportedFormat);
+    }
+    let ihdr = png.ihdr()?;
+    let image_data = png.image_data();
+    let original = Image {
+        ihdr,
+        raw: unfilter(&zlib::inflate(&image_data, limits)?, &ihdr)?,
+        palette: None,
+        transparency: None,
+    };
+    let mut images = vec![];
+    if options.reduce {
+        images = reductions(png, &original);
+    }
+    images.insert(0, original);
+
+    let mut best: Option<(Report, Vec<u8>, &Image)> = None;
+    for image in images.iter() {
+        for &strategy in options.strategies.iter() {
+            for &level in options.levels.iter() {
+                let data = compress(image, strategy, level);
+                let size = data.len() + image.extra_size();
+                if best.as_ref().is_none_or(|x| size < x.0.new_size) {
+                    let report = Report {
+                        old_size: image_data.len(),
+                        new_size: size,
+                        strategy,
+                        level,
+                        reduction: (image.ihdr != ihdr).then_some((ihdr, image.ihdr)),
+                    };
+                    best = Some((report, data, image));
+                }
+            }
+        }
+    }
+    let (report, data, image) = best.ok_or(OptimizeError::NothingToTry)?;
+
+    if report.is_smaller() {
+        png.chunks_mut()[0] = Chunk::new(ChunkType::IHDR, image.ihdr.as_bytes());
+        if let Some(palette) = &image.palette {
+            png.put_chunk_before(palette.to_chunk()?, &[ChunkType::IDAT]);
+        }
+        if let Some(transparency) = &image.transparency {
+            let chunk = Chunk::new(ChunkType::tRNS, transparency.to_bytes());
+            png.put_chunk_before(chunk, &[ChunkType::IDAT]);
+        }
+        png.set_image_data(vec![data]);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::testing_png;
+
+    fn pixels(png: &Png) -> Vec<u8> {
+        let filtered = zlib::inflate(&png.image_data(), &Limits::default()).unwrap();
+        unfilter(&filtered, &png.ihdr().unwrap()).unwrap()
+    }
+
+    /// A 16-bit RGBA image with opaque alpha and three colours, one of them grey.
+    fn testing_image(size: u32) -> Png {
+        let ihdr = Ihdr {
+            width: size,
+            height: size,
+            bit_depth: 16,
+            colour_type: ColourType::TruecolourAlpha,
+            interlaced: true,
+        };
+        let colours = [[0x12, 0x34, 0x56], [0x80, 0x80, 0x80], [0xff, 0, 0]];
+        let raw: Vec<u8> = (0..size * size)
+            .flat_map(|i| {
+                let [r, g, b] = colours[(i % 7 % 3) as usize];
+                [r, r, g, g, b, b, 0xff, 0xff]
+            })
+            .collect();
+        let data = zlib::deflate(&filter(&raw, &ihdr, FilterType::None));
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, ihdr.as_bytes()),
+            Chunk::new(ChunkType::gAMA, vec![0, 0, 0xb1, 0x8f]),
+            Chunk::new(ChunkType::IDAT, data),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ])
+    }
+
+    #[test]
+    fn test_strategy_from_str() {
+        for strategy in Strategy::DEFAULT.into_iter().chain([Strategy::BruteForce]) {
+            assert_eq!(Strategy::from_str(&strategy.to_string()), Ok(strategy));
+        }
+        assert_eq!(
+            Strategy::from_str("fastest"),
+            Err(OptimizeError::InvalidStrategy)
+        );
+    }
+
+    #[test]
+    fn test_optimize_keeps_pixels_and_chunks() {
+        let mut png = testing_png();
+        let raw = pixels(&png);
+        let chunk_types: Vec<ChunkType> = png.chunks().iter().map(|x| *x.chunk_type()).collect();
+        let options = OptimizeOptions {
+            strategies: Strategy::DEFAULT
+                .into_iter()
+                .chain([Strategy::BruteForce])
+                .collect(),
+            levels: vec![zlib::MAX_LEVEL],
+            reduce: false,
+        };
+
+        let report = optimize(&mut png, &options, &Limits::default()).unwrap();
+        assert_eq!(report.old_size, testing_png().image_data().len());
+        assert!(report.is_smaller());
+        assert_eq!(report.new_size, png.image_data().len());
+        assert_eq!(pixels(&png), raw);
+        assert_eq!(
+            png.chunks()
+                .iter()
+                .map(|x| *x.chunk_type())
+                .collect::<Vec<_>>(),
+            chunk_types
+        );
+        assert!(png.verify_crcs().is_ok());
+
+        let report = optimize(&mut png, &options, &Limits::default()).unwrap();
+        assert!(!report.is_smaller());
+        assert_eq!(pixels(&png), raw);
+    }
+
+    #[test]
+    fn test_reductions() {
+        let png = testing_image(3);
+        let original = Image {
+            ihdr: png.ihdr().unwrap(),
+            raw: pixels(&png),
+            palette: None,
+            transparency: None,
+        };
+        let images = reductions(&png, &original);
+        assert_eq!(images.len(), 2);
+
+        let indexed = &images[0];
+        assert_eq!(indexed.ihdr.colour_type, ColourType::Indexed);
+        assert_eq!(indexed.ihdr.bit_depth, 2);
+        let entries = &indexed.palette.as_ref().unwrap().entries;
+        assert_eq!(
+            entries,
+            &[[0x12, 0x34, 0x56], [0x80, 0x80, 0x80], [0xff, 0, 0]]
+        );
+        assert_eq!(indexed.transparency, None);
+        // Adam7 rows of a 3x3 image are 1, 1, 2, 1, 1 and 3 pixels wide, one byte each.
+        assert_eq!(indexed.raw, [0x00, 0x40, 0x80, 0x40, 0x80, 0x04]);
+
+        let truecolour = &images[1];
+        assert_eq!(truecolour.ihdr.colour_type, ColourType::Truecolour);
+        assert_eq!(truecolour.ihdr.bit_depth, 8);
+        assert_eq!(truecolour.raw[..6], [0x12, 0x34, 0x56, 0x80, 0x80, 0x80]);
+
+        let mut png = testing_image(3);
+        png.insert_chunk(1, Chunk::new(ChunkType::bKGD, vec![0; 6]));
+        assert!(reductions(&png, &original).is_empty());
+    }
+
+    #[test]
+    fn test_optimize_reduces_to_palette() {
+        let mut png = testing_image(32);
+        let options = OptimizeOptions {
+            reduce: true,
+            ..Default::default()
+        };
+        let report = optimize(&mut png, &options, &Limits::default()).unwrap();
+        assert!(report.is_smaller());
+        let ihdr = png.ihdr().unwrap();
+        assert_eq!(
+            report.reduction,
+            Some((testing_image(32).ihdr().unwrap(), ihdr))
+        );
+        assert_eq!(ihdr.colour_type, ColourType::Indexed);
+        assert!(png.validate().is_ok());
+        assert_eq!(
+            png.chunks()
+                .iter()
+                .map(|x| x.chunk_type().to_string())
+                .collect::<Vec<_>>(),
+            ["IHDR", "gAMA", "PLTE", "IDAT", "IEND"]
+        );
+        assert_eq!(
+            optimize(
+                &mut Png::with_signature(Signature::Mng, vec![]),
+                &options,
+                &Limits::default()
+            ),
+            Err(OptimizeError::UnsupportedFormat)
+        );
+    }
+}
@@ -0,0 +1,516 @@
+use std::fmt::Display;
+
+use derive_more::{Display, Error};
+
+use crate::ancillary::Hist;
+use crate::chunk_type::ChunkType;
+use crate::codec::{ChunkData, CodecError};
+use crate::filter::{self, passes, row_length, unfilter, FilterError, FilterType};
+use crate::ihdr::{ColourType, Ihdr};
+use crate::limits::Limits;
+use crate::png::{Png, PngError};
+use crate::zlib;
+
+#[derive(PartialEq, Debug, Display, Error)]
+pub enum PaletteError {
+    InvalidLength,
+    TooManyEntries,
+    PaletteNotAllowed,
+    MissingPalette,
+    TransparencyNotAllowed,
+    IndexOutOfRange,
+    SampleOutOfRange,
+    InvalidOrder,
+    InvalidColour,
+    InvalidChunk(CodecError),
+    InvalidImageData(FilterError),
+    InvalidPng(PngError),
+}
+
+impl From<CodecError> for PaletteError {
+    fn from(e: CodecError) -> Self {
+        PaletteError::InvalidChunk(e)
+    }
+}
+
+impl From<FilterError> for PaletteError {
+    fn from(e: FilterError) -> Self {
+        PaletteError::InvalidImageData(e)
+    }
+}
+
+impl From<PngError> for PaletteError {
+    fn from(e: PngError) -> Self {
+        PaletteError::InvalidPng(e)
+    }
+}
+
+/// The colour entries of a `PLTE` chunk.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Palette {
+    pub entries: Vec<[u8; 3]>,
+}
+
+impl Palette {
+    pub const MAX_ENTRIES: usize = 256;
+
+    /// Reads the `PLTE` chunk of `png`, if there is one.
+    pub fn from_png(png: &Png) -> Result<Option<Palette>, PaletteError> {
+        match png.chunk_by_type("PLTE") {
+            Some(chunk) => Ok(Some(Palette::from_bytes(chunk.data())?)),
+            None => Ok(None),
+        }
+    }
+    /// Checks the entry count against the colour type and bit depth of `ihdr`.
+    pub fn validate(&self, ihdr: &Ihdr) -> Result<(), PaletteError> {
+        match ihdr.colour_type {
+            ColourType::Greyscale | ColourType::GreyscaleAlpha => {
+                Err(PaletteError::PaletteNotAllowed)
+            }
+            ColourType::Indexed if self.entries.len() > 1 << ihdr.bit_depth => {
+                Err(PaletteError::TooManyEntries)
+            }
+            _ => Ok(()),
+        }
+    }
+    pub fn set(&mut self, index: usize, colour: [u8; 3]) -> Result<(), PaletteError> {
+        match self.entries.get_mut(index) {
+            Some(entry) => {
+                *entry = colour;
+                Ok(())
+            }
+            None => Err(PaletteError::IndexOutOfRange),
+        }
+    }
+    /// Moves entry `order[i]` to position `i`; `order` must be a permutation of all indices.
+    pub fn reorder(&mut self, order: &[usize]) -> Result<(), PaletteError> {
+        self.entries = permute(&self.entries, order)?;
+        Ok(())
+    }
+}
+
+fn permute<T: Copy>(values: &[T], order: &[usize]) -> Result<Vec<T>, PaletteError> {
+    let mut seen = vec![false; values.len()];
+    if order.len() != values.len() {
+        return Err(PaletteError::InvalidOrder);
+    }
+    for i in order.iter() {
+        match seen.get_mut(*i) {
+            Some(x) if !*x => *x = true,
+            _ => return Err(PaletteError::InvalidOrder),
+        }
+    }
+    Ok(order.iter().map(|i| values[*i]).collect())
+}
+
+impl ChunkData for Palette {
+    const CHUNK_TYPE: ChunkType = ChunkType::PLTE;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        let count = data.len() / 3;
+        if !data.len().is_multiple_of(3) || count == 0 || count > Palette::MAX_ENTRIES {
+            return Err(CodecError::InvalidLength);
+        }
+        Ok(Palette {
+            entries: data.chunks(3).map(|x| [x[0], x[1], x[2]]).collect(),
+        })
+    }
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        Ok(self.entries.concat())
+    }
+}
+
+impl Display for Palette {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} palette entries", self.entries.len())
+    }
+}
+
+/// The contents of a `tRNS` chunk, interpreted according to the colour type.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Transparency {
+    /// Alpha for the leading palette entries; missing entries are opaque.
+    Alpha(Vec<u8>),
+    Grey(u16),
+    Rgb(u16, u16, u16),
+}
+
+impl Transparency {
+    /// Reads the `tRNS` chunk of `png`, if there is one.
+    pub fn from_png(png: &Png, ihdr: &Ihdr) -> Result<Option<Transparency>, PaletteError> {
+        match png.chunk_by_type("tRNS") {
+            Some(chunk) => Ok(Some(Transparency::from_bytes(chunk.data(), ihdr)?)),
+            None => Ok(None),
+        }
+    }
+    pub fn from_bytes(data: &[u8], ihdr: &Ihdr) -> Result<Transparency, PaletteError> {
+        let sample = |i: usize| u16::from_be_bytes([data[i], data[i + 1]]);
+        match (ihdr.colour_type, data.len()) {
+            (ColourType::Indexed, n) if n <= Palette::MAX_ENTRIES => {
+                Ok(Transparency::Alpha(data.to_vec()))
+            }
+            (ColourType::Greyscale, 2) => Ok(Transparency::Grey(sample(0))),
+            (ColourType::Truecolour, 6) => Ok(Transparency::Rgb(sample(0), sample(2), sample(4))),
+            (ColourType::GreyscaleAlpha | ColourType::TruecolourAlpha, _) => {
+                Err(PaletteError::TransparencyNotAllowed)
+            }
+            _ => Err(PaletteError::InvalidLength),
+        }
+    }
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Transparency::Alpha(x) => x.clone(),
+            Transparency::Grey(g) => g.to_be_bytes().to_vec(),
+            Transparency::Rgb(r, g, b) => [r, g, b].iter().flat_map(|x| x.to_be_bytes()).collect(),
+        }
+    }
+    /// Checks the layout against `ihdr` and, for indexed images, the entry count against `palette`.
+    pub fn validate(&self, ihdr: &Ihdr, palette: Option<&Palette>) -> Result<(), PaletteError> {
+        let max_sample = (1u32 << ihdr.bit_depth) - 1;
+        let in_range = |x: &u16| *x as u32 <= max_sample;
+        match (self, ihdr.colour_type) {
+            (Transparency::Alpha(alpha), ColourType::Indexed) => match palette {
+                Some(p) if alpha.len() <= p.entries.len() => Ok(()),
+                Some(_) => Err(PaletteError::TooManyEntries),
+                None => Err(PaletteError::MissingPalette),
+            },
+            (Transparency::Grey(g), ColourType::Greyscale) if in_range(g) => Ok(()),
+            (Transparency::Rgb(r, g, b), ColourType::Truecolour)
+                if [r, g, b].into_iter().all(in_range) =>
+            {
+                Ok(())
+            }
+            (Transparency::Grey(_), ColourType::Greyscale)
+            | (Transparency::Rgb(..), ColourType::Truecolour) => {
+                Err(PaletteError::SampleOutOfRange)
+            }
+            (_, ColourType::GreyscaleAlpha | ColourType::TruecolourAlpha) => {
+                Err(PaletteError::TransparencyNotAllowed)
+            }
+            _ => Err(PaletteError::InvalidLength),
+        }
+    }
+    /// Alpha of palette entry `index`; entries beyond the `tRNS` data are opaque.
+    pub fn alpha(&self, index: usize) -> Option<u8> {
+        match self {
+            Transparency::Alpha(x) => Some(x.get(index).copied().unwrap_or(255)),
+            _ => None,
+        }
+    }
+    /// Sets the alpha of palette entry `index`, padding earlier entries as opaque.
+    pub fn set_alpha(&mut self, index: usize, alpha: u8) -> Result<(), PaletteError> {
+        match self {
+            Transparency::Alpha(x) if index < Palette::MAX_ENTRIES => {
+                if x.len() <= index {
+                    x.resize(index + 1, 255);
+                }
+                x[index] = alpha;
+                trim_opaque(x);
+                Ok(())
+            }
+            Transparency::Alpha(_) => Err(PaletteError::IndexOutOfRange),
+            _ => Err(PaletteError::TransparencyNotAllowed),
+        }
+    }
+    /// Applies the same permutation as [`Palette::reorder`] to the alpha values.
+    pub fn reorder(&mut self, order: &[usize]) -> Result<(), PaletteError> {
+        match self {
+            Transparency::Alpha(x) => {
+                let mut padded = x.clone();
+                padded.resize(order.len(), 255);
+                *x = permute(&padded, order)?;
+                trim_opaque(x);
+                Ok(())
+            }
+            _ => Err(PaletteError::TransparencyNotAllowed),
+        }
+    }
+}
+
+// Trailing opaque entries are implied, so they are left out of the chunk.
+fn trim_opaque(alpha: &mut Vec<u8>) {
+    while alpha.last() == Some(&255) {
+        alpha.pop();
+    }
+}
+
+/// Reorders `PLTE` and, when present, `tRNS` and `hIST` together.
+///
+/// Pixel indices in `IDAT` are left untouched, so the image changes appearance unless
+/// [`remap_indices`] is applied with the same `order`.
+pub fn reorder_palette(png: &mut Png, order: &[usize]) -> Result<(), PaletteError> {
+    let ihdr = png.ihdr().map_err(|_| PaletteError::MissingPalette)?;
+    let mut palette = Palette::from_png(png)?.ok_or(PaletteError::MissingPalette)?;
+    palette.reorder(order)?;
+    let transparency = match Transparency::from_png(png, &ihdr)? {
+        Some(mut x) => {
+            x.reorder(order)?;
+            Some(x)
+        }
+        None => None,
+    };
+    let hist = match png.chunk_by_type("hIST") {
+        Some(chunk) => Some(Hist(permute(&Hist::from_bytes(chunk.data())?.0, order)?)),
+        None => None,
+    };
+
+    png.chunk_by_type_mut("PLTE")
+        .unwrap()
+        .set_data(palette.to_bytes()?);
+    if let Some(x) = transparency {
+        png.chunk_by_type_mut("tRNS")
+            .unwrap()
+            .set_data(x.to_bytes());
+    }
+    if let Some(x) = hist {
+        png.chunk_by_type_mut("hIST")
+            .unwrap()
+            .set_data(x.to_bytes()?);
+    }
+    Ok(())
+}
+
+/// Rewrites the palette indices in `IDAT` so that an image reordered by [`reorder_palette`] with
+/// the same `order` looks as before. Images that are not indexed are left untouched.
+///
+/// The image data is refiltered with filter type None and recompressed.
+pub fn remap_indices(png: &mut Png, order: &[usize], limits: &Limits) -> Result<(), PaletteError> {
+    let ihdr = png.ihdr()?;
+    if ihdr.colour_type != ColourType::Indexed {
+        return Ok(());
+    }
+    let mut new_index = vec![None; order.len()];
+    for (i, &old) in order.iter().enumerate() {
+        *new_index.get_mut(old).ok_or(PaletteError::InvalidOrder)? = Some(i as u8);
+    }
+
+    let mut raw = unfilter(&zlib::inflate(&png.image_data(), limits)?, &ihdr)?;
+    let bit_depth = ihdr.bit_depth as usize;
+    let mask = ((1u16 << bit_depth) - 1) as u8;
+    let mut start = 0;
+    for (width, height) in passes(&ihdr) {
+        let length = row_length(&ihdr, width);
+        for row in raw[start..start + length * height as usize].chunks_exact_mut(length) {
+            for x in 0..width as usize {
+                let byte = &mut row[x * bit_depth / 8];
+                let shift = 8 - bit_depth - x * bit_depth % 8;
+                let old = (*byte >> shift) & mask;
+                let new = new_index
+                    .get(old as usize)
+                    .copied()
+                    .flatten()
+                    .ok_or(PaletteError::IndexOutOfRange)?;
+                *byte = (*byte & !(mask << shift)) | (new << shift);
+            }
+        }
+        start += length * height as usize;
+    }
+    png.set_image_data(vec![zlib::deflate(&filter::filter(
+        &raw,
+        &ihdr,
+        FilterType::None,
+    ))]);
+    Ok(())
+}
+
+/// Parses `#rrggbb` or `rrggbb`.
+pub fn parse_colour(s: &str) -> Result<[u8; 3], PaletteError> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if hex.len() != 6 || !hex.is_ascii() {
+        return Err(PaletteError::InvalidColour);
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+    match (channel(0), channel(2), channel(4)) {
+        (Ok(r), Ok(g), Ok(b)) => Ok([r, g, b]),
+        _ => Err(PaletteError::InvalidColour),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+
+    fn indexed_ihdr(bit_depth: u8) -> Ihdr {
+        Ihdr {
+            width: 1,
+            height: 1,
+            bit_depth,
+            colour_type: ColourType::Indexed,
+            interlaced: false,
+        }
+    }
+
+    fn testing_palette() -> Palette {
+        Palette::from_bytes(&[255, 0, 0, 0, 255, 0, 0, 0, 255]).unwrap()
+    }
+
+    #[test]
+    fn test_palette_from_bytes() {
+        let palette = testing_palette();
+        assert_eq!(palette.entries[1], [0, 255, 0]);
+        assert_eq!(
+            palette.to_bytes().unwrap(),
+            vec![255, 0, 0, 0, 255, 0, 0, 0, 255]
+        );
+        assert_eq!(Palette::from_bytes(&[]), Err(CodecError::InvalidLength));
+        assert_eq!(Palette::from_bytes(&[0; 4]), Err(CodecError::InvalidLength));
+        assert_eq!(
+            Palette::from_bytes(&[0; 257 * 3]),
+            Err(CodecError::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn test_palette_validate() {
+        let palette = testing_palette();
+        assert_eq!(palette.validate(&indexed_ihdr(2)), Ok(()));
+        assert_eq!(
+            palette.validate(&indexed_ihdr(1)),
+            Err(PaletteError::TooManyEntries)
+        );
+
+        let grey = Ihdr {
+            colour_type: ColourType::Greyscale,
+            ..indexed_ihdr(8)
+        };
+        assert_eq!(
+            palette.validate(&grey),
+            Err(PaletteError::PaletteNotAllowed)
+        );
+    }
+
+    #[test]
+    fn test_palette_edit() {
+        let mut palette = testing_palette();
+        palette.set(0, [1, 2, 3]).unwrap();
+        assert_eq!(palette.entries[0], [1, 2, 3]);
+        assert_eq!(palette.set(3, [0; 3]), Err(PaletteError::IndexOutOfRange));
+
+        palette.reorder(&[2, 0, 1]).unwrap();
+        assert_eq!(palette.entries, vec![[0, 0, 255], [1, 2, 3], [0, 255, 0]]);
+        assert_eq!(palette.reorder(&[0, 0, 1]), Err(PaletteError::InvalidOrder));
+        assert_eq!(palette.reorder(&[0, 1]), Err(PaletteError::InvalidOrder));
+    }
+
+    #[test]
+    fn test_transparency() {
+        let ihdr = indexed_ihdr(8);
+        let palette = testing_palette();
+        let mut trns = Transparency::from_bytes(&[0, 128], &ihdr).unwrap();
+        assert_eq!(trns.validate(&ihdr, Some(&palette)), Ok(()));
+        assert_eq!(
+            trns.validate(&ihdr, None),
+            Err(PaletteError::MissingPalette)
+        );
+        assert_eq!(trns.alpha(2), Some(255));
+
+        trns.set_alpha(2, 64).unwrap();
+        assert_eq!(trns, Transparency::Alpha(vec![0, 128, 64]));
+        trns.reorder(&[2, 1, 0]).unwrap();
+        assert_eq!(trns, Transparency::Alpha(vec![64, 128, 0]));
+        trns.set_alpha(2, 255).unwrap();
+        assert_eq!(trns.to_bytes(), vec![64, 128]);
+
+        let grey = Ihdr {
+            colour_type: ColourType::Greyscale,
+            ..indexed_ihdr(4)
+        };
+        let trns = Transparency::from_bytes(&[0, 15], &grey).unwrap();
+        assert_eq!(trns, Transparency::Grey(15));
+        assert_eq!(trns.validate(&grey, None), Ok(()));
+        let trns = Transparency::from_bytes(&[0, 16], &grey).unwrap();
+        assert_eq!(
+            trns.validate(&grey, None),
+            Err(PaletteError::SampleOutOfRange)
+        );
+        assert_eq!(
+            Transparency::from_bytes(&[0, 1, 2], &grey),
+            Err(PaletteError::InvalidLength)
+        );
+
+        let alpha = Ihdr {
+            colour_type: ColourType::TruecolourAlpha,
+            ..indexed_ihdr(8)
+        };
+        assert_eq!(
+            Transparency::from_bytes(&[0; 6], &alpha),
+            Err(PaletteError::TransparencyNotAllowed)
+        );
+    }
+
+    #[test]
+    fn test_reorder_palette() {
+        let mut png = Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, indexed_ihdr(8).as_bytes()),
+            testing_palette().to_chunk().unwrap(),
+            Chunk::new(ChunkType::tRNS, vec![0]),
+            Hist(vec![1, 2, 3]).to_chunk().unwrap(),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ]);
+        reorder_palette(&mut png, &[1, 2, 0]).unwrap();
+
+        let palette = Palette::from_png(&png).unwrap().unwrap();
+        assert_eq!(palette.entries, vec![[0, 255, 0], [0, 0, 255], [255, 0, 0]]);
+        assert_eq!(png.chunk_by_type("tRNS").unwrap().data(), &[255, 255, 0]);
+        assert_eq!(
+            png.chunk_by_type("hIST").unwrap().data(),
+            &[0, 2, 0, 3, 0, 1]
+        );
+    }
+
+    #[test]
+    fn test_remap_indices() {
+        let png_with = |ihdr: Ihdr, raw: &[u8]| {
+            Png::from_chunks(vec![
+                Chunk::new(ChunkType::IHDR, ihdr.as_bytes()),
+                testing_palette().to_chunk().unwrap(),
+                Chunk::new(
+                    ChunkType::IDAT,
+                    zlib::deflate(&filter::filter(raw, &ihdr, FilterType::Paeth)),
+                ),
+                Chunk::new(ChunkType::IEND, vec![]),
+            ])
+        };
+        let raw_of = |png: &Png| {
+            let data = zlib::inflate(&png.image_data(), &Limits::default()).unwrap();
+            unfilter(&data, &png.ihdr().unwrap()).unwrap()
+        };
+
+        // Rows 0 1 2 0 1 and 2 2 1 1 0 at two bits per pixel, with padding bits in each row.
+        let ihdr = Ihdr {
+            width: 5,
+            height: 2,
+            ..indexed_ihdr(2)
+        };
+        let mut png = png_with(ihdr, &[0x18, 0x40, 0xa5, 0x00]);
+        remap_indices(&mut png, &[1, 2, 0], &Limits::default()).unwrap();
+        assert_eq!(raw_of(&png), [0x86, 0x00, 0x50, 0x80]);
+
+        let ihdr = Ihdr {
+            width: 3,
+            height: 3,
+            interlaced: true,
+            ..indexed_ihdr(8)
+        };
+        let raw: Vec<u8> = (0..9).map(|x| x % 3).collect();
+        let mut png = png_with(ihdr, &raw);
+        remap_indices(&mut png, &[1, 2, 0], &Limits::default()).unwrap();
+        let expected: Vec<u8> = raw.iter().map(|x| [2, 0, 1][*x as usize]).collect();
+        assert_eq!(raw_of(&png), expected);
+
+        let mut png = png_with(ihdr, &[3; 9]);
+        assert_eq!(
+            remap_indices(&mut png, &[1, 2, 0], &Limits::default()),
+            Err(PaletteError::IndexOutOfRange)
+        );
+    }
+
+    #[test]
+    fn test_parse_colour() {
+        assert_eq!(parse_colour("#ff8000"), Ok([255, 128, 0]));
+        assert_eq!(parse_colour("0000FF"), Ok([0, 0, 255]));
+        assert_eq!(parse_colour("#fff"), Err(PaletteError::InvalidColour));
+        assert_eq!(parse_colour("gg0000"), Err(PaletteError::InvalidColour));
+    }
+}
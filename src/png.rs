@@ -1,26 +1,152 @@
 use derive_more::{Display, Error};
 
+use crate::ancillary::Itxt;
 use crate::chunk::{Chunk, ChunkError};
+use crate::chunk_type::ChunkType;
+use crate::codec::ChunkData;
+use crate::ihdr::{Ihdr, IhdrError};
+use crate::limits::Limits;
+use crate::png_ref::PngRef;
+use crate::signature::Signature;
+use crate::xmp::XMP_KEYWORD;
 
 #[derive(PartialEq, Debug, Display, Error)]
 pub enum PngError {
     InvalidHeader,
     InvalidChunk,
     InvalidChunkType,
+    UnexpectedEof,
+    CrcMismatch,
+    FileTooLarge,
+    ChunkTooLarge,
+    TooManyChunks,
+    DecompressedTooLarge,
+    InvalidCompressedData,
+    ImageTooLarge,
+    InvalidIhdr(IhdrError),
+    MissingIhdr,
+    /// The first chunk of an MNG or JNG stream is not its header chunk.
+    MissingHeaderChunk,
+    MissingIdat,
+    MissingEndChunk,
 }
 
+/// How the parser treats a chunk whose stored CRC does not match its contents.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum CrcMode {
+    #[default]
+    Strict,
+    Ignore,
+    Warn,
+    Fix,
+    /// Do not compute CRCs while parsing; trusted input can be checked later with `verify_crcs`.
+    Defer,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct ParseOptions {
+    pub crc: CrcMode,
+    /// Skip over corrupted regions, drop data after `IEND` and append a missing `IEND`.
+    pub recover: bool,
+    pub limits: Limits,
+}
+
+impl ParseOptions {
+    pub fn repair() -> ParseOptions {
+        ParseOptions {
+            crc: CrcMode::Fix,
+            recover: true,
+            limits: Limits::default(),
+        }
+    }
+}
+
+/// A problem found (and, depending on the options, fixed) while parsing leniently.
+#[derive(PartialEq, Debug, Display)]
+pub enum Repair {
+    #[display(
+        fmt = "chunk {} ({}) has CRC {:08x}, expected {:08x}",
+        index,
+        chunk_type,
+        stored,
+        computed
+    )]
+    CrcMismatch {
+        index: usize,
+        chunk_type: String,
+        stored: u32,
+        computed: u32,
+    },
+    #[display(
+        fmt = "chunk {} ({}) CRC fixed from {:08x} to {:08x}",
+        index,
+        chunk_type,
+        stored,
+        computed
+    )]
+    CrcFixed {
+        index: usize,
+        chunk_type: String,
+        stored: u32,
+        computed: u32,
+    },
+    #[display(fmt = "skipped {} corrupted bytes at offset {}", length, offset)]
+    SkippedBytes { offset: usize, length: usize },
+    #[display(fmt = "dropped {} bytes after IEND at offset {}", length, offset)]
+    DroppedAfterIend { offset: usize, length: usize },
+    #[display(fmt = "inserted missing {} chunk", _0)]
+    InsertedEndChunk(ChunkType),
+}
+
+#[derive(PartialEq, Debug)]
 pub struct Png {
+    _signature: Signature,
     _chunks: Vec<Chunk>,
 }
 
 impl Png {
-    pub const STANDARD_HEADER: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+    pub const STANDARD_HEADER: [u8; 8] = Signature::PNG;
     pub fn from_chunks(chunks: Vec<Chunk>) -> Png {
-        Png { _chunks: chunks }
+        Png::with_signature(Signature::Png, chunks)
+    }
+    /// Builds a file of the PNG family, such as an MNG or JNG stream.
+    pub fn with_signature(signature: Signature, chunks: Vec<Chunk>) -> Png {
+        Png {
+            _signature: signature,
+            _chunks: chunks,
+        }
+    }
+    pub fn signature(&self) -> Signature {
+        self._signature
     }
     pub fn append_chunk(&mut self, chunk: Chunk) {
         self._chunks.push(chunk);
     }
+    pub fn insert_chunk(&mut self, index: usize, chunk: Chunk) {
+        self._chunks.insert(index, chunk);
+    }
+    /// Inserts `chunk` before the first chunk whose type is in `before` or the end chunk.
+    pub fn insert_chunk_before(&mut self, chunk: Chunk, before: &[ChunkType]) {
+        let end = self._signature.end_chunk();
+        let index = self
+            ._chunks
+            .iter()
+            .position(|x| before.contains(x.chunk_type()) || *x.chunk_type() == end)
+            .unwrap_or(self._chunks.len());
+        self._chunks.insert(index, chunk);
+    }
+    /// Replaces every chunk of the same type with `chunk`, placed before the first chunk whose type is in `before` or the end chunk.
+    pub fn put_chunk_before(&mut self, chunk: Chunk, before: &[ChunkType]) {
+        let chunk_type = *chunk.chunk_type();
+        self.remove_chunks_by_type(&chunk_type);
+        self.insert_chunk_before(chunk, before);
+    }
+    /// Removes every chunk of `chunk_type`, returning how many were removed.
+    pub fn remove_chunks_by_type(&mut self, chunk_type: &ChunkType) -> usize {
+        let count = self._chunks.len();
+        self._chunks.retain(|x| x.chunk_type() != chunk_type);
+        count - self._chunks.len()
+    }
     pub fn remove_chunk(&mut self, chunk_type: &str) -> Result<Chunk, PngError> {
         let mut remove_idx = self._chunks.len();
         for (idx, chunk) in self._chunks.iter().enumerate() {
@@ -34,33 +160,164 @@ impl Png {
             Err(PngError::InvalidChunkType)
         }
     }
+    /// Removes and returns every chunk, leaving the signature in place.
+    pub fn take_chunks(&mut self) -> Vec<Chunk> {
+        std::mem::take(&mut self._chunks)
+    }
     pub fn header(&self) -> &[u8; 8] {
-        return &Png::STANDARD_HEADER;
+        self._signature.bytes()
     }
     pub fn chunks(&self) -> &[Chunk] {
         self._chunks.as_ref()
     }
+    /// Chunks for in-place edits; callers are responsible for keeping CRCs valid.
+    pub fn chunks_mut(&mut self) -> &mut [Chunk] {
+        &mut self._chunks
+    }
     pub fn chunk_by_type(&self, chunk_type: &str) -> Option<&Chunk> {
-        for chunk in self.chunks() {
-            if chunk.chunk_type().bytes() == chunk_type.as_bytes() {
-                return Some(chunk);
+        self.chunks()
+            .iter()
+            .find(|chunk| chunk.chunk_type().bytes() == chunk_type.as_bytes())
+    }
+    pub fn chunk_by_type_mut(&mut self, chunk_type: &str) -> Option<&mut Chunk> {
+        self._chunks
+            .iter_mut()
+            .find(|chunk| chunk.chunk_type().bytes() == chunk_type.as_bytes())
+    }
+    /// Index of the first chunk of `chunk_type`.
+    pub fn position(&self, chunk_type: &str) -> Option<usize> {
+        self.chunks()
+            .iter()
+            .position(|chunk| chunk.chunk_type().bytes() == chunk_type.as_bytes())
+    }
+    fn xmp_index(&self) -> Option<usize> {
+        self.chunks().iter().position(|chunk| {
+            *chunk.chunk_type() == ChunkType::iTXt
+                && chunk.data().starts_with(XMP_KEYWORD.as_bytes())
+                && chunk.data().get(XMP_KEYWORD.len()) == Some(&0)
+        })
+    }
+    /// The XMP packet stored in `iTXt` under the `XML:com.adobe.xmp` keyword.
+    pub fn xmp(&self) -> Result<Option<String>, PngError> {
+        self.xmp_with(&Limits::default())
+    }
+    /// Like [`Png::xmp`], bounding decompression of a compressed packet by `limits`.
+    pub fn xmp_with(&self, limits: &Limits) -> Result<Option<String>, PngError> {
+        match self.xmp_index() {
+            Some(i) => {
+                let itxt = Itxt::from_bytes(self.chunks()[i].data())
+                    .map_err(|_| PngError::InvalidChunk)?;
+                Ok(Some(itxt.text(limits)?))
             }
+            None => Ok(None),
         }
-        return None;
     }
-    pub fn as_bytes(&self) -> Vec<u8> {
-        let chunks_it = self.chunks().iter().flat_map(|x| x.as_bytes());
-        Png::STANDARD_HEADER
+    /// Stores `packet` uncompressed, as the XMP specification recommends.
+    ///
+    /// An existing packet is replaced in place; otherwise the chunk goes before the image data.
+    pub fn set_xmp(&mut self, packet: &str) -> Result<(), PngError> {
+        let chunk = Itxt::new(XMP_KEYWORD, packet)
+            .to_chunk()
+            .map_err(|_| PngError::InvalidChunk)?;
+        match self.xmp_index() {
+            Some(i) => self._chunks[i] = chunk,
+            None => {
+                let i = self
+                    .position("IDAT")
+                    .or_else(|| self.position("IEND"))
+                    .unwrap_or(self._chunks.len());
+                self._chunks.insert(i, chunk);
+            }
+        }
+        Ok(())
+    }
+    /// Whether the file starts with Apple's `CgBI` chunk, which standard decoders reject.
+    pub fn is_cgbi(&self) -> bool {
+        self.chunks()
+            .first()
+            .is_some_and(|x| *x.chunk_type() == ChunkType::CgBI)
+    }
+    /// The compressed image stream: the data of every `IDAT` chunk joined together.
+    pub fn image_data(&self) -> Vec<u8> {
+        self.chunks()
             .iter()
-            .copied()
-            .chain(chunks_it)
+            .filter(|x| *x.chunk_type() == ChunkType::IDAT)
+            .flat_map(|x| x.data().iter().copied())
             .collect()
     }
+    /// Replaces every `IDAT` chunk with `chunks`, placed where the first one was.
+    pub fn set_image_data(&mut self, chunks: Vec<Vec<u8>>) {
+        let index = self
+            .position("IDAT")
+            .or_else(|| self.position("IEND"))
+            .unwrap_or(self._chunks.len());
+        self.remove_chunks_by_type(&ChunkType::IDAT);
+        for (i, data) in chunks.into_iter().enumerate() {
+            self._chunks
+                .insert(index + i, Chunk::new(ChunkType::IDAT, data));
+        }
+    }
+    /// Decodes the leading `IHDR` chunk.
+    pub fn ihdr(&self) -> Result<Ihdr, PngError> {
+        match self.chunks().first() {
+            Some(chunk) if *chunk.chunk_type() == ChunkType::IHDR => {
+                Ok(Ihdr::try_from(chunk.data())?)
+            }
+            _ => Err(PngError::MissingIhdr),
+        }
+    }
+    /// Checks the critical chunk structure: the signature's header chunk first, which for a PNG
+    /// must be a valid `IHDR` followed somewhere by `IDAT`, and the signature's end chunk last.
+    pub fn validate(&self) -> Result<(), PngError> {
+        let signature = self.signature();
+        if signature == Signature::Png {
+            self.ihdr()?;
+            if self.chunk_by_type("IDAT").is_none() {
+                return Err(PngError::MissingIdat);
+            }
+        } else if self
+            .chunks()
+            .first()
+            .is_none_or(|x| *x.chunk_type() != signature.header_chunk())
+        {
+            return Err(PngError::MissingHeaderChunk);
+        }
+        match self.chunks().last() {
+            Some(chunk) if *chunk.chunk_type() == signature.end_chunk() => Ok(()),
+            _ => Err(PngError::MissingEndChunk),
+        }
+    }
+    pub fn verify_crcs(&self) -> Result<(), PngError> {
+        if self.chunks().iter().all(Chunk::is_crc_valid) {
+            Ok(())
+        } else {
+            Err(PngError::CrcMismatch)
+        }
+    }
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let chunks_it = self.chunks().iter().flat_map(|x| x.as_bytes());
+        self.header().iter().copied().chain(chunks_it).collect()
+    }
+    /// Parses `value` according to `options`, returning every problem that was tolerated or fixed.
+    pub fn parse_with(value: &[u8], options: ParseOptions) -> Result<(Png, Vec<Repair>), PngError> {
+        let (png, repairs) = PngRef::parse_with(value, options)?;
+        Ok((png.to_png(), repairs))
+    }
 }
 
 impl From<ChunkError> for PngError {
-    fn from(_: ChunkError) -> Self {
-        PngError::InvalidChunk
+    fn from(e: ChunkError) -> Self {
+        match e {
+            ChunkError::UnexpectedEof => PngError::UnexpectedEof,
+            ChunkError::CrcMismatch => PngError::CrcMismatch,
+            ChunkError::InvalidChunkType => PngError::InvalidChunk,
+        }
+    }
+}
+
+impl From<IhdrError> for PngError {
+    fn from(e: IhdrError) -> Self {
+        PngError::InvalidIhdr(e)
     }
 }
 
@@ -68,26 +325,8 @@ impl TryFrom<&[u8]> for Png {
     type Error = PngError;
 
     fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        if value[0..8] != Png::STANDARD_HEADER {
-            Err(PngError::InvalidHeader)
-        } else {
-            let mut i = 8;
-            let mut chunks = vec![];
-            while i < value.len() {
-                let data_len_bytes = [value[i], value[i + 1], value[i + 2], value[i + 3]];
-                let data_length = u32::from_be_bytes(data_len_bytes) as usize;
-
-                let mut chunk_bytes = vec![];
-                for j in i..i + data_length + 12 {
-                    chunk_bytes.push(value[j]);
-                }
-
-                let chunk = Chunk::try_from(chunk_bytes.as_ref())?;
-                chunks.push(chunk);
-                i += data_length + 12;
-            }
-            Ok(Png { _chunks: chunks })
-        }
+        let (png, _) = Png::parse_with(value, ParseOptions::default())?;
+        Ok(png)
     }
 }
 
@@ -103,7 +342,9 @@ mod tests {
     use crate::chunk::Chunk;
     use crate::chunk_type::{ChunkType, ChunkTypeError};
     use std::convert::TryFrom;
+    use std::str::FromStr;
 
+    #[allow(clippy::vec_init_then_push)]
     fn testing_chunks() -> Vec<Chunk> {
         let mut chunks = Vec::new();
 
@@ -218,6 +459,74 @@ mod tests {
         assert_eq!(&chunk.data_as_string().unwrap(), "Message");
     }
 
+    #[test]
+    fn test_insert_chunk() {
+        let mut png = testing_png();
+        png.insert_chunk(1, chunk_from_strings("TeSt", "Message").unwrap());
+        assert_eq!(png.position("TeSt"), Some(1));
+        assert_eq!(png.position("miDl"), Some(2));
+        assert_eq!(png.position("NoNe"), None);
+
+        png.chunk_by_type_mut("TeSt")
+            .unwrap()
+            .set_data(b"Changed".to_vec());
+        assert_eq!(&png.chunks()[1].data_as_string().unwrap(), "Changed");
+    }
+
+    #[test]
+    fn test_put_chunk_before() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("IEND", "").unwrap());
+        png.append_chunk(chunk_from_strings("teSt", "Old").unwrap());
+
+        let before = [ChunkType::from_str("miDl").unwrap()];
+        png.put_chunk_before(chunk_from_strings("teSt", "New").unwrap(), &before);
+        assert_eq!(png.position("teSt"), Some(1));
+        assert_eq!(png.chunks().len(), 5);
+        assert_eq!(&png.chunks()[1].data_as_string().unwrap(), "New");
+
+        png.put_chunk_before(chunk_from_strings("otHr", "").unwrap(), &[]);
+        assert_eq!(png.position("otHr"), Some(4));
+        assert_eq!(png.remove_chunks_by_type(&ChunkType::IEND), 1);
+        assert_eq!(png.remove_chunks_by_type(&ChunkType::IEND), 0);
+    }
+
+    #[test]
+    fn test_image_data() {
+        let mut png = testing_png();
+        png.insert_chunk(1, chunk_from_strings("IDAT", "ab").unwrap());
+        png.insert_chunk(2, chunk_from_strings("IDAT", "cd").unwrap());
+        assert_eq!(png.image_data(), b"abcd".to_vec());
+
+        png.set_image_data(vec![b"e".to_vec(), b"fgh".to_vec(), b"i".to_vec()]);
+        assert_eq!(png.image_data(), b"efghi".to_vec());
+        assert_eq!(png.position("IDAT"), Some(1));
+        assert_eq!(png.chunks().len(), 6);
+        assert_eq!(png.position("LASt"), Some(5));
+    }
+
+    #[test]
+    fn test_xmp() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let other = || Itxt::new("Comment", "unrelated").to_chunk().unwrap();
+        png.insert_chunk(1, other());
+        assert_eq!(png.xmp(), Ok(None));
+
+        png.set_xmp("<x:xmpmeta/>").unwrap();
+        assert_eq!(png.xmp(), Ok(Some("<x:xmpmeta/>".to_string())));
+        assert_eq!(png.position("iTXt"), Some(1));
+        let index = png.chunks().len() - 4;
+        assert_eq!(
+            png.chunks()[index].data(),
+            Itxt::new(XMP_KEYWORD, "<x:xmpmeta/>").to_bytes().unwrap()
+        );
+
+        png.set_xmp("<x:xmpmeta>2</x:xmpmeta>").unwrap();
+        assert_eq!(png.xmp(), Ok(Some("<x:xmpmeta>2</x:xmpmeta>".to_string())));
+        assert_eq!(png.chunks()[1], other());
+        assert_eq!(png.chunks().len(), 9);
+    }
+
     #[test]
     fn test_remove_chunk() {
         let mut png = testing_png();
@@ -241,6 +550,222 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    fn testing_png_bytes_with_iend() -> Vec<u8> {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("IEND", "").unwrap());
+        png.as_bytes()
+    }
+
+    #[test]
+    fn test_strict_crc_mismatch() {
+        let mut bytes = testing_png_bytes_with_iend();
+        let last_crc = bytes.len() - 13;
+        bytes[last_crc] ^= 1;
+
+        let png = Png::try_from(bytes.as_ref());
+        assert_eq!(png.err(), Some(PngError::CrcMismatch));
+    }
+
+    #[test]
+    fn test_truncated_png() {
+        let bytes = testing_png_bytes_with_iend();
+
+        let png = Png::try_from(&bytes[..bytes.len() - 3]);
+        assert_eq!(png.err(), Some(PngError::UnexpectedEof));
+
+        let png = Png::try_from(&bytes[..4]);
+        assert_eq!(png.err(), Some(PngError::InvalidHeader));
+    }
+
+    #[test]
+    fn test_parse_with_crc_modes() {
+        let mut bytes = testing_png_bytes_with_iend();
+        let last_crc = bytes.len() - 13;
+        bytes[last_crc] ^= 1;
+
+        let options = ParseOptions {
+            crc: CrcMode::Ignore,
+            ..Default::default()
+        };
+        let (png, repairs) = Png::parse_with(bytes.as_ref(), options).unwrap();
+        assert!(repairs.is_empty());
+        assert_eq!(png.as_bytes(), bytes);
+
+        let options = ParseOptions {
+            crc: CrcMode::Warn,
+            ..Default::default()
+        };
+        let (png, repairs) = Png::parse_with(bytes.as_ref(), options).unwrap();
+        assert!(matches!(
+            repairs[..],
+            [Repair::CrcMismatch { index: 2, .. }]
+        ));
+        assert_eq!(png.as_bytes(), bytes);
+
+        let options = ParseOptions {
+            crc: CrcMode::Fix,
+            ..Default::default()
+        };
+        let (png, repairs) = Png::parse_with(bytes.as_ref(), options).unwrap();
+        assert!(matches!(repairs[..], [Repair::CrcFixed { index: 2, .. }]));
+        assert_eq!(png.as_bytes(), testing_png_bytes_with_iend());
+    }
+
+    #[test]
+    fn test_parse_with_deferred_crc() {
+        let mut bytes = testing_png_bytes_with_iend();
+        let last_crc = bytes.len() - 13;
+        bytes[last_crc] ^= 1;
+
+        let options = ParseOptions {
+            crc: CrcMode::Defer,
+            ..Default::default()
+        };
+        let (png, repairs) = Png::parse_with(bytes.as_ref(), options).unwrap();
+        assert!(repairs.is_empty());
+        assert_eq!(png.verify_crcs(), Err(PngError::CrcMismatch));
+
+        let png = Png::try_from(testing_png_bytes_with_iend().as_ref()).unwrap();
+        assert_eq!(png.verify_crcs(), Ok(()));
+    }
+
+    #[test]
+    fn test_repair_drops_data_after_iend() {
+        let mut bytes = testing_png_bytes_with_iend();
+        let end = bytes.len();
+        bytes.extend(chunk_from_strings("TeSt", "Trailing").unwrap().as_bytes());
+
+        let (png, repairs) = Png::parse_with(bytes.as_ref(), ParseOptions::repair()).unwrap();
+        assert_eq!(png.chunks().len(), 4);
+        assert_eq!(
+            repairs,
+            vec![Repair::DroppedAfterIend {
+                offset: end,
+                length: bytes.len() - end
+            }]
+        );
+    }
+
+    #[test]
+    fn test_repair_resyncs_after_corruption() {
+        let mut bytes = testing_png_bytes_with_iend();
+        // Corrupt the length field of the second chunk.
+        let second = 8 + testing_chunks()[0].as_bytes().len();
+        bytes[second] = 0xff;
+
+        let (png, repairs) = Png::parse_with(bytes.as_ref(), ParseOptions::repair()).unwrap();
+        assert_eq!(png.chunks().len(), 3);
+        assert_eq!(
+            repairs,
+            vec![Repair::SkippedBytes {
+                offset: second,
+                length: testing_chunks()[1].as_bytes().len()
+            }]
+        );
+        assert!(png.chunk_by_type("miDl").is_none());
+        assert!(png.chunk_by_type("LASt").is_some());
+    }
+
+    #[test]
+    fn test_repair_inserts_iend() {
+        let bytes = testing_png().as_bytes();
+
+        let (png, repairs) = Png::parse_with(bytes.as_ref(), ParseOptions::repair()).unwrap();
+        assert_eq!(repairs, vec![Repair::InsertedEndChunk(ChunkType::IEND)]);
+        assert_eq!(png.as_bytes(), testing_png_bytes_with_iend());
+    }
+
+    #[test]
+    fn test_parse_with_limits() {
+        let bytes = testing_png().as_bytes();
+        let parse = |limits: Limits| {
+            let options = ParseOptions {
+                limits,
+                ..Default::default()
+            };
+            Png::parse_with(bytes.as_ref(), options).map(|_| ())
+        };
+
+        let limits = Limits {
+            max_file_size: bytes.len() - 1,
+            ..Default::default()
+        };
+        assert_eq!(parse(limits), Err(PngError::FileTooLarge));
+
+        let limits = Limits {
+            max_chunk_length: 19,
+            ..Default::default()
+        };
+        assert_eq!(parse(limits), Err(PngError::ChunkTooLarge));
+
+        let limits = Limits {
+            max_chunk_count: 2,
+            ..Default::default()
+        };
+        assert_eq!(parse(limits), Err(PngError::TooManyChunks));
+
+        let limits = Limits {
+            max_chunk_count: 3,
+            max_chunk_length: 20,
+            max_file_size: bytes.len(),
+            ..Default::default()
+        };
+        assert_eq!(parse(limits), Ok(()));
+    }
+
+    #[test]
+    fn test_parse_with_dimension_limits() {
+        let options = ParseOptions {
+            limits: Limits {
+                max_width: 49,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let png = Png::parse_with(&PNG_FILE[..], options);
+        assert_eq!(png.err(), Some(PngError::ImageTooLarge));
+
+        let options = ParseOptions {
+            limits: Limits::untrusted(),
+            ..Default::default()
+        };
+        assert!(Png::parse_with(&PNG_FILE[..], options).is_ok());
+    }
+
+    #[test]
+    fn test_validate() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert_eq!(png.validate(), Ok(()));
+        assert_eq!(png.ihdr().unwrap().width, 50);
+
+        assert_eq!(testing_png().validate(), Err(PngError::MissingIhdr));
+
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.remove_chunk("IEND").unwrap();
+        assert_eq!(png.validate(), Err(PngError::MissingEndChunk));
+
+        let chunk = |chunk_type: ChunkType| Chunk::new(chunk_type, vec![]);
+        let mng = Png::with_signature(
+            Signature::Mng,
+            vec![
+                chunk(ChunkType::MHDR),
+                chunk(ChunkType::IEND),
+                chunk(ChunkType::MEND),
+            ],
+        );
+        assert_eq!(mng.validate(), Ok(()));
+        let mng = Png::with_signature(
+            Signature::Mng,
+            vec![chunk(ChunkType::MHDR), chunk(ChunkType::IEND)],
+        );
+        assert_eq!(mng.validate(), Err(PngError::MissingEndChunk));
+        let jng = Png::with_signature(
+            Signature::Jng,
+            vec![chunk(ChunkType::IHDR), chunk(ChunkType::IEND)],
+        );
+        assert_eq!(jng.validate(), Err(PngError::MissingHeaderChunk));
+    }
+
     #[test]
     fn test_png_trait_impls() {
         let chunk_bytes: Vec<u8> = testing_chunks()
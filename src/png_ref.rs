@@ -0,0 +1,290 @@
+use crate::chunk_ref::ChunkRef;
+use crate::chunk_type::ChunkType;
+use crate::limits::Limits;
+use crate::png::{CrcMode, ParseOptions, Png, PngError, Repair};
+use crate::signature::Signature;
+
+/// A parsed PNG whose chunks borrow from the input buffer; convert with [`PngRef::to_png`] to modify it.
+#[derive(PartialEq, Debug)]
+pub struct PngRef<'a> {
+    _signature: Signature,
+    _chunks: Vec<ChunkRef<'a>>,
+}
+
+impl<'a> PngRef<'a> {
+    const IEND_CRC: u32 = 0xae42_6082;
+    const MEND_CRC: u32 = 0x2120_f7d5;
+
+    pub fn signature(&self) -> Signature {
+        self._signature
+    }
+    pub fn chunks(&self) -> &[ChunkRef<'a>] {
+        self._chunks.as_ref()
+    }
+    pub fn chunk_by_type(&self, chunk_type: &str) -> Option<&ChunkRef<'a>> {
+        self.chunks()
+            .iter()
+            .find(|chunk| chunk.chunk_type().bytes() == chunk_type.as_bytes())
+    }
+    /// Whether the file starts with Apple's `CgBI` chunk, which standard decoders reject.
+    pub fn is_cgbi(&self) -> bool {
+        self.chunks()
+            .first()
+            .is_some_and(|x| *x.chunk_type() == ChunkType::CgBI)
+    }
+    pub fn to_png(&self) -> Png {
+        let chunks = self.chunks().iter().map(ChunkRef::to_chunk).collect();
+        Png::with_signature(self._signature, chunks)
+    }
+    pub fn verify_crcs(&self) -> Result<(), PngError> {
+        if self.chunks().iter().all(ChunkRef::is_crc_valid) {
+            Ok(())
+        } else {
+            Err(PngError::CrcMismatch)
+        }
+    }
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let chunks_it = self.chunks().iter().flat_map(|x| x.as_bytes());
+        self._signature
+            .bytes()
+            .iter()
+            .copied()
+            .chain(chunks_it)
+            .collect()
+    }
+    /// Parses `value` according to `options` without copying chunk data.
+    pub fn parse_with(
+        value: &'a [u8],
+        options: ParseOptions,
+    ) -> Result<(PngRef<'a>, Vec<Repair>), PngError> {
+        let signature = Signature::detect(value).ok_or(PngError::InvalidHeader)?;
+        let end = signature.end_chunk();
+        options.limits.check_file_size(value.len())?;
+
+        let mut repairs = vec![];
+        let mut chunks: Vec<ChunkRef<'a>> = vec![];
+        let mut i = 8;
+        while i < value.len() {
+            let mut chunk = match ChunkRef::parse(&value[i..]) {
+                Ok(x) => x,
+                Err(_) if options.recover => {
+                    let next = PngRef::resync(value, i + 1, &options.limits);
+                    repairs.push(Repair::SkippedBytes {
+                        offset: i,
+                        length: next - i,
+                    });
+                    i = next;
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let data = chunk.data();
+            options.limits.check_chunk_length(data.len())?;
+            options.limits.check_chunk_count(chunks.len() + 1)?;
+            let is_header = [ChunkType::IHDR, ChunkType::MHDR, ChunkType::JHDR];
+            if is_header.contains(chunk.chunk_type()) && data.len() >= 8 {
+                let width = u32::from_be_bytes(data[0..4].try_into().unwrap());
+                let height = u32::from_be_bytes(data[4..8].try_into().unwrap());
+                options.limits.check_dimensions(width, height)?;
+            }
+            let next = i + chunk.total_length();
+
+            if options.crc != CrcMode::Defer && !chunk.is_crc_valid() {
+                // A bad length field also shows up as a CRC mismatch; only trust the
+                // chunk boundaries if another chunk (or the end of file) follows.
+                if options.recover && !PngRef::is_chunk_boundary(value, next, &options.limits) {
+                    let next = PngRef::resync(value, i + 1, &options.limits);
+                    repairs.push(Repair::SkippedBytes {
+                        offset: i,
+                        length: next - i,
+                    });
+                    i = next;
+                    continue;
+                }
+
+                let index = chunks.len();
+                let chunk_type = chunk.chunk_type().to_string();
+                let stored = chunk.crc();
+                let computed = chunk.computed_crc();
+                match options.crc {
+                    CrcMode::Strict => return Err(PngError::CrcMismatch),
+                    CrcMode::Ignore | CrcMode::Defer => {}
+                    CrcMode::Warn => repairs.push(Repair::CrcMismatch {
+                        index,
+                        chunk_type,
+                        stored,
+                        computed,
+                    }),
+                    CrcMode::Fix => {
+                        chunk.fix_crc();
+                        repairs.push(Repair::CrcFixed {
+                            index,
+                            chunk_type,
+                            stored,
+                            computed,
+                        });
+                    }
+                }
+            }
+
+            let is_end = *chunk.chunk_type() == end;
+            chunks.push(chunk);
+            i = next;
+
+            if is_end && options.recover {
+                if i < value.len() {
+                    repairs.push(Repair::DroppedAfterIend {
+                        offset: i,
+                        length: value.len() - i,
+                    });
+                }
+                break;
+            }
+        }
+
+        if options.recover && chunks.last().is_none_or(|chunk| *chunk.chunk_type() != end) {
+            let crc = match signature {
+                Signature::Mng => PngRef::MEND_CRC,
+                Signature::Png | Signature::Jng => PngRef::IEND_CRC,
+            };
+            chunks.push(ChunkRef::from_parts(end, &[], crc));
+            repairs.push(Repair::InsertedEndChunk(end));
+        }
+
+        let png = PngRef {
+            _signature: signature,
+            _chunks: chunks,
+        };
+        Ok((png, repairs))
+    }
+    fn is_chunk_boundary(value: &[u8], offset: usize, limits: &Limits) -> bool {
+        offset == value.len() || PngRef::is_plausible_header(&value[offset..], limits)
+    }
+    /// Whether `value` starts with a length and chunk type that could belong to a chunk, checked
+    /// before spending time on its CRC.
+    fn is_plausible_header(value: &[u8], limits: &Limits) -> bool {
+        if value.len() < 12 || !value[4..8].iter().all(u8::is_ascii_alphabetic) {
+            return false;
+        }
+        let length = u32::from_be_bytes(value[0..4].try_into().unwrap()) as usize;
+        length <= value.len() - 12 && length <= limits.max_chunk_length
+    }
+    /// Finds the offset of the next intact chunk at or after `from`, or the end of `value`.
+    fn resync(value: &[u8], from: usize, limits: &Limits) -> usize {
+        (from..value.len())
+            .find(|&j| {
+                PngRef::is_plausible_header(&value[j..], limits)
+                    && ChunkRef::try_from(&value[j..]).is_ok()
+            })
+            .unwrap_or(value.len())
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for PngRef<'a> {
+    type Error = PngError;
+
+    fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
+        let (png, _) = PngRef::parse_with(value, ParseOptions::default())?;
+        Ok(png)
+    }
+}
+
+impl std::fmt::Display for PngRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(self.as_bytes().as_slice()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use std::str::FromStr;
+
+    fn testing_bytes() -> Vec<u8> {
+        let chunks = [("FrSt", "I am the first chunk"), ("IEND", "")]
+            .iter()
+            .map(|(chunk_type, data)| {
+                let chunk_type = ChunkType::from_str(chunk_type).unwrap();
+                Chunk::new(chunk_type, data.bytes().collect())
+            })
+            .collect();
+        Png::from_chunks(chunks).as_bytes()
+    }
+
+    #[test]
+    fn test_png_ref_borrows_input() {
+        let bytes = testing_bytes();
+        let png = PngRef::try_from(bytes.as_ref()).unwrap();
+
+        assert_eq!(png.chunks().len(), 2);
+        let chunk = png.chunk_by_type("FrSt").unwrap();
+        assert_eq!(chunk.data_as_str().unwrap(), "I am the first chunk");
+        assert_eq!(chunk.data().as_ptr(), bytes[16..].as_ptr());
+        assert_eq!(png.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_png_ref_to_png() {
+        let bytes = testing_bytes();
+        let png = PngRef::try_from(bytes.as_ref()).unwrap().to_png();
+        assert_eq!(png, Png::try_from(bytes.as_ref()).unwrap());
+    }
+
+    #[test]
+    fn test_png_ref_plausible_header() {
+        let bytes = testing_bytes();
+        let limits = Limits::default();
+        assert!(PngRef::is_plausible_header(&bytes[8..], &limits));
+        assert!(!PngRef::is_plausible_header(&bytes[9..], &limits));
+        assert!(!PngRef::is_plausible_header(&bytes[8..30], &limits));
+        let limits = Limits {
+            max_chunk_length: 4,
+            ..Limits::default()
+        };
+        assert!(!PngRef::is_plausible_header(&bytes[8..], &limits));
+        assert_eq!(PngRef::resync(&bytes, 9, &limits), bytes.len() - 12);
+    }
+
+    #[test]
+    fn test_png_ref_inserted_iend_crc() {
+        assert_eq!(Chunk::new(ChunkType::IEND, vec![]).crc(), PngRef::IEND_CRC);
+        assert_eq!(Chunk::new(ChunkType::MEND, vec![]).crc(), PngRef::MEND_CRC);
+    }
+
+    #[test]
+    fn test_png_ref_mng_and_jng() {
+        let chunk = |chunk_type: ChunkType| Chunk::new(chunk_type, vec![0; 16]);
+        // MNG streams carry embedded PNGs and only end at MEND.
+        let mng = vec![
+            chunk(ChunkType::MHDR),
+            chunk(ChunkType::IHDR),
+            Chunk::new(ChunkType::IEND, vec![]),
+            Chunk::new(ChunkType::MEND, vec![]),
+        ];
+        let jng = vec![
+            chunk(ChunkType::JHDR),
+            chunk(ChunkType::JDAT),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ];
+        let options = ParseOptions {
+            recover: true,
+            ..Default::default()
+        };
+        for (signature, chunks) in [(Signature::Mng, mng), (Signature::Jng, jng)] {
+            let count = chunks.len();
+            let bytes = Png::with_signature(signature, chunks).as_bytes();
+            let (png, repairs) = PngRef::parse_with(&bytes, options).unwrap();
+            assert_eq!(png.signature(), signature);
+            assert_eq!(png.chunks().len(), count);
+            assert!(repairs.is_empty());
+            assert_eq!(png.as_bytes(), bytes);
+            assert_eq!(png.to_png().signature(), signature);
+        }
+
+        let bytes = Png::with_signature(Signature::Mng, vec![chunk(ChunkType::MHDR)]).as_bytes();
+        let (png, repairs) = PngRef::parse_with(&bytes, options).unwrap();
+        assert_eq!(*png.chunks()[1].chunk_type(), ChunkType::MEND);
+        assert_eq!(repairs, vec![Repair::InsertedEndChunk(ChunkType::MEND)]);
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+
+use derive_more::{Display, Error};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::{ChunkType, Placement};
+use crate::diff::longest_increasing;
+use crate::png::{Png, PngError};
+use crate::signature::Signature;
+
+#[derive(PartialEq, Debug, Display, Error)]
+pub enum ReorderError {
+    /// Only PNG files have a canonical order; MNG and JNG streams are left alone.
+    UnsupportedFormat,
+    InvalidIdatSize,
+    InvalidPng(PngError),
+}
+
+impl From<PngError> for ReorderError {
+    fn from(e: PngError) -> Self {
+        ReorderError::InvalidPng(e)
+    }
+}
+
+/// What [`reorder`] should do besides sorting the chunks.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ReorderOptions {
+    /// Rewrite the image data in `IDAT` chunks of at most this many bytes.
+    pub idat_size: Option<usize>,
+    /// Drop ancillary chunks identical to an earlier chunk.
+    pub remove_duplicates: bool,
+}
+
+impl Default for ReorderOptions {
+    fn default() -> Self {
+        ReorderOptions {
+            idat_size: Some(65536),
+            remove_duplicates: true,
+        }
+    }
+}
+
+/// The changes made by [`reorder`].
+#[derive(PartialEq, Debug, Default)]
+pub struct Summary {
+    pub moved: usize,
+    pub duplicates: usize,
+    pub old_idat_count: usize,
+    pub new_idat_count: usize,
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Moved {} chunks", self.moved)?;
+        writeln!(f, "Removed {} duplicate chunks", self.duplicates)?;
+        writeln!(
+            f,
+            "Rewrote {} IDAT chunks as {}",
+            self.old_idat_count, self.new_idat_count
+        )
+    }
+}
+
+/// Rank of a chunk in canonical order. Chunks allowed anywhere stay on their side of the image data.
+fn rank(chunk_type: &ChunkType, after_image_data: bool) -> u8 {
+    match chunk_type.placement() {
+        Placement::Header => 0,
+        Placement::BeforePalette => 1,
+        Placement::Palette => 2,
+        Placement::AfterPalette => 3,
+        Placement::BeforeImageData => 4,
+        Placement::Anywhere if !after_image_data => 4,
+        Placement::ImageData => 5,
+        Placement::Anywhere => 6,
+        Placement::End => 7,
+    }
+}
+
+/// Stably sorts the chunks into the order the specification requires, returning how many moved.
+/// Chunks that keep their order relative to each other count as staying put, so moving one
+/// chunk past many others counts once.
+///
+/// Chunks that may appear anywhere, such as text and private chunks, keep their order and stay
+/// before or after the image data, except that any found between or after `IDAT`s are placed
+/// after the last one, so the image data becomes contiguous.
+pub fn sort_chunks(png: &mut Png) -> usize {
+    let first_image_data = png.position("IDAT").unwrap_or(usize::MAX);
+    let mut ranks: Vec<(u8, usize)> = png
+        .chunks()
+        .iter()
+        .enumerate()
+        .map(|(i, x)| (rank(x.chunk_type(), i > first_image_data), i))
+        .collect();
+    ranks.sort();
+    let order: Vec<usize> = ranks.iter().map(|x| x.1).collect();
+    let moved = longest_increasing(&order).iter().filter(|x| !**x).count();
+
+    let mut chunks: Vec<Option<Chunk>> = png.take_chunks().into_iter().map(Some).collect();
+    for (_, i) in ranks {
+        if let Some(chunk) = chunks[i].take() {
+            png.append_chunk(chunk);
+        }
+    }
+    moved
+}
+
+/// Removes ancillary chunks whose type and data match an earlier chunk, returning how many were removed.
+pub fn remove_duplicates(png: &mut Png) -> usize {
+    let chunks = png.take_chunks();
+    let count = chunks.len();
+    let mut seen: HashSet<(ChunkType, &[u8])> = HashSet::new();
+    let keep: Vec<bool> = chunks
+        .iter()
+        .map(|x| seen.insert((*x.chunk_type(), x.data())) || x.chunk_type().is_critical())
+        .collect();
+    for (chunk, keep) in chunks.into_iter().zip(keep) {
+        if keep {
+            png.append_chunk(chunk);
+        }
+    }
+    count - png.chunks().len()
+}
+
+/// Joins the image data and splits it again into `IDAT` chunks of at most `size` bytes.
+pub fn resize_image_data(png: &mut Png, size: usize) -> Result<(), ReorderError> {
+    if size == 0 || size > i32::MAX as usize {
+        return Err(ReorderError::InvalidIdatSize);
+    }
+    let data = png.image_data();
+    png.set_image_data(data.chunks(size).map(<[u8]>::to_vec).collect());
+    Ok(())
+}
+
+/// Canonicalises the chunk order, drops duplicates and rewrites the image data as `options` ask,
+/// then checks that the result is a valid PNG.
+pub fn reorder(png: &mut Png, options: &ReorderOptions) -> Result<Summary, ReorderError> {
+    if png.signature() != Signature::Png {
+        return Err(ReorderError::UnsupportedFormat);
+    }
+    let idat_count = |png: &Png| {
+        png.chunks()
+            .iter()
+            .filter(|x| *x.chunk_type() == ChunkType::IDAT)
+            .count()
+    };
+    let mut summary = Summary {
+        old_idat_count: idat_count(png),
+        ..Default::default()
+    };
+    summary.moved = sort_chunks(png);
+    if options.remove_duplicates {
+        summary.duplicates = remove_duplicates(png);
+    }
+    if let Some(size) = options.idat_size {
+        resize_image_data(png, size)?;
+    }
+    summary.new_idat_count = idat_count(png);
+    png.validate()?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{chunk, chunk_types, testing_png};
+
+    #[test]
+    fn test_sort_chunks() {
+        let mut png = Png::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            chunk("tEXt", b"a\0b"),
+            chunk("pHYs", &[0; 9]),
+            chunk("PLTE", &[0; 3]),
+            chunk("gAMA", &[0; 4]),
+            chunk("IDAT", b"12"),
+            chunk("ruSt", b"x"),
+            chunk("IDAT", b"34"),
+            chunk("tRNS", &[0]),
+            chunk("IEND", &[]),
+        ]);
+        // gAMA, PLTE, tRNS and the second IDAT move; the rest keep their relative order.
+        assert_eq!(sort_chunks(&mut png), 4);
+        assert_eq!(
+            chunk_types(&png),
+            ["IHDR", "gAMA", "PLTE", "tRNS", "tEXt", "pHYs", "IDAT", "IDAT", "ruSt", "IEND"]
+        );
+        assert_eq!(sort_chunks(&mut png), 0);
+    }
+
+    #[test]
+    fn test_remove_duplicates() {
+        let mut png = Png::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            chunk("tEXt", b"a\0b"),
+            chunk("IDAT", b"12"),
+            chunk("IDAT", b"12"),
+            chunk("tEXt", b"a\0b"),
+            chunk("tEXt", b"a\0c"),
+            chunk("IEND", &[]),
+        ]);
+        assert_eq!(remove_duplicates(&mut png), 1);
+        assert_eq!(
+            chunk_types(&png),
+            ["IHDR", "tEXt", "IDAT", "IDAT", "tEXt", "IEND"]
+        );
+    }
+
+    #[test]
+    fn test_reorder() {
+        let mut png = testing_png();
+        let image_data = png.image_data();
+        png.insert_chunk(1, chunk("tEXt", b"Title\0Dice"));
+        png.insert_chunk(png.chunks().len() - 1, chunk("tEXt", b"Title\0Dice"));
+        let options = ReorderOptions {
+            idat_size: Some(1000),
+            remove_duplicates: true,
+        };
+        let summary = reorder(&mut png, &options).unwrap();
+        assert_eq!(summary.duplicates, 1);
+        assert_eq!(summary.old_idat_count, 1);
+        assert_eq!(summary.new_idat_count, image_data.len().div_ceil(1000));
+        assert_eq!(png.image_data(), image_data);
+        assert_eq!(png.chunks()[1].chunk_type().to_string(), "sRGB");
+
+        let summary = reorder(&mut png, &Default::default()).unwrap();
+        assert_eq!(summary.new_idat_count, 1);
+        assert_eq!(png.image_data(), image_data);
+        assert_eq!(
+            resize_image_data(&mut png, 0),
+            Err(ReorderError::InvalidIdatSize)
+        );
+
+        let mut mng = Png::with_signature(Signature::Mng, vec![]);
+        assert_eq!(
+            reorder(&mut mng, &options),
+            Err(ReorderError::UnsupportedFormat)
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt::Display;
+
+use crate::chunk_type::ChunkType;
+
+/// The 8-byte signature that starts a PNG-family file, which share the same chunk layout.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Signature {
+    #[default]
+    Png,
+    /// Multiple-image Network Graphics, from `MHDR` to `MEND`.
+    Mng,
+    /// JPEG Network Graphics, from `JHDR` to `IEND`.
+    Jng,
+}
+
+impl Signature {
+    pub const PNG: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+    pub const MNG: [u8; 8] = [138, 77, 78, 71, 13, 10, 26, 10];
+    pub const JNG: [u8; 8] = [139, 74, 78, 71, 13, 10, 26, 10];
+
+    /// Recognises the signature at the start of `value`.
+    pub fn detect(value: &[u8]) -> Option<Signature> {
+        match value.get(0..8)? {
+            x if x == Signature::PNG => Some(Signature::Png),
+            x if x == Signature::MNG => Some(Signature::Mng),
+            x if x == Signature::JNG => Some(Signature::Jng),
+            _ => None,
+        }
+    }
+    pub fn bytes(&self) -> &'static [u8; 8] {
+        match self {
+            Signature::Png => &Signature::PNG,
+            Signature::Mng => &Signature::MNG,
+            Signature::Jng => &Signature::JNG,
+        }
+    }
+    /// The chunk that must come first.
+    pub fn header_chunk(&self) -> ChunkType {
+        match self {
+            Signature::Png => ChunkType::IHDR,
+            Signature::Mng => ChunkType::MHDR,
+            Signature::Jng => ChunkType::JHDR,
+        }
+    }
+    /// The chunk that ends the stream; MNG files may contain embedded `IEND`s before it.
+    pub fn end_chunk(&self) -> ChunkType {
+        match self {
+            Signature::Mng => ChunkType::MEND,
+            Signature::Png | Signature::Jng => ChunkType::IEND,
+        }
+    }
+}
+
+impl Display for Signature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Signature::Png => write!(f, "PNG"),
+            Signature::Mng => write!(f, "MNG"),
+            Signature::Jng => write!(f, "JNG"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        for signature in [Signature::Png, Signature::Mng, Signature::Jng] {
+            assert_eq!(Signature::detect(signature.bytes()), Some(signature));
+        }
+        assert_eq!(Signature::detect(&Signature::PNG[..7]), None);
+        assert_eq!(Signature::detect(b"GIF89a\0\0"), None);
+        assert_eq!(Signature::Mng.end_chunk(), ChunkType::MEND);
+        assert_eq!(Signature::Jng.header_chunk(), ChunkType::JHDR);
+    }
+}
@@ -0,0 +1,24 @@
+//! Helpers shared by the unit tests.
+
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+
+pub fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+    Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+}
+
+/// The `tests/fixtures/dice.png` image.
+pub fn testing_png() -> Png {
+    let bytes = std::fs::read("tests/fixtures/dice.png").unwrap();
+    Png::try_from(bytes.as_ref()).unwrap()
+}
+
+pub fn chunk_types(png: &Png) -> Vec<String> {
+    png.chunks()
+        .iter()
+        .map(|x| x.chunk_type().to_string())
+        .collect()
+}
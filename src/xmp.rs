@@ -0,0 +1,373 @@
+use std::collections::HashSet;
+
+use derive_more::{Display, Error};
+
+/// The `iTXt` keyword XMP packets are stored under.
+pub const XMP_KEYWORD: &str = "XML:com.adobe.xmp";
+
+#[derive(PartialEq, Debug, Display, Error)]
+pub enum XmpError {
+    MissingRdf,
+    UnclosedDescription,
+    InvalidTag,
+}
+
+const OPEN: &str = "<rdf:Description";
+const CLOSE: &str = "</rdf:Description>";
+const RDF_CLOSE: &str = "</rdf:RDF>";
+
+/// A start or empty-element tag. Attribute ranges include the whitespace before the attribute.
+struct Tag<'a> {
+    name: &'a str,
+    attributes: Vec<(&'a str, &'a str, usize, usize)>,
+    end: usize,
+    empty: bool,
+}
+
+impl<'a> Tag<'a> {
+    /// Parses the tag starting with the `<` at `start`.
+    fn parse(xml: &'a str, start: usize) -> Result<Tag<'a>, XmpError> {
+        let is_name_end = |c: char| c.is_whitespace() || c == '/' || c == '>';
+        let name_end = xml[start + 1..]
+            .find(is_name_end)
+            .map(|x| x + start + 1)
+            .ok_or(XmpError::InvalidTag)?;
+        let mut tag = Tag {
+            name: &xml[start + 1..name_end],
+            attributes: vec![],
+            end: 0,
+            empty: false,
+        };
+        let mut pos = name_end;
+        loop {
+            let rest = &xml[pos..];
+            let next = pos + rest.len() - rest.trim_start().len();
+            if xml[next..].starts_with("/>") {
+                (tag.end, tag.empty) = (next + 2, true);
+                return Ok(tag);
+            }
+            if xml[next..].starts_with('>') {
+                tag.end = next + 1;
+                return Ok(tag);
+            }
+            let (name, rest) = xml[next..].split_once('=').ok_or(XmpError::InvalidTag)?;
+            let rest = rest.trim_start();
+            let quote = rest.chars().next().filter(|x| *x == '"' || *x == '\'');
+            let quote = quote.ok_or(XmpError::InvalidTag)?;
+            let value_start = xml.len() - rest.len() + 1;
+            let value_end = xml[value_start..]
+                .find(quote)
+                .map(|x| x + value_start)
+                .ok_or(XmpError::InvalidTag)?;
+            let name = name.trim_end();
+            if name.is_empty() || name.contains(is_name_end) {
+                return Err(XmpError::InvalidTag);
+            }
+            let value = &xml[value_start..value_end];
+            tag.attributes.push((name, value, pos, value_end + 1));
+            pos = value_end + 1;
+        }
+    }
+    /// The `xmlns:prefix` declarations of this tag.
+    fn declarations(&self) -> Vec<(&'a str, &'a str)> {
+        self.attributes
+            .iter()
+            .filter_map(|(name, value, ..)| Some((name.strip_prefix("xmlns:")?, *value)))
+            .collect()
+    }
+}
+
+/// Offsets of the start tags (`Some`) and end tags (`None`) in `from..to`, skipping comments and
+/// processing instructions.
+fn tags(xml: &str, from: usize, to: usize) -> Result<Vec<(usize, Option<Tag<'_>>)>, XmpError> {
+    let mut tags = vec![];
+    let mut pos = from;
+    while let Some(start) = xml[pos..to].find('<').map(|x| x + pos) {
+        let skip_to = |end: &str| {
+            xml[start..]
+                .find(end)
+                .map(|x| x + start + end.len())
+                .ok_or(XmpError::InvalidTag)
+        };
+        pos = if xml[start..].starts_with("<!--") {
+            skip_to("-->")?
+        } else if xml[start..].starts_with("<?") {
+            skip_to("?>")?
+        } else if xml[start..].starts_with("</") {
+            tags.push((start, None));
+            skip_to(">")?
+        } else {
+            let tag = Tag::parse(xml, start)?;
+            let end = tag.end;
+            tags.push((start, Some(tag)));
+            end
+        };
+    }
+    Ok(tags)
+}
+
+/// The namespace URI and local name of `name`, resolving its prefix with `scopes` in order.
+fn resolve<'a>(name: &'a str, scopes: &[&[(&'a str, &'a str)]]) -> (&'a str, &'a str) {
+    match name.split_once(':') {
+        Some((prefix, local)) => {
+            let uri = scopes
+                .iter()
+                .find_map(|x| x.iter().find(|(p, _)| *p == prefix))
+                .map_or(prefix, |(_, uri)| *uri);
+            (uri, local)
+        }
+        None => ("", name),
+    }
+}
+
+/// Namespace declarations made by the elements enclosing the first description of `packet`.
+fn outer_declarations(packet: &str) -> Result<Vec<(&str, &str)>, XmpError> {
+    let first = packet.find(OPEN).unwrap_or(packet.len());
+    Ok(tags(packet, 0, first)?
+        .iter()
+        .filter_map(|(_, tag)| tag.as_ref())
+        .flat_map(Tag::declarations)
+        .collect())
+}
+
+/// The properties of the description ending at `end` whose start tag is `tag`: each name with the
+/// byte range to remove it.
+fn properties<'a>(
+    xml: &'a str,
+    end: usize,
+    tag: &Tag<'a>,
+) -> Result<Vec<(&'a str, usize, usize)>, XmpError> {
+    let mut properties: Vec<_> = tag
+        .attributes
+        .iter()
+        .filter(|(name, ..)| !name.starts_with("xmlns") && !name.starts_with("rdf:"))
+        .map(|(name, _, from, to)| (*name, *from, *to))
+        .collect();
+    if tag.empty {
+        return Ok(properties);
+    }
+    let mut depth = 0;
+    let mut child = None;
+    for (pos, inner) in tags(xml, tag.end, end - CLOSE.len())? {
+        match inner {
+            Some(inner) if inner.empty => {
+                if depth == 0 {
+                    properties.push((inner.name, pos, inner.end));
+                }
+            }
+            Some(inner) => {
+                if depth == 0 {
+                    child = Some((inner.name, pos));
+                }
+                depth += 1;
+            }
+            None => {
+                depth -= 1;
+                if depth > 0 {
+                    continue;
+                }
+                if let Some((name, from)) = child.take() {
+                    let to = xml[pos..].find('>').map(|x| x + pos + 1);
+                    properties.push((name, from, to.ok_or(XmpError::InvalidTag)?));
+                }
+            }
+        }
+    }
+    Ok(properties)
+}
+
+/// Byte ranges of the top-level `rdf:Description` elements in `packet`.
+fn descriptions(packet: &str) -> Result<Vec<(usize, usize)>, XmpError> {
+    let mut ranges = vec![];
+    let mut pos = 0;
+    while let Some(start) = packet[pos..].find(OPEN).map(|x| x + pos) {
+        let tag_end = packet[start..]
+            .find('>')
+            .map(|x| x + start)
+            .ok_or(XmpError::UnclosedDescription)?;
+        if packet[..tag_end].ends_with('/') {
+            ranges.push((start, tag_end + 1));
+            pos = tag_end + 1;
+            continue;
+        }
+        // Descriptions can nest inside structured properties, so track the depth.
+        let mut depth = 1;
+        pos = tag_end + 1;
+        while depth > 0 {
+            let open = packet[pos..].find(OPEN).map(|x| x + pos);
+            let close = packet[pos..]
+                .find(CLOSE)
+                .map(|x| x + pos)
+                .ok_or(XmpError::UnclosedDescription)?;
+            match open {
+                Some(open) if open < close => {
+                    let end = packet[open..]
+                        .find('>')
+                        .map(|x| x + open)
+                        .ok_or(XmpError::UnclosedDescription)?;
+                    if !packet[..end].ends_with('/') {
+                        depth += 1;
+                    }
+                    pos = end + 1;
+                }
+                _ => {
+                    depth -= 1;
+                    pos = close + CLOSE.len();
+                }
+            }
+        }
+        ranges.push((start, pos));
+    }
+    Ok(ranges)
+}
+
+/// Adds the `rdf:Description` elements of `other` to the `rdf:RDF` element of `packet`, removing
+/// the properties of `packet` that `other` sets again.
+///
+/// Namespaces that `other` declares on its enclosing elements are declared on the inserted
+/// descriptions, unless `packet` already binds the prefix to the same URI.
+pub fn merge(packet: &str, other: &str) -> Result<String, XmpError> {
+    let insert_at = packet.rfind(RDF_CLOSE).ok_or(XmpError::MissingRdf)?;
+    if !other.contains(RDF_CLOSE) {
+        return Err(XmpError::MissingRdf);
+    }
+    let packet_outer = outer_declarations(packet)?;
+    let other_outer = outer_declarations(other)?;
+
+    let mut replaced = HashSet::new();
+    let mut inserted = String::new();
+    for (start, end) in descriptions(other)? {
+        let tag = Tag::parse(other, start)?;
+        let own = tag.declarations();
+        for (name, ..) in properties(other, end, &tag)? {
+            replaced.insert(resolve(name, &[&own, &other_outer]));
+        }
+
+        let mut used = HashSet::new();
+        for (_, inner) in tags(other, start, end)? {
+            let Some(inner) = inner else { continue };
+            let names = inner.attributes.iter().map(|(name, ..)| *name);
+            for name in names.chain([inner.name]) {
+                if let Some((prefix, _)) = name.split_once(':') {
+                    used.insert(prefix);
+                }
+            }
+        }
+        let mut declarations = String::new();
+        for (prefix, uri) in other_outer.iter() {
+            let declared = own.iter().any(|(p, _)| p == prefix)
+                || packet_outer.iter().any(|x| x == &(*prefix, *uri));
+            if used.contains(prefix) && !declared {
+                declarations += &format!(" xmlns:{}=\"{}\"", prefix, uri);
+            }
+        }
+        inserted += OPEN;
+        inserted += &declarations;
+        inserted += &other[start + OPEN.len()..end];
+        inserted += "\n";
+    }
+
+    let mut removed = vec![];
+    for (start, end) in descriptions(packet)? {
+        let tag = Tag::parse(packet, start)?;
+        let own = tag.declarations();
+        for (name, from, to) in properties(packet, end, &tag)? {
+            if replaced.contains(&resolve(name, &[&own, &packet_outer])) {
+                removed.push((from, to));
+            }
+        }
+    }
+    removed.sort();
+
+    let mut merged = String::new();
+    let mut pos = 0;
+    for (from, to) in removed {
+        merged += &packet[pos..from];
+        pos = to;
+    }
+    merged += &packet[pos..insert_at];
+    merged += &inserted;
+    merged += &packet[insert_at..];
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(body: &str) -> String {
+        format!(
+            "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">{}</rdf:RDF></x:xmpmeta>",
+            body
+        )
+    }
+
+    #[test]
+    fn test_descriptions() {
+        let body = "<rdf:Description rdf:about=\"\" a=\"1\"/><rdf:Description rdf:about=\"\"><p rdf:parseType=\"Resource\"><rdf:Description/><rdf:Description><q/></rdf:Description></p></rdf:Description>";
+        let xmp = packet(body);
+        let ranges = descriptions(&xmp).unwrap();
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(
+            &xmp[ranges[0].0..ranges[0].1],
+            "<rdf:Description rdf:about=\"\" a=\"1\"/>"
+        );
+        assert!(xmp[ranges[1].0..ranges[1].1].ends_with("</p></rdf:Description>"));
+
+        assert_eq!(
+            descriptions(&packet("<rdf:Description><a/>")),
+            Err(XmpError::UnclosedDescription)
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let base = packet("<rdf:Description rdf:about=\"\" xmp:Rating=\"3\"/>");
+        let other =
+            packet("<rdf:Description rdf:about=\"\"><dc:title>Dice</dc:title></rdf:Description>");
+        let merged = merge(&base, &other).unwrap();
+        assert_eq!(
+            merged,
+            packet("<rdf:Description rdf:about=\"\" xmp:Rating=\"3\"/><rdf:Description rdf:about=\"\"><dc:title>Dice</dc:title></rdf:Description>\n")
+        );
+        assert_eq!(merge("<x/>", &other), Err(XmpError::MissingRdf));
+        assert_eq!(
+            merge(&base, &packet("<rdf:Description a=1/>")),
+            Err(XmpError::InvalidTag)
+        );
+        assert_eq!(merge(&base, "<x/>"), Err(XmpError::MissingRdf));
+    }
+
+    #[test]
+    fn test_merge_namespaces() {
+        const RDF: &str = "xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\"";
+        const XMP: &str = "http://ns.adobe.com/xap/1.0/";
+        const DC: &str = "http://purl.org/dc/elements/1.1/";
+        let base = format!(
+            "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF {} xmlns:xmp=\"{}\" xmlns:dc=\"{}\">\
+             <rdf:Description rdf:about=\"\" xmp:Rating=\"3\" xmp:Label=\"Red\">\
+             <dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">Old</rdf:li></rdf:Alt></dc:title>\
+             <dc:format/></rdf:Description></rdf:RDF></x:xmpmeta>",
+            RDF, XMP, DC
+        );
+        // Declared on the outer elements, with a different prefix for the XMP namespace.
+        let other = format!(
+            "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\" xmlns:dc=\"{}\"><rdf:RDF {} xmlns:xap=\"{}\">\
+             <rdf:Description rdf:about=\"\" xap:Rating=\"5\"><dc:title>Dice</dc:title>\
+             </rdf:Description></rdf:RDF></x:xmpmeta>",
+            DC, RDF, XMP
+        );
+        let merged = merge(&base, &other).unwrap();
+        assert_eq!(
+            merged,
+            format!(
+                "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF {} xmlns:xmp=\"{}\" xmlns:dc=\"{}\">\
+                 <rdf:Description rdf:about=\"\" xmp:Label=\"Red\">\
+                 <dc:format/></rdf:Description>\
+                 <rdf:Description xmlns:xap=\"{}\" rdf:about=\"\" xap:Rating=\"5\"><dc:title>Dice</dc:title>\
+                 </rdf:Description>\n</rdf:RDF></x:xmpmeta>",
+                RDF, XMP, DC, XMP
+            )
+        );
+    }
+}
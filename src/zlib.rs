@@ -0,0 +1,80 @@
+use miniz_oxide::inflate::{
+    decompress_to_vec_with_limit, decompress_to_vec_zlib_with_limit, DecompressError, TINFLStatus,
+};
+
+use crate::limits::Limits;
+use crate::png::PngError;
+
+/// Compression level used when pngme writes compressed data.
+pub const DEFAULT_LEVEL: u8 = 6;
+/// Highest level accepted by [`deflate_with_level`], trading speed for size.
+pub const MAX_LEVEL: u8 = 10;
+
+/// Inflates a zlib stream, refusing to produce more than `limits.max_decompressed_size` bytes.
+pub fn inflate(data: &[u8], limits: &Limits) -> Result<Vec<u8>, PngError> {
+    decompress_to_vec_zlib_with_limit(data, limits.max_decompressed_size).map_err(inflate_error)
+}
+
+/// Inflates a raw deflate stream without the zlib header and checksum, as found in CgBI files.
+pub fn inflate_raw(data: &[u8], limits: &Limits) -> Result<Vec<u8>, PngError> {
+    decompress_to_vec_with_limit(data, limits.max_decompressed_size).map_err(inflate_error)
+}
+
+fn inflate_error(e: DecompressError) -> PngError {
+    if e.status == TINFLStatus::HasMoreOutput {
+        PngError::DecompressedTooLarge
+    } else {
+        PngError::InvalidCompressedData
+    }
+}
+
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    deflate_with_level(data, DEFAULT_LEVEL)
+}
+
+/// Compresses `data` into a zlib stream at `level`, from 0 (stored) to [`MAX_LEVEL`].
+pub fn deflate_with_level(data: &[u8], level: u8) -> Vec<u8> {
+    miniz_oxide::deflate::compress_to_vec_zlib(data, level.min(MAX_LEVEL))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let data = b"an ICC profile, or any other data".repeat(10);
+        let compressed = deflate(&data);
+        assert!(compressed.len() < data.len());
+        assert_eq!(inflate(&compressed, &Limits::default()), Ok(data.clone()));
+        for level in 0..=MAX_LEVEL {
+            let compressed = deflate_with_level(&data, level);
+            assert_eq!(inflate(&compressed, &Limits::default()), Ok(data.clone()));
+        }
+    }
+
+    #[test]
+    fn test_inflate_raw() {
+        let data = b"raw deflate".repeat(10);
+        let compressed = miniz_oxide::deflate::compress_to_vec(&data, DEFAULT_LEVEL);
+        assert_eq!(inflate_raw(&compressed, &Limits::default()), Ok(data));
+        assert!(inflate(&compressed, &Limits::default()).is_err());
+    }
+
+    #[test]
+    fn test_inflate_errors() {
+        let compressed = deflate(&[0; 1000]);
+        let limits = Limits {
+            max_decompressed_size: 999,
+            ..Limits::default()
+        };
+        assert_eq!(
+            inflate(&compressed, &limits),
+            Err(PngError::DecompressedTooLarge)
+        );
+        assert_eq!(
+            inflate(&compressed[..4], &Limits::default()),
+            Err(PngError::InvalidCompressedData)
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use pngme::chunk::Chunk;
+use pngme::chunk_type::ChunkType;
+use pngme::ihdr::IhdrError;
+use pngme::png::{Png, PngError};
+
+const EXPECTED_ERRORS: &[(&str, PngError)] = &[
+    ("xs1n0g01.png", PngError::InvalidHeader),
+    ("xs2n0g01.png", PngError::InvalidHeader),
+    ("xs4n0g01.png", PngError::InvalidHeader),
+    ("xs7n0g01.png", PngError::InvalidHeader),
+    ("xcrn0g04.png", PngError::InvalidHeader),
+    ("xlfn0g04.png", PngError::InvalidHeader),
+    ("xhdn0g08.png", PngError::CrcMismatch),
+    ("xcsn0g01.png", PngError::CrcMismatch),
+    (
+        "xc1n0g08.png",
+        PngError::InvalidIhdr(IhdrError::InvalidColourType),
+    ),
+    (
+        "xc9n2c08.png",
+        PngError::InvalidIhdr(IhdrError::InvalidColourType),
+    ),
+    (
+        "xd0n2c08.png",
+        PngError::InvalidIhdr(IhdrError::InvalidBitDepth),
+    ),
+    (
+        "xd3n2c08.png",
+        PngError::InvalidIhdr(IhdrError::InvalidBitDepth),
+    ),
+    (
+        "xd9n2c08.png",
+        PngError::InvalidIhdr(IhdrError::InvalidBitDepth),
+    ),
+    ("xdtn0g01.png", PngError::MissingIdat),
+];
+
+type Corruption = fn(Vec<u8>) -> Vec<u8>;
+
+/// The corruption PngSuite documents for each `x` file, applied to `dice.png`.
+const CORRUPTIONS: &[(&str, Corruption)] = &[
+    ("xs1n0g01.png", |x| set_byte(x, 0, 0x09)),
+    ("xs2n0g01.png", |x| set_byte(x, 1, b'Q')),
+    ("xs4n0g01.png", |x| set_byte(x, 3, b'g')),
+    ("xs7n0g01.png", |x| set_byte(x, 6, b' ')),
+    ("xcrn0g04.png", |x| replace_bytes(&x, b'\n', b"\r\n")),
+    ("xlfn0g04.png", |x| replace_bytes(&x, b'\r', b"\r\n")),
+    ("xhdn0g08.png", |x| corrupt_crc(x, &ChunkType::IHDR)),
+    ("xcsn0g01.png", |x| corrupt_crc(x, &ChunkType::IDAT)),
+    ("xc1n0g08.png", |x| edit_ihdr(x, 9, 1)),
+    ("xc9n2c08.png", |x| edit_ihdr(x, 9, 9)),
+    ("xd0n2c08.png", |x| edit_ihdr(x, 8, 0)),
+    ("xd3n2c08.png", |x| edit_ihdr(x, 8, 3)),
+    ("xd9n2c08.png", |x| edit_ihdr(x, 8, 99)),
+    ("xdtn0g01.png", |x| {
+        let mut png = Png::try_from(x.as_ref()).unwrap();
+        png.remove_chunks_by_type(&ChunkType::IDAT);
+        png.as_bytes()
+    }),
+];
+
+fn set_byte(mut contents: Vec<u8>, index: usize, value: u8) -> Vec<u8> {
+    contents[index] = value;
+    contents
+}
+
+fn replace_bytes(contents: &[u8], from: u8, to: &[u8]) -> Vec<u8> {
+    contents
+        .iter()
+        .flat_map(|&x| if x == from { to.to_vec() } else { vec![x] })
+        .collect()
+}
+
+/// Flips a bit in the CRC of the first chunk of `chunk_type`.
+fn corrupt_crc(mut contents: Vec<u8>, chunk_type: &ChunkType) -> Vec<u8> {
+    let png = Png::try_from(contents.as_ref()).unwrap();
+    let mut end = Png::STANDARD_HEADER.len();
+    for chunk in png.chunks() {
+        end += chunk.as_bytes().len();
+        if chunk.chunk_type() == chunk_type {
+            break;
+        }
+    }
+    contents[end - 1] ^= 1;
+    contents
+}
+
+/// Changes one byte of the IHDR data, keeping its CRC valid.
+fn edit_ihdr(contents: Vec<u8>, index: usize, value: u8) -> Vec<u8> {
+    let png = Png::try_from(contents.as_ref()).unwrap();
+    let mut chunks: Vec<Chunk> = png
+        .chunks()
+        .iter()
+        .map(|x| Chunk::new(*x.chunk_type(), x.data().to_vec()))
+        .collect();
+    let mut data = chunks[0].data().to_vec();
+    data[index] = value;
+    chunks[0] = Chunk::new(ChunkType::IHDR, data);
+    Png::from_chunks(chunks).as_bytes()
+}
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+fn pngsuite_files() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = match fs::read_dir(fixtures_dir().join("pngsuite")) {
+        Ok(entries) => entries
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+            .collect(),
+        Err(_) => vec![],
+    };
+    files.sort();
+    assert!(
+        !files.is_empty(),
+        "tests/fixtures/pngsuite is empty; unpack PngSuite-2017jul19.tgz into it"
+    );
+    files
+}
+
+fn is_corrupt(path: &Path) -> bool {
+    path.file_name().unwrap().to_string_lossy().starts_with('x')
+}
+
+fn parse_and_validate(contents: &[u8]) -> Result<Png, PngError> {
+    let png = Png::try_from(contents)?;
+    png.validate()?;
+    Ok(png)
+}
+
+fn assert_round_trips(path: &Path) {
+    let contents = fs::read(path).unwrap();
+    let png = parse_and_validate(contents.as_ref())
+        .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    assert_eq!(png.as_bytes(), contents, "{}", path.display());
+}
+
+#[test]
+fn dice_round_trips() {
+    assert_round_trips(&fixtures_dir().join("dice.png"));
+}
+
+#[test]
+fn documented_corruptions_are_rejected() {
+    let contents = fs::read(fixtures_dir().join("dice.png")).unwrap();
+    for (name, expected) in EXPECTED_ERRORS {
+        let corrupt = CORRUPTIONS
+            .iter()
+            .find(|(file, _)| file == name)
+            .map(|(_, corrupt)| corrupt)
+            .unwrap_or_else(|| panic!("{}: no corruption listed", name));
+        let actual = parse_and_validate(&corrupt(contents.clone())).err();
+        assert_eq!(actual.as_ref(), Some(expected), "{}", name);
+    }
+}
+
+#[test]
+#[ignore = "needs PngSuite-2017jul19 unpacked into tests/fixtures/pngsuite"]
+fn valid_images_round_trip() {
+    let files = pngsuite_files()
+        .into_iter()
+        .filter(|path| !is_corrupt(path));
+    for path in files {
+        assert_round_trips(&path);
+    }
+}
+
+#[test]
+#[ignore = "needs PngSuite-2017jul19 unpacked into tests/fixtures/pngsuite"]
+fn corrupt_images_are_rejected() {
+    let files = pngsuite_files();
+    for (name, _) in EXPECTED_ERRORS {
+        assert!(
+            files.iter().any(|x| x.file_name().unwrap() == *name),
+            "{}: missing from the corpus",
+            name
+        );
+    }
+    for path in files.into_iter().filter(|path| is_corrupt(path)) {
+        let name = path.file_name().unwrap().to_string_lossy();
+        let expected = EXPECTED_ERRORS
+            .iter()
+            .find(|(file, _)| *file == name)
+            .map(|(_, error)| error)
+            .unwrap_or_else(|| panic!("{}: no expected error listed", name));
+
+        let contents = fs::read(&path).unwrap();
+        let actual = parse_and_validate(contents.as_ref()).err();
+        assert_eq!(actual.as_ref(), Some(expected), "{}", name);
+    }
+}
@@ -0,0 +1,70 @@
+use pngme::chunk::Chunk;
+use pngme::chunk_type::ChunkType;
+use pngme::png::{ParseOptions, Png};
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+fn chunk_type_strategy() -> impl Strategy<Value = ChunkType> {
+    let letter = prop_oneof![b'A'..=b'Z', b'a'..=b'z'];
+    [letter.clone(), letter.clone(), letter.clone(), letter]
+        .prop_map(|bytes| ChunkType::try_from(bytes).unwrap())
+}
+
+fn chunk_strategy() -> impl Strategy<Value = Chunk> {
+    (chunk_type_strategy(), vec(any::<u8>(), 0..512))
+        .prop_map(|(chunk_type, data)| Chunk::new(chunk_type, data))
+}
+
+fn png_strategy() -> impl Strategy<Value = Png> {
+    vec(chunk_strategy(), 0..16).prop_map(Png::from_chunks)
+}
+
+proptest! {
+    #[test]
+    fn chunk_type_round_trips(chunk_type in chunk_type_strategy()) {
+        let parsed = ChunkType::try_from(chunk_type.bytes()).unwrap();
+        prop_assert_eq!(&parsed, &chunk_type);
+        prop_assert_eq!(parsed.to_string().parse::<ChunkType>().unwrap(), chunk_type);
+    }
+
+    #[test]
+    fn chunk_round_trips(chunk in chunk_strategy()) {
+        let parsed = Chunk::try_from(chunk.as_bytes().as_ref()).unwrap();
+        prop_assert_eq!(parsed, chunk);
+    }
+
+    #[test]
+    fn png_round_trips(png in png_strategy()) {
+        let bytes = png.as_bytes();
+        let parsed = Png::try_from(bytes.as_ref()).unwrap();
+        prop_assert_eq!(parsed.as_bytes(), bytes);
+        prop_assert_eq!(parsed, png);
+    }
+
+    #[test]
+    fn png_parse_never_panics(bytes in vec(any::<u8>(), 0..1024)) {
+        let _ = Png::try_from(bytes.as_ref());
+        let _ = Png::parse_with(bytes.as_ref(), ParseOptions::repair());
+    }
+
+    #[test]
+    fn png_parse_never_panics_on_mutated_input(
+        png in png_strategy(),
+        flips in vec((any::<prop::sample::Index>(), any::<u8>()), 1..8),
+    ) {
+        let mut bytes = png.as_bytes();
+        for (index, value) in flips {
+            let i = index.index(bytes.len());
+            bytes[i] ^= value;
+        }
+        let _ = Png::try_from(bytes.as_ref());
+        if let Ok((repaired, _)) = Png::parse_with(bytes.as_ref(), ParseOptions::repair()) {
+            prop_assert!(repaired.chunks().iter().all(|chunk| chunk.is_crc_valid()));
+        }
+    }
+
+    #[test]
+    fn chunk_parse_never_panics(bytes in vec(any::<u8>(), 0..64)) {
+        let _ = Chunk::try_from(bytes.as_ref());
+    }
+}